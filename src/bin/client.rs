@@ -5,13 +5,13 @@ use bevy::prelude::*;
 use bevy::app::{ScheduleRunnerSettings};
 use bevy_rapier3d::physics::{RapierPhysicsPlugin, RigidBodyHandleComponent};
 use bevy_rapier3d::rapier::dynamics::{BodyStatus, RigidBody, RigidBodyBuilder};
-use bevy_rapier3d::rapier::geometry::ColliderBuilder;
+use bevy_rapier3d::rapier::geometry::{ColliderBuilder, InteractionGroups};
 use bevy_prototype_networking_laminar::{NetworkResource, NetworkingPlugin, NetworkDelivery};
-use bevy_fly_camera::{FlyCamera, FlyCameraPlugin};
 
 use craft::components::*;
 use craft::events::*;
 use craft::models::*;
+use craft::render::*;
 use craft::resources::*;
 use craft::systems::*;
 
@@ -33,18 +33,35 @@ fn main() {
         .add_plugins(DefaultPlugins)
         .add_plugin(RapierPhysicsPlugin)
         .add_plugin(NetworkingPlugin)
+        .add_plugin(TerrainRenderPlugin)
         .add_event::<CommandFrameEvent>()
         .add_event::<StateFrameEvent>()
         .add_event::<EntitySpawnEvent>()
+        .add_event::<VoxelEditRequestEvent>()
+        .add_event::<VoxelEditEvent>()
+        .add_event::<VoxelEditRejectedEvent>()
+        .add_event::<ChunkTransferEvent>()
         .add_resource(client)
         .add_resource(SimulationTime::new(60))
-        .add_resource(WorldGenerator::new(16))
+        // Replaced by the server's world once we're authorized
+        .add_resource(WorldGenerator::load_or_default(0, TERRAIN_GRAPH_PATH, CAVE_SETTINGS_PATH))
+        .add_resource(VoxelWorld::tracking_changes())
+        .init_resource::<ChunkStreamingSettings>()
+        .init_resource::<ChunkLoadingState>()
+        .init_resource::<ChunkMeshingState>()
+        .init_resource::<PendingVoxelWrites>()
+        .init_resource::<ChunkCache>()
+        .init_resource::<ClientChunkTransferState>()
+        .init_resource::<VoxelEditSettings>()
+        .init_resource::<VoxelEditPrediction>()
+        .add_resource(InputMap::load_or_default("input.ron"))
         .init_resource::<NetworkEventListenerState>()
         .init_resource::<Clients>()
+        .init_resource::<ReplayRecorder>()
         .init_resource::<CommandAccumulatorState>()
         .init_resource::<LocalPlayerCameraState>()
         .init_resource::<LocalPlayerMovementState>()
-        .init_resource::<PlayerMovementState>()
+        .init_resource::<VoxelEditingState>()
         .add_startup_system(setup.system())
         .add_system_to_stage(stage::PRE_UPDATE, client_entity_spawning_system.system())
         .add_system(simulation_time_system.system())
@@ -53,10 +70,16 @@ fn main() {
         .add_system(local_player_movement_system.system())
         .add_system(player_movement_system.system())
         .add_system(network_message_listener_system.system())
+        .add_system(client_chunk_transfer_system.system())
+        .add_system(voxel_editing_system.system())
+        .add_system(client_voxel_edit_system.system())
         .add_system(client_authoratative_state_consumption_system::<RigidBodyHandleComponent>.system())
+        .add_system(client_authoratative_state_consumption_system::<Player>.system())
+        .add_system(client_authoratative_state_consumption_system::<CharacterController>.system())
+        .add_system(player_presentation_system.system())
+        .add_system(chunk_loading_system.system())
+        .add_system(chunk_meshing_system.system())
         .add_system(client_prediction_system::<RigidBodyHandleComponent>.system())
-        .add_startup_system(chunk_loading_system.system())
-        .add_plugin(FlyCameraPlugin)
         .run();
 }
 
@@ -98,11 +121,12 @@ fn setup(
         .spawn(LightComponents {
             transform: Transform::from_translation(Vec3::new(4.0, 8.0, 4.0)),
             ..Default::default()
-        })
-        .spawn(Camera3dComponents::default())
-        .with(FlyCamera::default());
+        });
     
     // player
+    let [half_x, half_y, half_z] = CharacterControllerSettings::default().half_extents;
+    let [x, y, z] = PLAYER_SPAWN_POINT;
+
     commands
         .spawn(PbrBundle {
             mesh: meshes.add(Mesh::from(shape::Cube { size: 1.0 })),
@@ -119,14 +143,14 @@ fn setup(
             })
             .with(LocalPlayerHead)
             .with_children(|head| {
-            // camera
-                // head.spawn(Camera3dComponents {
-                //     transform: Transform::new_sync_disabled(Mat4::identity()),
-                //     ..Default::default()
-                // });
+                // first person camera
+                head.spawn(Camera3dBundle::default());
             });
         })
-        .with(RigidBodyBuilder::new(BodyStatus::Dynamic).translation(5.0, 2.0, 5.0))
-        .with(ColliderBuilder::cuboid(1.0, 1.0, 1.0))
+        .with(RigidBodyBuilder::new(BodyStatus::Kinematic).translation(x, y, z))
+        .with(ColliderBuilder::cuboid(half_x, half_y, half_z)
+            .collision_groups(InteractionGroups::new(PLAYER_COLLISION_GROUP, u16::MAX)))
+        .with(CharacterController::default())
+        .with(Synchronized::<CharacterController>::default())
         .with(Synchronized::<RigidBodyHandleComponent>::default());
 }
@@ -0,0 +1,92 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::app::{ScheduleRunnerSettings};
+use bevy_rapier3d::physics::{RapierPhysicsPlugin, RigidBodyHandleComponent};
+use bevy_rapier3d::rapier::dynamics::{BodyStatus, RigidBodyBuilder};
+use bevy_rapier3d::rapier::geometry::ColliderBuilder;
+
+use craft::components::*;
+use craft::events::*;
+use craft::models::*;
+use craft::resources::*;
+use craft::systems::*;
+
+/// Runs a recorded session through a headless server simulation, against the terrain the server
+/// played on
+///
+/// Usage: replay <replay file> [--speed <multiplier>] [--seek <frame>]
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let path = args.get(1).expect("Usage: replay <replay file> [--speed <multiplier>] [--seek <frame>]");
+    let arg = |name: &str| args.iter()
+        .position(|arg| arg == name)
+        .and_then(|index| args.get(index + 1));
+
+    let replay = Replay::load(path).expect("Failed to load replay");
+    println!(
+        "Loaded replay {} (seed {}, {} ticks/s, frames {}..{})",
+        path,
+        replay.header().world_seed,
+        replay.header().tick_rate,
+        replay.first_frame(),
+        replay.last_frame()
+    );
+
+    let mut player = ReplayPlayer::new(replay);
+
+    if let Some(speed) = arg("--speed") {
+        player.set_speed(speed.parse().expect("--speed must be a number"));
+    }
+
+    if let Some(frame) = arg("--seek") {
+        player.seek(frame.parse().expect("--seek must be a frame number"));
+    }
+
+    let mut sim_time = SimulationTime::new(player.header().tick_rate);
+    sim_time.set_frame(player.frame());
+
+    // Replaced by the recorded graph and caves as soon as playback starts
+    let world_generator = WorldGenerator::new(player.header().world_seed);
+
+    App::build()
+        .add_resource(ScheduleRunnerSettings::run_loop(Duration::from_secs_f64(
+            1.0 / player.tick_rate() as f64,
+        )))
+        .add_plugins(MinimalPlugins)
+        .add_plugin(RapierPhysicsPlugin)
+        .add_event::<CommandFrameEvent>()
+        .add_event::<StateFrameEvent>()
+        .add_event::<EntitySpawnEvent>()
+        .add_resource(ConnectionInfo::Server { addr: "127.0.0.1:0".parse().unwrap() })
+        .add_resource(sim_time)
+        .add_resource(player)
+        .add_resource(world_generator)
+        .add_resource(VoxelWorld::tracking_changes())
+        .init_resource::<VoxelEditLog>()
+        .init_resource::<NetworkEventListenerState>()
+        .init_resource::<ServerTerrainState>()
+        .add_startup_system(setup.system())
+        .add_system(simulation_time_system.system())
+        .add_system_to_stage(stage::PRE_UPDATE, replay_playback_system.system())
+        .add_system_to_stage(stage::PRE_UPDATE, server_player_movement_system.system())
+        .add_system(server_terrain_collision_system.system())
+        .add_system(replay_restoration_system::<RigidBodyHandleComponent>.system())
+        .add_system(replay_restoration_system::<Player>.system())
+        .add_system(replay_restoration_system::<CharacterController>.system())
+        .add_system(player_movement_system.system())
+        .run();
+}
+
+/// The static ground the server has
+fn setup(commands: &mut Commands) {
+    commands
+        // plane
+        .spawn((Transform::default(), GlobalTransform::default()))
+        .with(RigidBodyBuilder::new(BodyStatus::Static))
+        .with(ColliderBuilder::cuboid(10.0, 0.0, 10.0))
+        // cube
+        .spawn((Transform::default(), GlobalTransform::default()))
+        .with(RigidBodyBuilder::new(BodyStatus::Static).translation(0.0, 1.0, 0.0))
+        .with(ColliderBuilder::cuboid(1.0, 1.0, 1.0));
+}
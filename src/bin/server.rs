@@ -1,9 +1,11 @@
 use std::net::SocketAddr;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use bevy::prelude::*;
 use bevy::app::{ScheduleRunnerSettings};
 use bevy_rapier3d::physics::{RapierPhysicsPlugin, RigidBodyHandleComponent};
+use bevy_rapier3d::rapier::dynamics::{BodyStatus, RigidBodyBuilder};
+use bevy_rapier3d::rapier::geometry::ColliderBuilder;
 use bevy_prototype_networking_laminar::{NetworkResource, NetworkingPlugin};
 
 use craft::components::*;
@@ -15,6 +17,46 @@ use craft::systems::*;
 fn main() {
     let addr: SocketAddr = "127.0.0.1:12350".parse().expect("The socket address wasn't a valid format");
     let server = ConnectionInfo::Server { addr };
+    let sim_time = SimulationTime::new(60);
+
+    // Usage: server [--seed <world seed>] [--mesher <marching-cubes|dual-contouring|greedy>] [--record <replay file>]
+    let args: Vec<String> = std::env::args().collect();
+    let world_seed = match args.iter().position(|arg| arg == "--seed") {
+        Some(index) => args.get(index + 1)
+            .and_then(|seed| seed.parse::<u64>().ok())
+            .expect("--seed requires a whole number"),
+        None => SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64
+    };
+    println!("World seed: {}", world_seed);
+
+    let meshing_method = match args.iter().position(|arg| arg == "--mesher") {
+        Some(index) => args.get(index + 1)
+            .ok_or_else(|| "--mesher requires a meshing method, one of marching-cubes, dual-contouring or greedy".to_string())
+            .and_then(|method| method.parse::<MeshingMethod>())
+            .unwrap_or_else(|error| {
+                eprintln!("{}", error);
+                std::process::exit(2);
+            }),
+        None => MeshingMethod::default()
+    };
+    println!("Meshing method: {}", meshing_method);
+
+    let world_generator = WorldGenerator::load_or_default(world_seed, TERRAIN_GRAPH_PATH, CAVE_SETTINGS_PATH)
+        .with_meshing_method(meshing_method);
+
+    let mut recorder = match args.iter().position(|arg| arg == "--record") {
+        Some(index) => {
+            let path = args.get(index + 1).expect("--record requires a file path");
+            ReplayRecorder::create(path, world_seed, sim_time.tick_rate()).expect("Failed to create replay file")
+        },
+        None => ReplayRecorder::default()
+    };
+
+    // The graph and caves may not be around when the replay is played
+    recorder.record(ReplayEvent::WorldInfo {
+        frame: sim_time.frame(),
+        world_info: world_generator.world_info()
+    });
 
     App::build()
         .add_resource(ScheduleRunnerSettings::run_loop(Duration::from_secs_f64(
@@ -26,20 +68,45 @@ fn main() {
         .add_event::<CommandFrameEvent>()
         .add_event::<StateFrameEvent>()
         .add_event::<EntitySpawnEvent>()
+        .add_event::<VoxelEditRequestEvent>()
+        .add_event::<VoxelEditEvent>()
+        .add_event::<VoxelEditRejectedEvent>()
+        .add_event::<ChunkTransferEvent>()
         .add_resource(server)
-        .add_resource(SimulationTime::new(60))
+        .add_resource(sim_time)
+        .add_resource(recorder)
+        .add_resource(world_generator)
+        .add_resource(VoxelWorld::tracking_changes())
+        .init_resource::<TerrainGraphWatcher>()
+        .init_resource::<VoxelEditLog>()
+        .init_resource::<VoxelEditSettings>()
+        .init_resource::<ChunkStreamingSettings>()
         .init_resource::<NetworkEventListenerState>()
         .init_resource::<Clients>()
+        .init_resource::<ServerVoxelEditState>()
+        .init_resource::<ServerChunkTransferState>()
+        .init_resource::<ServerTerrainState>()
         .add_startup_system(setup.system())
+        .add_system(simulation_time_system.system())
         .add_system_to_stage(stage::PRE_UPDATE, network_message_listener_system.system())
         .add_system_to_stage(stage::PRE_UPDATE, server_player_movement_system.system())
+        .add_system(terrain_graph_reload_system.system())
+        .add_system(server_voxel_editing_system.system())
+        .add_system(server_chunk_transfer_system.system())
+        .add_system(server_terrain_collision_system.system())
+        .add_system(player_movement_system.system())
+        .add_system(player_animation_system.system())
         .add_stage_after(stage::POST_UPDATE, "pre_synchronize")
         .add_stage_after("pre_synchronize", "synchronize")
         .add_stage_after("synchronize", "post_synchronize")
         .add_system_to_stage("pre_synchronize", server_state_preauthoring_system::<RigidBodyHandleComponent>.system())
+        .add_system_to_stage("pre_synchronize", server_state_preauthoring_system::<Player>.system())
+        .add_system_to_stage("pre_synchronize", server_state_preauthoring_system::<CharacterController>.system())
         .add_system_to_stage("pre_synchronize", server_entity_spawning_for_connected_clients.system())
         .add_system_to_stage("pre_synchronize", server_entity_spawning_for_new_clients.system())
         .add_system_to_stage("synchronize", server_state_authoring_system::<RigidBodyHandleComponent>.system())
+        .add_system_to_stage("synchronize", server_state_authoring_system::<Player>.system())
+        .add_system_to_stage("synchronize", server_state_authoring_system::<CharacterController>.system())
         .run();
 }
 
@@ -50,4 +117,15 @@ fn setup(
     ci: Res<ConnectionInfo>
 ) {
     net.bind(ci.addr()).expect("We failed to bind to the socket.");
+
+    // The same static ground clients have, so players collide with it here too
+    commands
+        // plane
+        .spawn((Transform::default(), GlobalTransform::default()))
+        .with(RigidBodyBuilder::new(BodyStatus::Static))
+        .with(ColliderBuilder::cuboid(10.0, 0.0, 10.0))
+        // cube
+        .spawn((Transform::default(), GlobalTransform::default()))
+        .with(RigidBodyBuilder::new(BodyStatus::Static).translation(0.0, 1.0, 0.0))
+        .with(ColliderBuilder::cuboid(1.0, 1.0, 1.0));
 }
@@ -1,15 +1,23 @@
+mod character_controller;
 mod component_registry;
 mod local_player;
+mod look_direction;
+mod owner;
 mod player;
 mod synchronized;
 mod server_entity;
 mod rigid_body;
+mod world;
 
 pub use self::{
+    character_controller::*,
     component_registry::*,
     local_player::*,
+    look_direction::*,
+    owner::*,
     player::*,
     synchronized::*,
     server_entity::*,
-    rigid_body::*
+    rigid_body::*,
+    world::*
 };
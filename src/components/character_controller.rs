@@ -0,0 +1,634 @@
+use bevy::math::Vec3;
+use bevy::ecs::{Entity, Resources, World};
+use serde::{Serialize, Deserialize};
+use crate::components::Synchronizable;
+use crate::models::*;
+
+/// Tuning for a `CharacterController`. Distances are in metres, times in seconds.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct CharacterControllerSettings {
+    /// Half the size of the box used for collision
+    pub half_extents: [f32; 3],
+    pub max_speed: f32,
+    /// How quickly the character reaches `max_speed` on the ground
+    pub acceleration: f32,
+    /// How quickly the character can change direction while airborne
+    pub air_acceleration: f32,
+    /// How quickly the character comes to a stop on the ground without input
+    pub friction: f32,
+    pub jump_speed: f32,
+    pub gravity: f32,
+    /// The tallest ledge the character walks up without jumping
+    pub step_height: f32,
+    /// The steepest slope, in radians, that still counts as ground
+    pub max_slope: f32,
+    /// Gap kept between the character and whatever it touches
+    pub skin: f32
+}
+
+impl Default for CharacterControllerSettings {
+    fn default() -> Self {
+        Self {
+            half_extents: [0.4, 0.9, 0.4],
+            max_speed: 5.0,
+            acceleration: 40.0,
+            air_acceleration: 10.0,
+            friction: 30.0,
+            jump_speed: 6.5,
+            gravity: 20.0,
+            step_height: 1.05,
+            max_slope: 50f32.to_radians(),
+            skin: 0.01
+        }
+    }
+}
+
+/// Where a swept box first touches the world
+#[derive(Copy, Clone, Debug)]
+pub struct CharacterHit {
+    pub distance: f32,
+    pub normal: Vec3
+}
+
+/// The collision queries a `CharacterController` needs from the world it moves through
+pub trait CharacterCollisionWorld {
+    /// Sweeps an axis-aligned box from `center` along the unit vector `direction`, returning the
+    /// first hit within `max_distance`
+    fn cast_box(&self, center: Vec3, half_extents: Vec3, direction: Vec3, max_distance: f32) -> Option<CharacterHit>;
+}
+
+const MAX_SLIDES: usize = 4;
+const GROUND_SNAP: f32 = 0.1;
+const EPSILON: f32 = 1e-5;
+
+/// Moves a character through the world in fixed steps, driven by input commands.
+///
+/// Stepping only depends on the settings, the current state, the input command and the collision
+/// world, so the client's prediction and the server's simulation arrive at identical positions.
+/// The state is synchronized along with the rigid body, so a correction restores both.
+#[derive(Clone, Debug, Default)]
+pub struct CharacterController {
+    pub settings: CharacterControllerSettings,
+    pub velocity: Vec3,
+    pub grounded: bool,
+    /// The most recent input command frame this controller has simulated
+    pub last_frame: Option<u32>,
+    /// The input simulated at `last_frame`, repeated for frames that never arrived
+    pub last_input: Option<InputCommand>
+}
+
+/// The part of a `CharacterController` that changes as it moves
+#[derive(Serialize, Deserialize)]
+struct CharacterControllerState {
+    velocity: [f32; 3],
+    grounded: bool,
+    last_frame: Option<u32>,
+    last_input: Option<InputCommand>
+}
+
+impl Synchronizable for CharacterController {
+    fn type_id() -> u8 { 3 }
+
+    fn spawn(world: &mut World, resources: &mut Resources, entity: Entity) {
+        world.insert_one(entity, CharacterController::default()).unwrap();
+    }
+
+    fn author_serialized_state(&self, resources: &mut Resources) -> Vec<u8> {
+        bincode::serialize(&CharacterControllerState {
+            velocity: [self.velocity.x, self.velocity.y, self.velocity.z],
+            grounded: self.grounded,
+            last_frame: self.last_frame,
+            last_input: self.last_input
+        }).unwrap()
+    }
+
+    fn consume_serialized_state(&mut self, state: &Vec<u8>, resources: &mut Resources) {
+        let state = bincode::deserialize::<CharacterControllerState>(&state[..]).unwrap();
+        let [x, y, z] = state.velocity;
+
+        self.velocity = Vec3::new(x, y, z);
+        self.grounded = state.grounded;
+        self.last_frame = state.last_frame;
+        self.last_input = state.last_input;
+    }
+}
+
+impl CharacterController {
+    pub fn new(settings: CharacterControllerSettings) -> Self {
+        Self {
+            settings,
+            ..Default::default()
+        }
+    }
+
+    pub fn half_extents(&self) -> Vec3 {
+        let [x, y, z] = self.settings.half_extents;
+        Vec3::new(x, y, z)
+    }
+
+    /// Advances the character by `delta_seconds` from `position`, returning its new position
+    pub fn step(
+        &mut self,
+        position: Vec3,
+        input_command: &InputCommand,
+        delta_seconds: f32,
+        world: &impl CharacterCollisionWorld
+    ) -> Vec3 {
+        let settings = self.settings;
+        let half_extents = self.half_extents();
+
+        self.grounded = self.ground_normal(position, world).is_some();
+
+        // Horizontal velocity moves towards the wished velocity, or comes to rest without input
+        let wish_direction = wish_direction(input_command);
+        let horizontal = Vec3::new(self.velocity.x, 0.0, self.velocity.z);
+        let horizontal = if wish_direction.length_squared() > EPSILON {
+            let acceleration = if self.grounded { settings.acceleration } else { settings.air_acceleration };
+            approach(horizontal, wish_direction * settings.max_speed, acceleration * delta_seconds)
+        } else if self.grounded {
+            approach(horizontal, Vec3::zero(), settings.friction * delta_seconds)
+        } else {
+            horizontal
+        };
+
+        let mut vertical = self.velocity.y;
+        let mut jumped = false;
+        if self.grounded {
+            vertical = vertical.max(0.0);
+
+            if input_command.pressed(InputAction::Jump) {
+                vertical = settings.jump_speed;
+                jumped = true;
+            }
+        } else {
+            vertical -= settings.gravity * delta_seconds;
+        }
+
+        self.velocity = Vec3::new(horizontal.x, vertical, horizontal.z);
+
+        // Horizontal movement, stepping up ledges if a wall stops us while on the ground
+        let horizontal_motion = horizontal * delta_seconds;
+        let unblocked_velocity = self.velocity;
+        let (mut next_position, blocked) = self.slide(position, horizontal_motion, true, world);
+
+        if blocked && self.grounded && !jumped {
+            let blocked_velocity = self.velocity;
+            self.velocity = unblocked_velocity;
+
+            let stepped = self.step_up(position, horizontal_motion, world).filter(|stepped| {
+                let stepped_progress = Vec3::new(stepped.x - position.x, 0.0, stepped.z - position.z).length();
+                let plain_progress = Vec3::new(next_position.x - position.x, 0.0, next_position.z - position.z).length();
+
+                stepped_progress > plain_progress + EPSILON
+            });
+
+            match stepped {
+                Some(stepped) => next_position = stepped,
+                None => self.velocity = blocked_velocity
+            }
+        }
+
+        // Vertical movement, landing on walkable ground and stopping at ceilings
+        let vertical_motion = Vec3::new(0.0, self.velocity.y * delta_seconds, 0.0);
+        let was_grounded = self.grounded;
+        self.grounded = false;
+
+        if vertical_motion.y.abs() > EPSILON {
+            let direction = Vec3::new(0.0, vertical_motion.y.signum(), 0.0);
+            let distance = vertical_motion.y.abs();
+
+            match world.cast_box(next_position, half_extents, direction, distance + settings.skin) {
+                Some(hit) => {
+                    next_position += direction * (hit.distance - settings.skin).max(0.0);
+                    self.grounded = direction.y < 0.0 && self.is_walkable(hit.normal);
+                    self.velocity = Vec3::new(self.velocity.x, 0.0, self.velocity.z);
+                },
+                None => next_position += vertical_motion
+            }
+        }
+
+        // Stick to the ground when walking down slopes and steps instead of launching off them
+        if was_grounded && !jumped && !self.grounded {
+            let down = Vec3::new(0.0, -1.0, 0.0);
+            let snap = GROUND_SNAP + settings.step_height;
+
+            if let Some(hit) = world.cast_box(next_position, half_extents, down, snap + settings.skin) {
+                if self.is_walkable(hit.normal) {
+                    next_position += down * (hit.distance - settings.skin).max(0.0);
+                    self.grounded = true;
+                    self.velocity = Vec3::new(self.velocity.x, 0.0, self.velocity.z);
+                }
+            }
+        }
+
+        next_position
+    }
+
+    /// Whether a surface with this normal can be stood on
+    pub fn is_walkable(&self, normal: Vec3) -> bool {
+        normal.y >= self.settings.max_slope.cos()
+    }
+
+    fn ground_normal(&self, position: Vec3, world: &impl CharacterCollisionWorld) -> Option<Vec3> {
+        let down = Vec3::new(0.0, -1.0, 0.0);
+
+        world.cast_box(position, self.half_extents(), down, self.settings.skin + GROUND_SNAP)
+            .map(|hit| hit.normal)
+            .filter(|normal| self.is_walkable(*normal))
+    }
+
+    /// Moves as far along `motion` as possible, sliding along whatever is hit. Returns the new
+    /// position and whether anything was hit.
+    fn slide(&mut self, position: Vec3, motion: Vec3, horizontal: bool, world: &impl CharacterCollisionWorld) -> (Vec3, bool) {
+        let half_extents = self.half_extents();
+        let skin = self.settings.skin;
+        let mut position = position;
+        let mut remaining = motion;
+        let mut blocked = false;
+
+        for _ in 0..MAX_SLIDES {
+            let distance = remaining.length();
+            if distance < EPSILON {
+                break;
+            }
+
+            let direction = remaining / distance;
+
+            match world.cast_box(position, half_extents, direction, distance + skin) {
+                Some(hit) => {
+                    let travel = (hit.distance - skin).max(0.0).min(distance);
+                    position += direction * travel;
+                    blocked = true;
+
+                    // Steep slopes act as walls when moving horizontally, so they can't be climbed
+                    let mut normal = hit.normal;
+                    if horizontal && !self.is_walkable(normal) {
+                        normal = Vec3::new(normal.x, 0.0, normal.z);
+                        if normal.length_squared() < EPSILON {
+                            break;
+                        }
+                        normal = normal.normalize();
+                    }
+
+                    remaining = direction * (distance - travel);
+                    remaining -= normal * remaining.dot(normal);
+
+                    let into_surface = self.velocity.dot(normal);
+                    if into_surface < 0.0 {
+                        self.velocity -= normal * into_surface;
+                    }
+                },
+                None => {
+                    position += remaining;
+                    break;
+                }
+            }
+        }
+
+        (position, blocked)
+    }
+
+    /// Tries to climb a ledge up to `step_height` tall, returning where the character ends up if
+    /// it lands on walkable ground
+    fn step_up(&mut self, position: Vec3, motion: Vec3, world: &impl CharacterCollisionWorld) -> Option<Vec3> {
+        let half_extents = self.half_extents();
+        let skin = self.settings.skin;
+        let step_height = self.settings.step_height;
+
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        let rise = match world.cast_box(position, half_extents, up, step_height + skin) {
+            Some(hit) => (hit.distance - skin).max(0.0),
+            None => step_height
+        };
+
+        let raised = position + up * rise;
+        let (moved, _) = self.slide(raised, motion, true, world);
+
+        let down = Vec3::new(0.0, -1.0, 0.0);
+        world.cast_box(moved, half_extents, down, rise + skin)
+            .filter(|hit| self.is_walkable(hit.normal))
+            .map(|hit| moved + down * (hit.distance - skin).max(0.0))
+    }
+}
+
+/// The horizontal direction the movement actions ask for, relative to where the player is looking
+pub fn wish_direction(input_command: &InputCommand) -> Vec3 {
+    let forward = input_command.axis(InputAction::MoveForward) - input_command.axis(InputAction::MoveBackward);
+    let right = input_command.axis(InputAction::MoveRight) - input_command.axis(InputAction::MoveLeft);
+
+    let (sin, cos) = input_command.yaw.sin_cos();
+    let forward_direction = Vec3::new(-sin, 0.0, -cos);
+    let right_direction = Vec3::new(cos, 0.0, -sin);
+
+    let direction = forward_direction * forward + right_direction * right;
+    if direction.length_squared() > 1.0 {
+        direction.normalize()
+    } else {
+        direction
+    }
+}
+
+/// Moves `current` towards `target` by at most `max_delta`
+fn approach(current: Vec3, target: Vec3, max_delta: f32) -> Vec3 {
+    let difference = target - current;
+    let distance = difference.length();
+
+    if distance <= max_delta || distance < EPSILON {
+        target
+    } else {
+        current + difference * (max_delta / distance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DELTA_SECONDS: f32 = 1.0 / 60.0;
+
+    /// What the test worlds are made of
+    enum Shape {
+        /// An axis-aligned solid box
+        Block { min: Vec3, max: Vec3 },
+        /// Solid everywhere below a plane through the origin
+        Below { normal: Vec3 }
+    }
+
+    struct TestWorld {
+        shapes: Vec<Shape>
+    }
+
+    impl TestWorld {
+        /// Flat ground with its surface at `y = 0`
+        fn flat() -> TestWorld {
+            TestWorld {
+                shapes: vec![ground(0.0)]
+            }
+        }
+    }
+
+    fn ground(height: f32) -> Shape {
+        Shape::Block {
+            min: Vec3::new(-100.0, height - 10.0, -100.0),
+            max: Vec3::new(100.0, height, 100.0)
+        }
+    }
+
+    impl CharacterCollisionWorld for TestWorld {
+        fn cast_box(&self, center: Vec3, half_extents: Vec3, direction: Vec3, max_distance: f32) -> Option<CharacterHit> {
+            self.shapes.iter()
+                .filter_map(|shape| match shape {
+                    Shape::Block { min, max } => cast_against_block(center, direction, *min - half_extents, *max + half_extents),
+                    Shape::Below { normal } => cast_against_plane(center, half_extents, direction, *normal)
+                })
+                .filter(|hit| hit.distance <= max_distance)
+                .min_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap())
+        }
+    }
+
+    /// Casts a ray against a block grown by the box's half extents
+    fn cast_against_block(origin: Vec3, direction: Vec3, min: Vec3, max: Vec3) -> Option<CharacterHit> {
+        let (origin, direction, min, max) = (components(origin), components(direction), components(min), components(max));
+        let mut near = std::f32::MIN;
+        let mut far = std::f32::MAX;
+        let mut normal = [0.0; 3];
+
+        for axis in 0..3 {
+            if direction[axis].abs() < EPSILON {
+                if origin[axis] <= min[axis] || origin[axis] >= max[axis] {
+                    return None;
+                }
+
+                continue;
+            }
+
+            let a = (min[axis] - origin[axis]) / direction[axis];
+            let b = (max[axis] - origin[axis]) / direction[axis];
+            let (enter, exit) = (a.min(b), a.max(b));
+
+            if enter > near {
+                near = enter;
+                normal = [0.0; 3];
+                normal[axis] = -direction[axis].signum();
+            }
+
+            far = far.min(exit);
+        }
+
+        // Boxes starting inside the block are ignored, as the controller never ends up there
+        if near > far || near < -1e-4 {
+            return None;
+        }
+
+        Some(CharacterHit {
+            distance: near.max(0.0),
+            normal: Vec3::new(normal[0], normal[1], normal[2])
+        })
+    }
+
+    fn cast_against_plane(center: Vec3, half_extents: Vec3, direction: Vec3, normal: Vec3) -> Option<CharacterHit> {
+        // How far the box's lowest corner along the normal is above the plane
+        let reach = normal.x.abs() * half_extents.x + normal.y.abs() * half_extents.y + normal.z.abs() * half_extents.z;
+        let height = normal.dot(center) - reach;
+        let approach = -normal.dot(direction);
+
+        if height < -1e-4 || approach <= EPSILON {
+            return None;
+        }
+
+        Some(CharacterHit {
+            distance: height.max(0.0) / approach,
+            normal
+        })
+    }
+
+    fn components(vector: Vec3) -> [f32; 3] {
+        [vector.x, vector.y, vector.z]
+    }
+
+    fn bits(vector: Vec3) -> [u32; 3] {
+        [vector.x.to_bits(), vector.y.to_bits(), vector.z.to_bits()]
+    }
+
+    fn input(actions: &[InputAction]) -> InputCommand {
+        let mut input_command = InputCommand::default();
+
+        for action in actions.iter() {
+            input_command.set_pressed(*action, true);
+        }
+
+        input_command
+    }
+
+    /// Steps a character `frames` times with the same input, returning where it ends up
+    fn run(controller: &mut CharacterController, position: Vec3, input_command: &InputCommand, frames: usize, world: &TestWorld) -> Vec3 {
+        (0..frames).fold(position, |position, _| controller.step(position, input_command, DELTA_SECONDS, world))
+    }
+
+    /// Where a character stands on ground at `height`
+    fn standing_height(controller: &CharacterController, height: f32) -> f32 {
+        height + controller.settings.half_extents[1] + controller.settings.skin
+    }
+
+    #[test]
+    fn falls_until_it_lands_on_the_ground() {
+        let world = TestWorld::flat();
+        let mut controller = CharacterController::default();
+
+        let position = controller.step(Vec3::new(0.0, 3.0, 0.0), &input(&[]), DELTA_SECONDS, &world);
+        assert!(!controller.grounded);
+        assert!(controller.velocity.y < 0.0);
+
+        let position = run(&mut controller, position, &input(&[]), 120, &world);
+        assert!(controller.grounded);
+        assert_eq!(controller.velocity.y, 0.0);
+        assert!((position.y - standing_height(&controller, 0.0)).abs() < 1e-4, "Standing at {}", position.y);
+    }
+
+    #[test]
+    fn only_jumps_from_the_ground() {
+        let world = TestWorld::flat();
+        let mut controller = CharacterController::default();
+        let jump = input(&[InputAction::Jump]);
+
+        // Pressing jump in the air does nothing
+        controller.step(Vec3::new(0.0, 3.0, 0.0), &jump, DELTA_SECONDS, &world);
+        assert!(controller.velocity.y < 0.0);
+
+        let mut controller = CharacterController::default();
+        let standing = Vec3::new(0.0, standing_height(&controller, 0.0), 0.0);
+        let position = run(&mut controller, standing, &input(&[]), 10, &world);
+        assert!(controller.grounded);
+
+        let position = controller.step(position, &jump, DELTA_SECONDS, &world);
+        assert!(!controller.grounded);
+        assert!(position.y > standing.y);
+        assert_eq!(controller.velocity.y, controller.settings.jump_speed);
+
+        // Holding jump doesn't jump again until it lands
+        let next = controller.step(position, &jump, DELTA_SECONDS, &world);
+        assert!(controller.velocity.y < controller.settings.jump_speed);
+        assert!(next.y > position.y);
+    }
+
+    #[test]
+    fn steps_up_ledges_no_taller_than_the_step_height() {
+        let forward = input(&[InputAction::MoveForward]);
+
+        for (ledge, climbs) in [(0.5, true), (1.0, true), (1.5, false)].iter() {
+            // A ledge across the way forward, which is towards -z
+            let world = TestWorld {
+                shapes: vec![
+                    ground(0.0),
+                    Shape::Block { min: Vec3::new(-100.0, -1.0, -100.0), max: Vec3::new(100.0, *ledge, -2.0) }
+                ]
+            };
+
+            let mut controller = CharacterController::default();
+            let start = Vec3::new(0.0, standing_height(&controller, 0.0), 0.0);
+            let position = run(&mut controller, start, &forward, 90, &world);
+
+            if *climbs {
+                assert!((position.y - standing_height(&controller, *ledge)).abs() < 1e-3, "Stuck at {} below a {} ledge", position.y, ledge);
+                assert!(position.z < -3.0);
+            } else {
+                assert!((position.y - start.y).abs() < 1e-3, "Climbed to {} up a {} ledge", position.y, ledge);
+                assert!(position.z > -2.0 + controller.settings.half_extents[2] - 1e-3);
+            }
+
+            assert!(controller.grounded);
+        }
+    }
+
+    #[test]
+    fn only_walks_up_slopes_below_the_max_slope() {
+        let forward = input(&[InputAction::MoveForward]);
+
+        for (degrees, climbs) in [(30f32, true), (65f32, false)].iter() {
+            // Ground rising towards -z from the origin
+            let angle = degrees.to_radians();
+            let world = TestWorld {
+                shapes: vec![ground(0.0), Shape::Below { normal: Vec3::new(0.0, angle.cos(), angle.sin()) }]
+            };
+
+            let mut controller = CharacterController::default();
+            assert_eq!(controller.is_walkable(Vec3::new(0.0, angle.cos(), angle.sin())), *climbs);
+
+            let start = Vec3::new(0.0, standing_height(&controller, 0.0), 3.0);
+            let position = run(&mut controller, start, &forward, 120, &world);
+
+            if *climbs {
+                assert!(position.y > start.y + 2.0, "Only got up to {} on a {} degree slope", position.y, degrees);
+            } else {
+                assert!(position.y < start.y + controller.settings.step_height, "Climbed to {} up a {} degree slope", position.y, degrees);
+            }
+        }
+    }
+
+    #[test]
+    fn sticks_to_the_ground_walking_down_a_step() {
+        // A platform half a metre up, ending where the way forward drops to the ground
+        let world = TestWorld {
+            shapes: vec![
+                ground(0.0),
+                Shape::Block { min: Vec3::new(-100.0, -1.0, 0.0), max: Vec3::new(100.0, 0.5, 100.0) }
+            ]
+        };
+        let forward = input(&[InputAction::MoveForward]);
+
+        let mut controller = CharacterController::default();
+        let start = Vec3::new(0.0, standing_height(&controller, 0.5), 2.0);
+        let mut position = run(&mut controller, start, &forward, 1, &world);
+
+        for _ in 0..90 {
+            position = controller.step(position, &forward, DELTA_SECONDS, &world);
+            assert!(controller.grounded, "Left the ground at {:?}", position);
+        }
+
+        assert!(position.z < -1.0);
+        assert!((position.y - standing_height(&controller, 0.0)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn identical_input_gives_bit_identical_results() {
+        let world = TestWorld {
+            shapes: vec![
+                ground(0.0),
+                Shape::Block { min: Vec3::new(-100.0, -1.0, -100.0), max: Vec3::new(100.0, 0.8, -3.0) },
+                Shape::Block { min: Vec3::new(2.0, -1.0, -100.0), max: Vec3::new(3.0, 4.0, 100.0) }
+            ]
+        };
+
+        // Walking, turning and jumping into the ledge and the wall beside it
+        let inputs: Vec<InputCommand> = (0..240)
+            .map(|frame| {
+                let mut actions = vec![InputAction::MoveForward];
+                if frame % 50 < 5 {
+                    actions.push(InputAction::Jump);
+                }
+                if frame % 70 > 40 {
+                    actions.push(InputAction::MoveRight);
+                }
+
+                let mut input_command = input(&actions);
+                input_command.frame = frame;
+                input_command.yaw = (frame as f32 * 0.013).sin();
+                input_command
+            })
+            .collect();
+
+        let simulate = || {
+            let mut controller = CharacterController::default();
+            let mut position = Vec3::new(0.0, 2.0, 0.0);
+
+            inputs.iter()
+                .map(|input_command| {
+                    position = controller.step(position, input_command, DELTA_SECONDS, &world);
+                    (bits(position), bits(controller.velocity), controller.grounded)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(simulate(), simulate());
+    }
+}
@@ -1,3 +1,5 @@
 pub enum ComponentRegistry {
-    RigidBody = 1
+    RigidBody = 1,
+    Player = 2,
+    CharacterController = 3
 }
@@ -1,3 +1,4 @@
+/// Client only: the player entity this client controls
 pub struct LocalPlayer;
 pub struct LocalPlayerHead;
 pub struct LocalPlayerBody;
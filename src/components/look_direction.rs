@@ -0,0 +1,8 @@
+use serde::{Serialize, Deserialize};
+
+/// Where a player is looking, as sent in their latest input command
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct LookDirection {
+    pub yaw: f32,
+    pub pitch: f32
+}
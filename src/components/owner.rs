@@ -0,0 +1,5 @@
+/// The client whose commands control this entity
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Owner {
+    pub client_id: u128
+}
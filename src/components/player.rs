@@ -1,30 +1,115 @@
 use bevy::prelude::*;
 use bevy::ecs::{Entity, Resources, World};
-use crate::components::Synchronizable;
+use bevy_rapier3d::physics::RigidBodyHandleComponent;
+use bevy_rapier3d::rapier::dynamics::{BodyStatus, RigidBodyBuilder};
+use bevy_rapier3d::rapier::geometry::{ColliderBuilder, InteractionGroups};
+use serde::{Serialize, Deserialize};
+use crate::components::*;
+use crate::models::*;
+use crate::systems::PLAYER_COLLISION_GROUP;
+
+pub const PLAYER_MAX_HEALTH: f32 = 100.0;
+
+/// How far above the centre of the body the head sits
+pub const PLAYER_HEAD_HEIGHT: f32 = 0.7;
+
+/// The replicated state of a player
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Player {
+    /// The name and color never change, so they are sent when the player spawns rather than
+    /// with every state
+    #[serde(skip)]
+    pub name: String,
+    #[serde(skip)]
+    pub color: [f32; 3],
+    pub health: f32,
+    /// The hotbar slot the player is holding
+    pub selected_item: u8,
+    pub look_direction: LookDirection,
+    pub animation: PlayerAnimation
+}
 
-pub struct Player;
 pub struct PlayerHead;
 pub struct PlayerBody;
 
+impl Default for Player {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            color: [1.0, 1.0, 1.0],
+            health: PLAYER_MAX_HEALTH,
+            selected_item: 0,
+            look_direction: LookDirection::default(),
+            animation: PlayerAnimation::default()
+        }
+    }
+}
+
+impl Player {
+    pub fn new(profile: PlayerProfile) -> Self {
+        Self {
+            name: profile.name,
+            color: profile.color,
+            ..Default::default()
+        }
+    }
+
+    pub fn profile(&self) -> PlayerProfile {
+        PlayerProfile {
+            name: self.name.clone(),
+            color: self.color
+        }
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.health > 0.0
+    }
+}
+
 impl Synchronizable for Player {
     fn type_id() -> u8 { 2 }
 
-    fn spawn(world: &mut World, resources: &mut Resources, entity:Entity) {
-        let mut meshes = resources.get_mut::<Assets<Mesh>>().unwrap();
-        let mut materials = resources.get_mut::<Assets<StandardMaterial>>().unwrap();
-
-        world.insert(entity, PbrBundle {
-            mesh: meshes.add(Mesh::from(shape::Cube { size: 1.0 })),
-            material: materials.add(Color::rgb(0.5, 0.4, 0.3).into()),
-            ..Default::default()
-        });
+    fn spawn(world: &mut World, resources: &mut Resources, entity: Entity) {
+        world.insert_one(entity, Player::default()).unwrap();
     }
 
-    fn author_serialized_state(&self, resources: &mut bevy::ecs::Resources) -> Vec<u8> {
-        todo!()
+    fn author_serialized_state(&self, resources: &mut Resources) -> Vec<u8> {
+        bincode::serialize(self).unwrap()
     }
 
-    fn consume_serialized_state(&mut self, state: &Vec<u8>, resources: &mut bevy::ecs::Resources) {
-        todo!()
+    fn consume_serialized_state(&mut self, state: &Vec<u8>, resources: &mut Resources) {
+        let profile = self.profile();
+        *self = bincode::deserialize::<Player>(&state[..]).unwrap();
+        self.name = profile.name;
+        self.color = profile.color;
     }
-}
\ No newline at end of file
+}
+
+/// Where newly authorized players appear
+pub const PLAYER_SPAWN_POINT: [f32; 3] = [5.0, 2.0, 5.0];
+
+/// Server only: spawns the simulated player entity controlled by a client
+pub fn spawn_server_player(commands: &mut Commands, client_id: u128) -> Entity {
+    let settings = CharacterControllerSettings::default();
+    let [half_x, half_y, half_z] = settings.half_extents;
+    let [x, y, z] = PLAYER_SPAWN_POINT;
+
+    commands
+        .spawn((
+            Synchronize,
+            Player::new(PlayerProfile::for_client(client_id)),
+            Synchronized::<Player>::default(),
+            Owner { client_id },
+            CharacterController::new(settings),
+            Synchronized::<CharacterController>::default(),
+            Synchronized::<RigidBodyHandleComponent>::default(),
+            Transform::from_translation(Vec3::new(x, y, z)),
+            GlobalTransform::default()
+        ))
+        .with(RigidBodyBuilder::new(BodyStatus::Kinematic)
+            .translation(x, y, z))
+        .with(ColliderBuilder::cuboid(half_x, half_y, half_z)
+            .collision_groups(InteractionGroups::new(PLAYER_COLLISION_GROUP, u16::MAX)));
+
+    commands.current_entity().unwrap()
+}
@@ -33,7 +33,7 @@ impl<TComponent> Command for SynchronizableStateAuthoring<TComponent> where TCom
             component_type_id,
             frame: self.frame,
             state: serialized_state
-        })
+        });
     }
 }
 
@@ -1,6 +1,11 @@
-use bevy::prelude::*;
+use crate::models::*;
 
 pub struct Terrain;
-pub struct TerrainChunk { 
-    pub Vec3 coords
+
+/// Links an entity to the chunk of the `VoxelWorld` it displays
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TerrainChunk {
+    pub position: ChunkPos,
+    /// The biome in the middle of the chunk
+    pub biome: Biome
 }
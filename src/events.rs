@@ -1,9 +1,13 @@
 mod command_frame;
 mod state_frame;
 mod entity_spawn;
+mod voxel_edit;
+mod chunk_transfer;
 
 pub use self::{
     command_frame::*,
     state_frame::*,
-    entity_spawn::*
+    entity_spawn::*,
+    voxel_edit::*,
+    chunk_transfer::*
 };
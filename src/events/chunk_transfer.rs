@@ -0,0 +1,15 @@
+use crate::models::*;
+
+/// Chunk voxels moving between the server and a client. Kept as one event so a client handles
+/// revisions and fragments in the order the server sent them.
+pub enum ChunkTransferEvent {
+    /// Server only: a client asking for the voxels of edited chunks
+    Requested {
+        from: u128,
+        positions: Vec<ChunkPos>
+    },
+    /// Client only: the revision of chunks coming into range
+    Revisions(Vec<(ChunkPos, u64)>),
+    /// Client only: a piece of an edited chunk's voxels
+    Fragment(ChunkFragment)
+}
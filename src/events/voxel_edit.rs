@@ -0,0 +1,18 @@
+use crate::models::*;
+
+/// Server only: a client asking to change the world
+pub struct VoxelEditRequestEvent {
+    pub from: u128,
+    pub request: VoxelEditRequest
+}
+
+/// Client only: an edit the server accepted
+pub struct VoxelEditEvent {
+    pub edit: VoxelEdit
+}
+
+/// Client only: the server turned down one of our edits
+pub struct VoxelEditRejectedEvent {
+    pub id: u32,
+    pub reason: VoxelEditRejection
+}
@@ -1,5 +1,6 @@
 pub mod components;
 pub mod events;
+pub mod meshing;
 pub mod models;
 pub mod render;
 pub mod resources;
@@ -9,6 +10,7 @@ pub mod utilities;
 pub use self::{
     components::*,
     events::*,
+    meshing::*,
     models::*,
     render::*,
     resources::*,
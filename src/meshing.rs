@@ -0,0 +1,18 @@
+mod cell;
+mod chunk_neighbourhood;
+mod dual_contouring;
+mod greedy;
+mod marching_cubes;
+mod mesh_data;
+mod mesher;
+mod skirts;
+
+pub use self::{
+    chunk_neighbourhood::*,
+    dual_contouring::*,
+    greedy::*,
+    marching_cubes::*,
+    mesh_data::*,
+    mesher::*,
+    skirts::*
+};
@@ -0,0 +1,62 @@
+// The layout of the cube between eight neighbouring voxels, shared by the meshers.
+
+use crate::meshing::ChunkNeighbourhood;
+
+/// The corner of a cell at each index, as offsets from its lowest corner. Bit 0 of the index is
+/// the x offset, bit 1 the y offset and bit 2 the z offset.
+pub const CORNERS: [[i32; 3]; 8] = [
+    [0, 0, 0], [1, 0, 0], [0, 1, 0], [1, 1, 0],
+    [0, 0, 1], [1, 0, 1], [0, 1, 1], [1, 1, 1]
+];
+
+/// The edges of a cell by the corners at either end, lowest first. Edges 0 to 3 run along x, 4 to
+/// 7 along y and 8 to 11 along z.
+pub const EDGES: [(usize, usize); 12] = [
+    (0, 1), (2, 3), (4, 5), (6, 7),
+    (0, 2), (1, 3), (4, 6), (5, 7),
+    (0, 4), (1, 5), (2, 6), (3, 7)
+];
+
+/// How far along the edge from a grid point the surface crosses it, and the surface normal there.
+/// Always measured from the lower end, so every cell sharing the edge finds the same point.
+pub fn edge_crossing(neighbourhood: &ChunkNeighbourhood, start: [i32; 3], axis: usize) -> (f32, [f32; 3]) {
+    let mut end = start;
+    end[axis] += 1;
+
+    let from = neighbourhood.density(start[0], start[1], start[2]);
+    let to = neighbourhood.density(end[0], end[1], end[2]);
+    let t = from / (from - to);
+
+    let from = neighbourhood.normal(start[0], start[1], start[2]);
+    let to = neighbourhood.normal(end[0], end[1], end[2]);
+    let normal = normalize([
+        from[0] + (to[0] - from[0]) * t,
+        from[1] + (to[1] - from[1]) * t,
+        from[2] + (to[2] - from[2]) * t
+    ]);
+
+    (t, normal)
+}
+
+/// The material of the solid end of an edge the surface crosses
+pub fn edge_material(neighbourhood: &ChunkNeighbourhood, start: [i32; 3], axis: usize) -> u8 {
+    let voxel = neighbourhood.get(start[0], start[1], start[2]);
+
+    if voxel.is_solid() {
+        voxel.material
+    } else {
+        let mut end = start;
+        end[axis] += 1;
+        neighbourhood.get(end[0], end[1], end[2]).material
+    }
+}
+
+pub fn normalize(vector: [f32; 3]) -> [f32; 3] {
+    let length = (vector[0] * vector[0] + vector[1] * vector[1] + vector[2] * vector[2]).sqrt();
+
+    if length > std::f32::EPSILON {
+        [vector[0] / length, vector[1] / length, vector[2] / length]
+    } else {
+        [0.0, 1.0, 0.0]
+    }
+}
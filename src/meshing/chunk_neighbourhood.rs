@@ -0,0 +1,234 @@
+use crate::models::*;
+use crate::resources::{VoxelWorld, WorldGenerator};
+
+/// The lowest and highest local coordinate a full detail neighbourhood covers on each axis
+pub const NEIGHBOURHOOD_MIN: i32 = -1;
+pub const NEIGHBOURHOOD_MAX: i32 = CHUNK_SIZE as i32 + 2;
+
+/// A copy of the voxels a chunk's mesh is built from. Meshes join up voxel centres, so a chunk's
+/// cells reach from its first voxel to the first voxel of the next chunk, and normals need one
+/// voxel more on every side. Dual contouring also places vertices in the cells between the next
+/// chunk's first two voxels, so the neighbourhood reaches one voxel further on the far sides.
+/// Neighbouring chunks mesh from the same voxels where they meet, so their edges line up exactly.
+///
+/// Chunks far away are drawn from a coarser grid, taking every `scale`th voxel along each axis.
+/// Coordinates count grid points rather than voxels, so the meshers work the same at any scale
+/// and only stretch their vertices by it.
+#[derive(Clone)]
+pub struct ChunkNeighbourhood {
+    position: ChunkPos,
+    scale: i32,
+    voxels: Vec<Voxel>
+}
+
+impl ChunkNeighbourhood {
+    /// Builds a neighbourhood by asking for the voxel at every world position it covers
+    pub fn from_fn(position: ChunkPos, f: impl FnMut(VoxelPos) -> Voxel) -> ChunkNeighbourhood {
+        ChunkNeighbourhood::from_fn_scaled(position, 1, f)
+    }
+
+    /// Builds a neighbourhood from every `scale`th voxel, asking for each one by its world position
+    pub fn from_fn_scaled(position: ChunkPos, scale: i32, mut f: impl FnMut(VoxelPos) -> Voxel) -> ChunkNeighbourhood {
+        let origin = position.origin();
+        let max = CHUNK_SIZE as i32 / scale + 2;
+        let width = Self::width(scale);
+        let mut voxels = Vec::with_capacity(width * width * width);
+
+        for z in NEIGHBOURHOOD_MIN..=max {
+            for y in NEIGHBOURHOOD_MIN..=max {
+                for x in NEIGHBOURHOOD_MIN..=max {
+                    voxels.push(f(origin.offset(x * scale, y * scale, z * scale)));
+                }
+            }
+        }
+
+        ChunkNeighbourhood {
+            position,
+            scale,
+            voxels
+        }
+    }
+
+    /// Copies a chunk's neighbourhood out of the world, or nothing if any chunk it reaches into
+    /// isn't loaded yet
+    pub fn from_world(world: &VoxelWorld, position: ChunkPos) -> Option<ChunkNeighbourhood> {
+        for z in -1..=1 {
+            for y in -1..=1 {
+                for x in -1..=1 {
+                    if !world.contains_chunk(position.offset(x, y, z)) {
+                        return None;
+                    }
+                }
+            }
+        }
+
+        Some(ChunkNeighbourhood::from_fn(position, |voxel| world.voxel_or_air(voxel)))
+    }
+
+    /// Samples a far away chunk's neighbourhood straight from the generator, without the chunk or
+    /// those around it being generated in full
+    pub fn from_generator(generator: &WorldGenerator, position: ChunkPos, scale: i32) -> ChunkNeighbourhood {
+        let first = position.origin().offset(NEIGHBOURHOOD_MIN * scale, NEIGHBOURHOOD_MIN * scale, NEIGHBOURHOOD_MIN * scale);
+
+        ChunkNeighbourhood {
+            position,
+            scale,
+            voxels: generator.sample_coarse(first, scale, Self::width(scale))
+        }
+    }
+
+    /// Replaces the grid points that fall in a chunk with its voxels, so a far away chunk someone
+    /// has edited can be drawn from the voxels the server sent rather than the generator's
+    pub fn overlay_chunk(&mut self, position: ChunkPos, chunk: &Chunk) {
+        let origin = self.position.origin();
+        let max = self.cells() + 2;
+        let mut index = 0;
+
+        for z in NEIGHBOURHOOD_MIN..=max {
+            for y in NEIGHBOURHOOD_MIN..=max {
+                for x in NEIGHBOURHOOD_MIN..=max {
+                    let voxel = origin.offset(x * self.scale, y * self.scale, z * self.scale);
+
+                    if voxel.chunk() == position {
+                        self.voxels[index] = chunk.get(voxel.local());
+                    }
+
+                    index += 1;
+                }
+            }
+        }
+    }
+
+    /// The chunks a neighbourhood at a scale takes grid points from
+    pub fn chunks_covered(position: ChunkPos, scale: i32) -> Vec<ChunkPos> {
+        let origin = position.origin();
+        let first = origin.offset(NEIGHBOURHOOD_MIN * scale, NEIGHBOURHOOD_MIN * scale, NEIGHBOURHOOD_MIN * scale).chunk();
+        let last_offset = (CHUNK_SIZE as i32 / scale + 2) * scale;
+        let last = origin.offset(last_offset, last_offset, last_offset).chunk();
+
+        let mut chunks = vec![];
+
+        for z in first.2..=last.2 {
+            for y in first.1..=last.1 {
+                for x in first.0..=last.0 {
+                    chunks.push(ChunkPos(x, y, z));
+                }
+            }
+        }
+
+        chunks
+    }
+
+    /// How many grid points a neighbourhood covers along each axis
+    fn width(scale: i32) -> usize {
+        CHUNK_SIZE / scale as usize + 4
+    }
+
+    pub fn position(&self) -> ChunkPos {
+        self.position
+    }
+
+    /// How many voxels apart the grid points are
+    pub fn scale(&self) -> i32 {
+        self.scale
+    }
+
+    /// How many cells span the chunk along each axis
+    pub fn cells(&self) -> i32 {
+        CHUNK_SIZE as i32 / self.scale
+    }
+
+    /// The voxel at a grid point relative to the chunk's origin, each axis in
+    /// `NEIGHBOURHOOD_MIN..=cells() + 2`
+    pub fn get(&self, x: i32, y: i32, z: i32) -> Voxel {
+        let width = Self::width(self.scale);
+        let index = |value: i32| {
+            debug_assert!(value >= NEIGHBOURHOOD_MIN && value <= self.cells() + 2);
+            (value - NEIGHBOURHOOD_MIN) as usize
+        };
+
+        self.voxels[(index(z) * width + index(y)) * width + index(x)]
+    }
+
+    pub fn density(&self, x: i32, y: i32, z: i32) -> f32 {
+        self.get(x, y, z).density()
+    }
+
+    /// The direction the density falls fastest at a voxel, which is the surface normal there.
+    /// Only defined one voxel in from the neighbourhood's edges.
+    pub fn normal(&self, x: i32, y: i32, z: i32) -> [f32; 3] {
+        [
+            self.density(x - 1, y, z) - self.density(x + 1, y, z),
+            self.density(x, y - 1, z) - self.density(x, y + 1, z),
+            self.density(x, y, z - 1) - self.density(x, y, z + 1)
+        ]
+    }
+
+    /// Whether there's any surface to mesh, which there isn't if every voxel is solid or every
+    /// voxel is empty
+    pub fn has_surface(&self) -> bool {
+        let solid = self.voxels[0].is_solid();
+        self.voxels.iter().any(|voxel| voxel.is_solid() != solid)
+    }
+
+    /// The chunks whose neighbourhoods include a voxel, which all need meshing again when it
+    /// changes
+    pub fn chunks_containing(position: VoxelPos) -> Vec<ChunkPos> {
+        let chunk = position.chunk();
+        let local = position.local();
+
+        // The chunk before covers this chunk's first three voxels, and the chunk after its last
+        let offsets = |local: usize| {
+            let mut offsets = vec![0];
+
+            if (local as i32) <= NEIGHBOURHOOD_MAX - CHUNK_SIZE as i32 {
+                offsets.push(-1);
+            }
+
+            if local as i32 >= CHUNK_SIZE as i32 + NEIGHBOURHOOD_MIN {
+                offsets.push(1);
+            }
+
+            offsets
+        };
+
+        let mut chunks = vec![];
+
+        for z in offsets(local.2) {
+            for y in offsets(local.1) {
+                for x in offsets(local.0) {
+                    chunks.push(chunk.offset(x, y, z));
+                }
+            }
+        }
+
+        chunks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overlaid_chunks_replace_the_generated_voxels_they_cover() {
+        let generator = WorldGenerator::new(7);
+        let position = ChunkPos(0, 0, 0);
+        let edited = ChunkPos(1, 0, 0);
+        let chunk = Chunk::from_fn(|_| Voxel::from_density(STONE, 1.0));
+
+        for scale in [2, 4, 8].iter() {
+            assert!(ChunkNeighbourhood::chunks_covered(position, *scale).contains(&edited));
+
+            let generated = ChunkNeighbourhood::from_generator(&generator, position, *scale);
+            let mut neighbourhood = ChunkNeighbourhood::from_generator(&generator, position, *scale);
+            neighbourhood.overlay_chunk(edited, &chunk);
+
+            // The first grid point past the chunk's last cell falls in the edited chunk, the one
+            // before it doesn't
+            let edge = neighbourhood.cells();
+            assert_eq!(neighbourhood.get(edge, 0, 0), Voxel::from_density(STONE, 1.0));
+            assert_eq!(neighbourhood.get(edge - 1, 0, 0), generated.get(edge - 1, 0, 0));
+        }
+    }
+}
@@ -0,0 +1,263 @@
+use crate::models::*;
+use crate::meshing::{ChunkNeighbourhood, MeshData};
+use crate::meshing::cell::*;
+
+/// How strongly a cell's vertex is pulled towards the middle of its crossings. Keeps vertices
+/// steady where the crossings' normals agree, as on flat ground, without rounding off the corners
+/// where they don't.
+const MASS_POINT_WEIGHT: f32 = 0.05;
+
+/// The steps a vertex's position within its cell is rounded to along each axis
+const VERTEX_STEPS: f32 = 1024.0;
+
+/// The cells around an edge, as offsets across the edge, counter-clockwise looking along it
+const EDGE_CELLS: [[i32; 2]; 4] = [[-1, -1], [0, -1], [0, 0], [-1, 0]];
+
+/// Builds a mesh of the surface through a chunk's cells, in the chunk's local space. Where marching
+/// cubes puts vertices on the edges of cells, dual contouring puts one inside each cell the
+/// surface passes through, at the point that best fits the normals where the surface crosses the
+/// cell's edges. Planes meeting in a cell meet at that point, so edges and corners stay sharp, and
+/// every crossed edge becomes a single quad between the four cells around it.
+///
+/// A chunk makes the quads for edges whose cells are all its own. That includes the cells along
+/// its far faces, which the chunks after it place the same vertices in.
+pub fn dual_contouring(neighbourhood: &ChunkNeighbourhood) -> MeshData {
+    let mut mesh = MeshData::default();
+    let size = neighbourhood.cells();
+    let cells = size + 1;
+
+    // The vertex in each cell the surface passes through
+    let mut vertices = vec![None; (cells * cells * cells) as usize];
+    let cell_index = |cell: [i32; 3]| ((cell[2] * cells + cell[1]) * cells + cell[0]) as usize;
+
+    for z in 0..cells {
+        for y in 0..cells {
+            for x in 0..cells {
+                if let Some((position, normal, material)) = cell_vertex(neighbourhood, [x, y, z]) {
+                    vertices[cell_index([x, y, z])] = Some(mesh.positions.len() as u32);
+                    mesh.positions.push(position);
+                    mesh.normals.push(normal);
+                    mesh.materials.push(material);
+                }
+            }
+        }
+    }
+
+    for axis in 0..3 {
+        let u = (axis + 1) % 3;
+        let v = (axis + 2) % 3;
+
+        for along in 0..size {
+            for i in 1..cells {
+                for j in 1..cells {
+                    let mut start = [0; 3];
+                    start[axis] = along;
+                    start[u] = i;
+                    start[v] = j;
+
+                    let mut end = start;
+                    end[axis] += 1;
+
+                    let solid_start = neighbourhood.get(start[0], start[1], start[2]).is_solid();
+                    let solid_end = neighbourhood.get(end[0], end[1], end[2]).is_solid();
+
+                    if solid_start == solid_end {
+                        continue;
+                    }
+
+                    let mut quad = [0; 4];
+                    for (corner, offset) in EDGE_CELLS.iter().enumerate() {
+                        let mut cell = start;
+                        cell[u] += offset[0];
+                        cell[v] += offset[1];
+
+                        // Every cell around a crossed edge has a vertex
+                        quad[corner] = vertices[cell_index(cell)].unwrap();
+                    }
+
+                    // The quad faces away from the solid end of the edge
+                    if solid_start {
+                        mesh.indices.extend_from_slice(&[quad[0], quad[1], quad[2], quad[0], quad[2], quad[3]]);
+                    } else {
+                        mesh.indices.extend_from_slice(&[quad[0], quad[2], quad[1], quad[0], quad[3], quad[2]]);
+                    }
+                }
+            }
+        }
+    }
+
+    mesh
+}
+
+/// The vertex of a cell with its normal and material, if the surface passes through the cell. The
+/// material is the one most of the cell's solid corners are made of.
+fn cell_vertex(neighbourhood: &ChunkNeighbourhood, cell: [i32; 3]) -> Option<([f32; 3], [f32; 3], u8)> {
+    let voxels: Vec<Voxel> = CORNERS.iter()
+        .map(|[x, y, z]| neighbourhood.get(cell[0] + x, cell[1] + y, cell[2] + z))
+        .collect();
+    let solid: Vec<bool> = voxels.iter().map(|voxel| voxel.is_solid()).collect();
+
+    if solid.iter().all(|corner| *corner == solid[0]) {
+        return None;
+    }
+
+    // Where the surface crosses the cell's edges and its normals there, relative to the cell
+    let mut crossings = vec![];
+
+    for (edge, (a, b)) in EDGES.iter().enumerate() {
+        if solid[*a] == solid[*b] {
+            continue;
+        }
+
+        let axis = edge / 4;
+        let corner = CORNERS[*a];
+        let start = [cell[0] + corner[0], cell[1] + corner[1], cell[2] + corner[2]];
+        let (t, _) = edge_crossing(neighbourhood, start, axis);
+        let normal = crossing_normal(neighbourhood, start, axis);
+
+        let mut point = [corner[0] as f32, corner[1] as f32, corner[2] as f32];
+        point[axis] += t;
+        crossings.push((point, normal));
+    }
+
+    let mut mass_point = [0.0; 3];
+    let mut normal = [0.0; 3];
+
+    for (point, crossing_normal) in crossings.iter() {
+        for axis in 0..3 {
+            mass_point[axis] += point[axis] / crossings.len() as f32;
+            normal[axis] += crossing_normal[axis];
+        }
+    }
+
+    // Least squares fit of the planes through each crossing, pulled slightly towards their middle:
+    // (NᵀN + wI)x = Nᵀd + w·m
+    let mut matrix = [[0.0; 3]; 3];
+    let mut target = [0.0; 3];
+
+    for (point, normal) in crossings.iter() {
+        let distance = dot(*normal, *point);
+
+        for row in 0..3 {
+            for column in 0..3 {
+                matrix[row][column] += normal[row] * normal[column];
+            }
+
+            target[row] += normal[row] * distance;
+        }
+    }
+
+    for axis in 0..3 {
+        matrix[axis][axis] += MASS_POINT_WEIGHT;
+        target[axis] += MASS_POINT_WEIGHT * mass_point[axis];
+    }
+
+    // Kept inside the cell, as a vertex outside it could fold the surface over its neighbours'.
+    // Rounded so it's exact whichever chunk's cell it is, as chunks share the cells between them.
+    let solved = solve(matrix, target).unwrap_or(mass_point);
+    let offset = |value: f32| (value.max(0.0).min(1.0) * VERTEX_STEPS).round() / VERTEX_STEPS;
+    let scale = neighbourhood.scale() as f32;
+    let position = [
+        (cell[0] as f32 + offset(solved[0])) * scale,
+        (cell[1] as f32 + offset(solved[1])) * scale,
+        (cell[2] as f32 + offset(solved[2])) * scale
+    ];
+
+    // The most common solid material, ties going to the lowest
+    let mut counts = [0; 256];
+    for voxel in voxels.iter().filter(|voxel| voxel.is_solid()) {
+        counts[voxel.material as usize] += 1;
+    }
+    let material = (0..256).max_by_key(|material| (counts[*material], std::cmp::Reverse(*material))).unwrap() as u8;
+
+    Some((position, normalize(normal), material))
+}
+
+/// The surface normal where it crosses an edge, taken from whichever end's normal lines up best
+/// with the edge. Near a sharp edge or corner of the surface, the normal at one end mixes in the
+/// planes meeting there while the other end's doesn't, and blending the two would round it off.
+fn crossing_normal(neighbourhood: &ChunkNeighbourhood, start: [i32; 3], axis: usize) -> [f32; 3] {
+    let mut end = start;
+    end[axis] += 1;
+
+    let from = normalize(neighbourhood.normal(start[0], start[1], start[2]));
+    let to = normalize(neighbourhood.normal(end[0], end[1], end[2]));
+
+    if from[axis].abs() >= to[axis].abs() { from } else { to }
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+/// Solves a 3x3 system of equations by Cramer's rule, or nothing if it has no single solution
+fn solve(matrix: [[f32; 3]; 3], target: [f32; 3]) -> Option<[f32; 3]> {
+    let columns = [
+        [matrix[0][0], matrix[1][0], matrix[2][0]],
+        [matrix[0][1], matrix[1][1], matrix[2][1]],
+        [matrix[0][2], matrix[1][2], matrix[2][2]]
+    ];
+
+    let determinant = dot(columns[0], cross(columns[1], columns[2]));
+
+    if determinant.abs() <= std::f32::EPSILON {
+        return None;
+    }
+
+    Some([
+        dot(target, cross(columns[1], columns[2])) / determinant,
+        dot(columns[0], cross(target, columns[2])) / determinant,
+        dot(columns[0], cross(columns[1], target)) / determinant
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::meshing::marching_cubes;
+    use crate::resources::WorldGenerator;
+    use super::*;
+
+    fn triangles(mesh: &MeshData) -> usize {
+        mesh.indices.len() / 3
+    }
+
+    /// How far the closest vertex of a mesh is from a point
+    fn closest_vertex(mesh: &MeshData, point: [f32; 3]) -> f32 {
+        mesh.positions.iter()
+            .map(|position| {
+                let offset = [position[0] - point[0], position[1] - point[1], position[2] - point[2]];
+                dot(offset, offset).sqrt()
+            })
+            .fold(std::f32::MAX, f32::min)
+    }
+
+    #[test]
+    fn fewer_triangles_than_marching_cubes() {
+        let neighbourhood = ChunkNeighbourhood::from_generator(&WorldGenerator::new(7), ChunkPos(0, 0, 0), 1);
+        let dual = dual_contouring(&neighbourhood);
+        let marched = marching_cubes(&neighbourhood);
+
+        assert!(!dual.is_empty());
+        assert!(triangles(&dual) < triangles(&marched), "{} triangles against {}", triangles(&dual), triangles(&marched));
+    }
+
+    #[test]
+    fn keeps_corners_sharp() {
+        // The corner of a block of ground, with faces facing up and towards +x and +z
+        let corner = [7.5, 6.5, 8.5];
+        let neighbourhood = ChunkNeighbourhood::from_fn(ChunkPos(0, 0, 0), |VoxelPos(x, y, z)| {
+            let distance = (corner[0] - x as f32).min(corner[1] - y as f32).min(corner[2] - z as f32);
+            Voxel::from_density(STONE, distance / 4.0)
+        });
+
+        let dual = closest_vertex(&dual_contouring(&neighbourhood), corner);
+        let marched = closest_vertex(&marching_cubes(&neighbourhood), corner);
+
+        assert!(dual < 0.1, "The closest vertex is {} from the corner", dual);
+        assert!(dual < marched);
+    }
+}
@@ -0,0 +1,137 @@
+use crate::meshing::{ChunkNeighbourhood, MeshData};
+
+/// Builds a blocky mesh of a chunk's solid voxels, in the chunk's local space. Each voxel fills
+/// the unit cube from its position, and only faces open to empty space are kept. Faces in the same
+/// plane with the same material are merged into as few rectangles as possible.
+///
+/// A chunk meshes the faces of its own voxels, including those facing into the chunks around it,
+/// so every face belongs to exactly one chunk. Coarse chunks keep every face on their borders, as
+/// the chunks beside them may be finer and not cover the same ground.
+pub fn greedy_mesh(neighbourhood: &ChunkNeighbourhood) -> MeshData {
+    let mut mesh = MeshData::default();
+    let size = neighbourhood.cells();
+    let scale = neighbourhood.scale();
+
+    for axis in 0..3 {
+        // The two axes across each slice, in the order that makes their cross product `axis`
+        let u = (axis + 1) % 3;
+        let v = (axis + 2) % 3;
+
+        for &direction in [-1, 1].iter() {
+            for layer in 0..size {
+                // The material of each face open in this direction, row by row
+                let mut faces: Vec<Option<u8>> = Vec::with_capacity((size * size) as usize);
+
+                for j in 0..size {
+                    for i in 0..size {
+                        let mut position = [0; 3];
+                        position[axis] = layer;
+                        position[u] = i;
+                        position[v] = j;
+
+                        let voxel = neighbourhood.get(position[0], position[1], position[2]);
+                        position[axis] += direction;
+                        let facing = neighbourhood.get(position[0], position[1], position[2]);
+                        let border = scale > 1 && (position[axis] < 0 || position[axis] >= size);
+
+                        faces.push(if voxel.is_solid() && (border || !facing.is_solid()) { Some(voxel.material) } else { None });
+                    }
+                }
+
+                // The face sits on the voxel's far side when looking along the axis
+                let plane = if direction > 0 { layer + 1 } else { layer };
+                merge_faces(&mut mesh, &mut faces, size as usize, [axis, u, v], plane * scale, direction, scale);
+            }
+        }
+    }
+
+    mesh
+}
+
+/// Covers a slice of faces with rectangles, growing each as far along `u` as it goes and then as
+/// far along `v` as the whole row allows. Faces are cleared as they're covered.
+fn merge_faces(
+    mesh: &mut MeshData,
+    faces: &mut [Option<u8>],
+    size: usize,
+    axes: [usize; 3],
+    plane: i32,
+    direction: i32,
+    scale: i32
+) {
+    let index = |i: usize, j: usize| j * size + i;
+
+    for j in 0..size {
+        let mut i = 0;
+
+        while i < size {
+            let material = match faces[index(i, j)] {
+                Some(material) => material,
+                None => {
+                    i += 1;
+                    continue;
+                }
+            };
+
+            let mut width = 1;
+            while i + width < size && faces[index(i + width, j)] == Some(material) {
+                width += 1;
+            }
+
+            let mut height = 1;
+            while j + height < size && (i..i + width).all(|k| faces[index(k, j + height)] == Some(material)) {
+                height += 1;
+            }
+
+            for row in j..j + height {
+                for column in i..i + width {
+                    faces[index(column, row)] = None;
+                }
+            }
+
+            let (start, extent) = ([i * scale as usize, j * scale as usize], [width * scale as usize, height * scale as usize]);
+            push_quad(mesh, axes, plane, direction, start, extent, material);
+            i += width;
+        }
+    }
+}
+
+fn push_quad(
+    mesh: &mut MeshData,
+    axes: [usize; 3],
+    plane: i32,
+    direction: i32,
+    start: [usize; 2],
+    extent: [usize; 2],
+    material: u8
+) {
+    let [axis, u, v] = axes;
+    let corners = [
+        [start[0], start[1]],
+        [start[0] + extent[0], start[1]],
+        [start[0] + extent[0], start[1] + extent[1]],
+        [start[0], start[1] + extent[1]]
+    ];
+
+    let mut normal = [0.0; 3];
+    normal[axis] = direction as f32;
+    let first = mesh.positions.len() as u32;
+
+    for corner in corners.iter() {
+        let mut position = [0.0; 3];
+        position[axis] = plane as f32;
+        position[u] = corner[0] as f32;
+        position[v] = corner[1] as f32;
+
+        mesh.positions.push(position);
+        mesh.normals.push(normal);
+        mesh.materials.push(material);
+    }
+
+    // The corners go counter-clockwise seen from the positive end of the axis
+    if direction > 0 {
+        mesh.indices.extend_from_slice(&[first, first + 1, first + 2, first, first + 2, first + 3]);
+    } else {
+        mesh.indices.extend_from_slice(&[first, first + 2, first + 1, first, first + 3, first + 2]);
+    }
+}
@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+
+use crate::meshing::{ChunkNeighbourhood, MeshData};
+use crate::meshing::cell::*;
+
+/// The faces of a cell by their corners, counter-clockwise as seen from outside the cell
+const FACES: [[usize; 4]; 6] = [
+    [0, 4, 6, 2], [1, 3, 7, 5],
+    [0, 1, 5, 4], [2, 6, 7, 3],
+    [0, 2, 3, 1], [4, 5, 7, 6]
+];
+
+/// The longest loop triangulated as a fan. Longer loops can tunnel through a face twice, where a
+/// fan would lay triangles flat across the face on top of the neighbouring cell's.
+const MAX_FAN_LENGTH: usize = 5;
+
+/// Identifies an edge of the voxel grid by its lowest point and the axis it runs along, so cells
+/// sharing an edge share its vertex
+type EdgeKey = (i32, i32, i32, usize);
+
+fn edge_between(a: usize, b: usize) -> usize {
+    let (a, b) = (a.min(b), a.max(b));
+    EDGES.iter().position(|edge| *edge == (a, b)).unwrap()
+}
+
+/// Builds a mesh of the surface through a chunk's cells, in the chunk's local space. Each voxel is
+/// a corner of the grid, so a chunk's cells run from its own voxels to the first voxels of the
+/// chunks after it. Neighbouring chunks find the same crossings on the faces they share and place
+/// the same vertices there, so their meshes join without cracks.
+///
+/// Rather than looking the surface up in a table, each cell traces where it crosses its faces and
+/// joins the crossings into loops. Faces where the surface could pass either way are settled by
+/// the density at their centre, which both cells sharing the face agree on.
+pub fn marching_cubes(neighbourhood: &ChunkNeighbourhood) -> MeshData {
+    let mut mesh = MeshData::default();
+    let mut vertices: HashMap<EdgeKey, u32> = HashMap::new();
+    let size = neighbourhood.cells();
+
+    for z in 0..size {
+        for y in 0..size {
+            for x in 0..size {
+                let densities: Vec<f32> = CORNERS.iter()
+                    .map(|[cx, cy, cz]| neighbourhood.density(x + cx, y + cy, z + cz))
+                    .collect();
+
+                let inside: Vec<bool> = densities.iter().map(|density| *density > 0.0).collect();
+
+                if inside.iter().all(|corner| *corner == inside[0]) {
+                    continue;
+                }
+
+                for polygon in cell_polygons(&densities, &inside) {
+                    let indices: Vec<u32> = polygon.iter()
+                        .map(|edge| edge_vertex(neighbourhood, &mut mesh, &mut vertices, [x, y, z], *edge))
+                        .collect();
+
+                    // Loops run clockwise as seen from outside the ground
+                    if indices.len() <= MAX_FAN_LENGTH {
+                        for i in 1..indices.len() - 1 {
+                            mesh.indices.extend_from_slice(&[indices[0], indices[i + 1], indices[i]]);
+                        }
+                    } else {
+                        let centre = centre_vertex(&mut mesh, &indices);
+
+                        for i in 0..indices.len() {
+                            mesh.indices.extend_from_slice(&[centre, indices[(i + 1) % indices.len()], indices[i]]);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    mesh
+}
+
+/// The loops of edges the surface crosses in one cell. Each face's crossings are joined from
+/// the edge leaving the ground to the edge entering it, going around the face, so a cell sharing
+/// the face joins them the other way and every loop winds the same way around the ground.
+fn cell_polygons(densities: &[f32], inside: &[bool]) -> Vec<Vec<usize>> {
+    // Every crossed edge lies on two faces, leading out of it on one and into it on the other
+    let mut next: [Option<usize>; 12] = [None; 12];
+
+    for face in FACES.iter() {
+        let edge = |k: usize| edge_between(face[k], face[(k + 1) % 4]);
+        let leaving = |k: usize| inside[face[k]] && !inside[face[(k + 1) % 4]];
+        let crossed: Vec<usize> = (0..4).filter(|k| inside[face[*k]] != inside[face[(k + 1) % 4]]).collect();
+
+        match crossed.len() {
+            2 => {
+                let (from, to) = if leaving(crossed[0]) { (crossed[0], crossed[1]) } else { (crossed[1], crossed[0]) };
+                next[edge(from)] = Some(edge(to));
+            },
+            4 => {
+                // Diagonal corners match, so cut off the pair the face's centre doesn't side with.
+                // Summed in sorted order, as the cell on the other side lists the corners
+                // differently and must come to the same answer.
+                let mut corners: Vec<f32> = face.iter().map(|corner| densities[*corner]).collect();
+                corners.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                let centre = corners.iter().sum::<f32>() > 0.0;
+
+                for k in 0..4 {
+                    if inside[face[k]] != centre {
+                        let (before, after) = (edge((k + 3) % 4), edge(k));
+
+                        if inside[face[k]] {
+                            next[after] = Some(before);
+                        } else {
+                            next[before] = Some(after);
+                        }
+                    }
+                }
+            },
+            _ => {}
+        }
+    }
+
+    let mut visited = [false; 12];
+    let mut polygons = vec![];
+
+    for start in 0..12 {
+        if visited[start] || next[start].is_none() {
+            continue;
+        }
+
+        let mut polygon = vec![];
+        let mut current = start;
+
+        while !visited[current] {
+            visited[current] = true;
+            polygon.push(current);
+            current = next[current].unwrap();
+        }
+
+        polygons.push(polygon);
+    }
+
+    polygons
+}
+
+/// The index of the vertex where the surface crosses one of a cell's edges, adding it the first
+/// time the edge is crossed
+fn edge_vertex(
+    neighbourhood: &ChunkNeighbourhood,
+    mesh: &mut MeshData,
+    vertices: &mut HashMap<EdgeKey, u32>,
+    cell: [i32; 3],
+    edge: usize
+) -> u32 {
+    let (a, _) = EDGES[edge];
+    let axis = edge / 4;
+    let start = [cell[0] + CORNERS[a][0], cell[1] + CORNERS[a][1], cell[2] + CORNERS[a][2]];
+    let key = (start[0], start[1], start[2], axis);
+
+    if let Some(index) = vertices.get(&key) {
+        return *index;
+    }
+
+    let (t, normal) = edge_crossing(neighbourhood, start, axis);
+
+    let mut position = [start[0] as f32, start[1] as f32, start[2] as f32];
+    position[axis] += t;
+    let scale = neighbourhood.scale() as f32;
+    let position = [position[0] * scale, position[1] * scale, position[2] * scale];
+
+    let index = mesh.positions.len() as u32;
+    mesh.positions.push(position);
+    mesh.normals.push(normal);
+    mesh.materials.push(edge_material(neighbourhood, start, axis));
+    vertices.insert(key, index);
+
+    index
+}
+
+/// Adds a vertex at the middle of a loop, for it to be split into triangles around. It takes the
+/// material of the loop's first vertex.
+fn centre_vertex(mesh: &mut MeshData, loop_indices: &[u32]) -> u32 {
+    let mut position = [0.0; 3];
+    let mut normal = [0.0; 3];
+
+    for index in loop_indices.iter() {
+        for axis in 0..3 {
+            position[axis] += mesh.positions[*index as usize][axis] / loop_indices.len() as f32;
+            normal[axis] += mesh.normals[*index as usize][axis];
+        }
+    }
+
+    let index = mesh.positions.len() as u32;
+    mesh.positions.push(position);
+    mesh.normals.push(normalize(normal));
+    mesh.materials.push(mesh.materials[loop_indices[0] as usize]);
+
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::*;
+    use crate::resources::VoxelWorld;
+    use super::*;
+
+    /// Rolling ground crossing the borders between the chunks around the origin
+    fn rolling_world() -> VoxelWorld {
+        let mut world = VoxelWorld::default();
+
+        for z in -1..=2 {
+            for y in -1..=2 {
+                for x in -1..=2 {
+                    let origin = ChunkPos(x, y, z).origin();
+                    let chunk = Chunk::from_fn(|LocalVoxelPos(x, y, z)| {
+                        let (x, y, z) = (origin.0 + x as i32, origin.1 + y as i32, origin.2 + z as i32);
+                        let height = 15.0 + 5.0 * (x as f32 * 0.3).sin() + 4.0 * (z as f32 * 0.23).cos();
+                        let material = if x + z > 16 { STONE } else { DIRT };
+
+                        Voxel::from_density(material, (height - y as f32) / 4.0)
+                    });
+
+                    world.insert_chunk(ChunkPos(x, y, z), chunk);
+                }
+            }
+        }
+
+        world
+    }
+
+    /// The distinct vertices of a mesh on the plane where `axis` is `at`, moved by `offset` along
+    /// it, as exact bits so they can be compared. Where the surface passes right through a voxel,
+    /// each edge through it crossing the surface places a vertex there, so only one is kept.
+    fn vertices_on_plane(mesh: &MeshData, axis: usize, at: f32, offset: f32) -> Vec<([u32; 3], [u32; 3], u8)> {
+        let mut vertices: Vec<([u32; 3], [u32; 3], u8)> = (0..mesh.positions.len())
+            .filter(|index| mesh.positions[*index][axis] == at)
+            .map(|index| {
+                let mut position = mesh.positions[index];
+                position[axis] += offset;
+
+                let normal = mesh.normals[index];
+                (
+                    [position[0].to_bits(), position[1].to_bits(), position[2].to_bits()],
+                    [normal[0].to_bits(), normal[1].to_bits(), normal[2].to_bits()],
+                    mesh.materials[index]
+                )
+            })
+            .collect();
+
+        vertices.sort();
+        vertices.dedup();
+        vertices
+    }
+
+    #[test]
+    fn neighbouring_chunks_share_vertices_where_they_meet() {
+        let world = rolling_world();
+        let position = ChunkPos(0, 0, 0);
+        let mesh = marching_cubes(&ChunkNeighbourhood::from_world(&world, position).unwrap());
+
+        for axis in 0..3 {
+            let mut offset = [0, 0, 0];
+            offset[axis] = 1;
+
+            let neighbour = position.offset(offset[0], offset[1], offset[2]);
+            let neighbour_mesh = marching_cubes(&ChunkNeighbourhood::from_world(&world, neighbour).unwrap());
+
+            let size = CHUNK_SIZE as f32;
+            let shared = vertices_on_plane(&mesh, axis, size, 0.0);
+            let neighbour_shared = vertices_on_plane(&neighbour_mesh, axis, 0.0, size);
+
+            assert!(!shared.is_empty(), "The surface doesn't cross the border along axis {}", axis);
+            assert_eq!(shared, neighbour_shared, "Vertices differ on the border along axis {}", axis);
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use bevy::render::{
+    mesh::{Indices, Mesh, VertexAttributeValues},
+    pipeline::PrimitiveTopology
+};
+use bevy_rapier3d::rapier::{geometry::ColliderBuilder, na::Point3};
+
+/// The name of the per-vertex material attribute, the material's index as a float
+pub const ATTRIBUTE_MATERIAL: &str = "Vertex_Material";
+
+/// The vertices and triangles a mesher builds, positioned relative to the chunk's origin. Kept
+/// apart from `Mesh` so meshers don't depend on the renderer.
+#[derive(Clone, Debug, Default)]
+pub struct MeshData {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    /// The material of the voxel each vertex was placed for
+    pub materials: Vec<u8>,
+    pub indices: Vec<u32>
+}
+
+impl MeshData {
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// A collider matching the mesh's triangles, relative to the chunk's origin. Building one is
+    /// slow, so it's best done off the main thread.
+    pub fn collider(&self) -> ColliderBuilder {
+        let vertices = self.positions.iter().map(|[x, y, z]| Point3::new(*x, *y, *z)).collect();
+        let triangles = self.indices.chunks(3).map(|triangle| Point3::new(triangle[0], triangle[1], triangle[2])).collect();
+
+        ColliderBuilder::trimesh(vertices, triangles)
+    }
+
+    pub fn into_mesh(self) -> Mesh {
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, VertexAttributeValues::Float3(self.positions));
+        mesh.set_attribute(Mesh::ATTRIBUTE_NORMAL, VertexAttributeValues::Float3(self.normals));
+        mesh.set_attribute(
+            ATTRIBUTE_MATERIAL,
+            VertexAttributeValues::Float(self.materials.into_iter().map(|material| material as f32).collect())
+        );
+        mesh.set_indices(Some(Indices::U32(self.indices)));
+        mesh
+    }
+}
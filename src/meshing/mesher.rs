@@ -0,0 +1,41 @@
+use crate::models::*;
+use crate::meshing::{ChunkNeighbourhood, MeshData, marching_cubes, dual_contouring, greedy_mesh, add_skirts};
+
+/// Meshes a chunk the way its world asks for. Coarse chunks get skirts to cover the cracks where
+/// they meet finer ones, except blocky ones, which keep the faces along their borders instead.
+pub fn build_mesh(neighbourhood: &ChunkNeighbourhood, method: MeshingMethod) -> MeshData {
+    let mut mesh = match method {
+        MeshingMethod::MarchingCubes => marching_cubes(neighbourhood),
+        MeshingMethod::DualContouring => dual_contouring(neighbourhood),
+        MeshingMethod::Greedy => return greedy_mesh(neighbourhood)
+    };
+
+    if neighbourhood.scale() > 1 {
+        add_skirts(&mut mesh, neighbourhood.scale());
+    }
+
+    mesh
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::resources::WorldGenerator;
+    use super::*;
+
+    #[test]
+    fn coarser_chunks_have_fewer_triangles() {
+        let generator = WorldGenerator::new(7);
+
+        for method in [MeshingMethod::MarchingCubes, MeshingMethod::DualContouring, MeshingMethod::Greedy].iter() {
+            let triangles: Vec<usize> = [1, 2, 4, 8].iter()
+                .map(|scale| build_mesh(&ChunkNeighbourhood::from_generator(&generator, ChunkPos(0, 0, 0), *scale), *method).indices.len() / 3)
+                .collect();
+
+            // Each halving of the resolution leaves a quarter of the surface's cells, and skirts
+            // only add a few triangles along the borders
+            for pair in triangles.windows(2) {
+                assert!(pair[1] * 2 < pair[0], "{} triangles at each scale: {:?}", method, triangles);
+            }
+        }
+    }
+}
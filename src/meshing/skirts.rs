@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+
+use crate::models::*;
+use crate::meshing::MeshData;
+use crate::meshing::cell::normalize;
+
+/// How far a skirt hangs from the edge of a coarse mesh, in its cells. The surface of a finer
+/// chunk beside it strays from the coarse one by less than a cell.
+const SKIRT_DEPTH: f32 = 1.0;
+
+/// Hangs a skirt from every open edge of a coarse chunk's mesh, to hide the cracks where it meets
+/// a chunk meshed at another level of detail. Open edges only come where a mesh meets the chunk's
+/// faces, and each skirt reaches into the ground across the face the edge runs along, so it's
+/// only seen through a crack. Skirts are drawn from both sides and lit like the edge they hang
+/// from.
+pub fn add_skirts(mesh: &mut MeshData, scale: i32) {
+    let edge_key = |a: u32, b: u32| (a.min(b), a.max(b));
+    let mut uses: HashMap<(u32, u32), usize> = HashMap::new();
+
+    for triangle in mesh.indices.chunks(3) {
+        for k in 0..3 {
+            *uses.entry(edge_key(triangle[k], triangle[(k + 1) % 3])).or_insert(0) += 1;
+        }
+    }
+
+    let size = CHUNK_SIZE as f32;
+    let depth = SKIRT_DEPTH * scale as f32;
+    let triangles = mesh.indices.len() / 3;
+
+    for triangle in 0..triangles {
+        for k in 0..3 {
+            let a = mesh.indices[triangle * 3 + k];
+            let b = mesh.indices[triangle * 3 + (k + 1) % 3];
+
+            if uses[&edge_key(a, b)] != 1 {
+                continue;
+            }
+
+            // The chunk face nearest the edge's middle
+            let (pa, pb) = (mesh.positions[a as usize], mesh.positions[b as usize]);
+            let face_distance = |axis: usize| {
+                let middle = (pa[axis] + pb[axis]) / 2.0;
+                middle.abs().min((size - middle).abs())
+            };
+            let axis = (0..3).min_by(|i, j| face_distance(*i).partial_cmp(&face_distance(*j)).unwrap()).unwrap();
+
+            let hanging = [hang(mesh, a, axis, depth), hang(mesh, b, axis, depth)];
+            mesh.indices.extend_from_slice(&[a, b, hanging[1], a, hanging[1], hanging[0]]);
+            mesh.indices.extend_from_slice(&[a, hanging[1], b, a, hanging[0], hanging[1]]);
+        }
+    }
+}
+
+/// Adds a vertex below one at the edge of a mesh, moved into the ground along the chunk's face
+fn hang(mesh: &mut MeshData, index: u32, axis: usize, depth: f32) -> u32 {
+    let position = mesh.positions[index as usize];
+    let normal = mesh.normals[index as usize];
+
+    // Straight down if the surface faces straight out of the chunk
+    let mut direction = normal;
+    direction[axis] = 0.0;
+    let direction = normalize(direction);
+
+    let hanging = mesh.positions.len() as u32;
+    mesh.positions.push([
+        position[0] - direction[0] * depth,
+        position[1] - direction[1] * depth,
+        position[2] - direction[2] * depth
+    ]);
+    mesh.normals.push(normal);
+    mesh.materials.push(mesh.materials[index as usize]);
+
+    hanging
+}
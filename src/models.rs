@@ -1,27 +1,67 @@
+mod input_action;
+mod input_binding;
 mod input_command_buffer;
 mod input_command;
 mod command_frame;
-mod command_frame_buffer;
 mod state_frame;
-mod state_frame_buffer;
+mod frame_ring_buffer;
 mod entity_spawn;
+mod player_animation;
+mod player_profile;
 mod synchronized_input;
 mod synchronized_state;
 mod net_client;
 mod net_message;
 mod connection_info;
+mod replay;
+mod world_info;
+mod voxel;
+mod material;
+mod chunk_pos;
+mod chunk_storage;
+mod chunk;
+mod sparse_voxel_octree;
+mod noise_graph;
+mod biome;
+mod cave_settings;
+mod ore;
+mod voxel_write;
+mod meshing_method;
+mod brush;
+mod voxel_edit;
+mod chunk_fragment;
 
 pub use self::{
+    input_action::*,
+    input_binding::*,
     input_command_buffer::*,
     input_command::InputCommand,
     command_frame::*,
-    command_frame_buffer::*,
     state_frame::*,
-    state_frame_buffer::*,
+    frame_ring_buffer::*,
     entity_spawn::*,
+    player_animation::*,
+    player_profile::*,
     synchronized_input::*,
     synchronized_state::*,
     net_client::*,
     net_message::*,
-    connection_info::*
+    connection_info::*,
+    replay::*,
+    world_info::*,
+    voxel::*,
+    material::*,
+    chunk_pos::*,
+    chunk_storage::*,
+    chunk::*,
+    sparse_voxel_octree::*,
+    noise_graph::*,
+    biome::*,
+    cave_settings::*,
+    ore::*,
+    voxel_write::*,
+    meshing_method::*,
+    brush::*,
+    voxel_edit::*,
+    chunk_fragment::*
 };
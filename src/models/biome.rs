@@ -0,0 +1,194 @@
+use serde::{Serialize, Deserialize};
+use crate::models::*;
+
+/// The kinds of land the world is divided into, chosen by the climate of each column
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Biome {
+    Tundra,
+    Taiga,
+    Plains,
+    Forest,
+    Savanna,
+    Desert,
+    Swamp
+}
+
+/// How a biome looks and where it appears
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BiomeDefinition {
+    /// The climate the biome is most likely in, each in the range `-1.0..=1.0`
+    pub temperature: f64,
+    pub humidity: f64,
+    /// The material of the top voxel of the ground
+    pub surface_material: u8,
+    /// The material of the voxels below the surface, down to `subsurface_depth`, before stone
+    pub subsurface_material: u8,
+    pub subsurface_depth: usize,
+    /// Raises the terrain, in chunks
+    pub height_offset: f64,
+    /// Stretches the terrain vertically, so hills are taller above 1.0 and flatter below it
+    pub height_scale: f64,
+    /// How many trees and boulders are placed on each chunk's surface, on average
+    pub trees_per_chunk: f32,
+    pub boulders_per_chunk: f32,
+    pub leaves_material: u8
+}
+
+/// The deepest any biome's subsurface goes
+pub const MAX_SUBSURFACE_DEPTH: usize = 4;
+
+impl Biome {
+    pub const ALL: [Biome; 7] = [
+        Biome::Tundra,
+        Biome::Taiga,
+        Biome::Plains,
+        Biome::Forest,
+        Biome::Savanna,
+        Biome::Desert,
+        Biome::Swamp
+    ];
+
+    pub fn definition(&self) -> BiomeDefinition {
+        match self {
+            Biome::Tundra => BiomeDefinition {
+                temperature: -0.5,
+                humidity: -0.2,
+                surface_material: SNOW,
+                subsurface_material: DIRT,
+                subsurface_depth: 2,
+                height_offset: 0.1,
+                height_scale: 0.9,
+                trees_per_chunk: 0.0,
+                boulders_per_chunk: 0.6,
+                leaves_material: PINE_LEAVES
+            },
+            Biome::Taiga => BiomeDefinition {
+                temperature: -0.4,
+                humidity: 0.3,
+                surface_material: TAIGA_GRASS,
+                subsurface_material: DIRT,
+                subsurface_depth: 3,
+                height_offset: 0.1,
+                height_scale: 1.2,
+                trees_per_chunk: 3.0,
+                boulders_per_chunk: 0.3,
+                leaves_material: PINE_LEAVES
+            },
+            Biome::Plains => BiomeDefinition {
+                temperature: 0.0,
+                humidity: -0.2,
+                surface_material: GRASS,
+                subsurface_material: DIRT,
+                subsurface_depth: 3,
+                height_offset: 0.0,
+                height_scale: 0.7,
+                trees_per_chunk: 0.3,
+                boulders_per_chunk: 0.5,
+                leaves_material: LEAVES
+            },
+            Biome::Forest => BiomeDefinition {
+                temperature: 0.05,
+                humidity: 0.3,
+                surface_material: FOREST_GRASS,
+                subsurface_material: DIRT,
+                subsurface_depth: 4,
+                height_offset: 0.0,
+                height_scale: 1.0,
+                trees_per_chunk: 5.0,
+                boulders_per_chunk: 0.2,
+                leaves_material: LEAVES
+            },
+            Biome::Savanna => BiomeDefinition {
+                temperature: 0.4,
+                humidity: 0.0,
+                surface_material: DRY_GRASS,
+                subsurface_material: DIRT,
+                subsurface_depth: 2,
+                height_offset: 0.0,
+                height_scale: 0.8,
+                trees_per_chunk: 0.6,
+                boulders_per_chunk: 0.4,
+                leaves_material: LEAVES
+            },
+            Biome::Desert => BiomeDefinition {
+                temperature: 0.5,
+                humidity: -0.4,
+                surface_material: SAND,
+                subsurface_material: SANDSTONE,
+                subsurface_depth: 4,
+                height_offset: -0.05,
+                height_scale: 0.6,
+                trees_per_chunk: 0.0,
+                boulders_per_chunk: 0.4,
+                leaves_material: LEAVES
+            },
+            Biome::Swamp => BiomeDefinition {
+                temperature: 0.3,
+                humidity: 0.5,
+                surface_material: MUD,
+                subsurface_material: DIRT,
+                subsurface_depth: 3,
+                height_offset: -0.15,
+                height_scale: 0.4,
+                trees_per_chunk: 1.5,
+                boulders_per_chunk: 0.0,
+                leaves_material: LEAVES
+            }
+        }
+    }
+}
+
+/// How much each biome contributes to one column, so terrain can blend across biome borders
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BiomeBlend {
+    /// The weight of each biome in `Biome::ALL`, which add up to 1.0
+    weights: [f64; Biome::ALL.len()]
+}
+
+impl BiomeBlend {
+    /// Normalizes the weights, so only their proportions matter
+    pub fn new(mut weights: [f64; Biome::ALL.len()]) -> BiomeBlend {
+        let total: f64 = weights.iter().sum();
+
+        for weight in weights.iter_mut() {
+            *weight /= total;
+        }
+
+        BiomeBlend {
+            weights
+        }
+    }
+
+    pub fn weight(&self, biome: Biome) -> f64 {
+        let index = Biome::ALL.iter().position(|other| *other == biome).unwrap();
+        self.weights[index]
+    }
+
+    /// The biome with the most weight, which decides the column's materials
+    pub fn dominant(&self) -> Biome {
+        let mut dominant = 0;
+
+        for (index, weight) in self.weights.iter().enumerate() {
+            if *weight > self.weights[dominant] {
+                dominant = index;
+            }
+        }
+
+        Biome::ALL[dominant]
+    }
+
+    pub fn height_offset(&self) -> f64 {
+        self.blend(|definition| definition.height_offset)
+    }
+
+    pub fn height_scale(&self) -> f64 {
+        self.blend(|definition| definition.height_scale)
+    }
+
+    fn blend(&self, value: impl Fn(&BiomeDefinition) -> f64) -> f64 {
+        Biome::ALL.iter()
+            .zip(self.weights.iter())
+            .map(|(biome, weight)| value(&biome.definition()) * weight)
+            .sum()
+    }
+}
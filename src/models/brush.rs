@@ -0,0 +1,129 @@
+use serde::{Serialize, Deserialize};
+use crate::models::*;
+
+/// The region a brush covers
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum BrushShape {
+    Sphere,
+    /// An axis aligned cube, `radius` voxels from its centre to each face
+    Cube
+}
+
+/// What a brush does to the voxels it covers
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum BrushMode {
+    /// Fills the shape with a material
+    Add(u8),
+    /// Empties the shape
+    Remove,
+    /// Raises the density by up to `strength`, most at the centre and fading to nothing at the
+    /// edge, so repeated strokes build up a smooth mound. Voxels it makes solid take the material.
+    SmoothAdd(u8),
+    /// Lowers the density the way `SmoothAdd` raises it
+    SmoothRemove
+}
+
+/// An edit to every voxel within a shape, in world space
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Brush {
+    pub shape: BrushShape,
+    pub mode: BrushMode,
+    pub center: [f32; 3],
+    pub radius: f32,
+    /// How much a smooth brush changes the density in one stroke, in the range `0.0..=1.0`
+    pub strength: f32
+}
+
+impl Brush {
+    /// The lowest and highest voxel the brush can change
+    pub fn bounds(&self) -> (VoxelPos, VoxelPos) {
+        // One voxel further, as the surface's density reaches a voxel beyond the shape
+        let reach = self.radius + 1.0;
+        let min = |axis: usize| (self.center[axis] - reach).floor() as i32;
+        let max = |axis: usize| (self.center[axis] + reach).ceil() as i32;
+
+        (VoxelPos(min(0), min(1), min(2)), VoxelPos(max(0), max(1), max(2)))
+    }
+
+    /// Every chunk holding a voxel the brush can change
+    pub fn chunks(&self) -> Vec<ChunkPos> {
+        let (min, max) = self.bounds();
+        let (min, max) = (min.chunk(), max.chunk());
+        let mut chunks = vec![];
+
+        for z in min.2..=max.2 {
+            for y in min.1..=max.1 {
+                for x in min.0..=max.0 {
+                    chunks.push(ChunkPos(x, y, z));
+                }
+            }
+        }
+
+        chunks
+    }
+
+    /// Whether the brush is one players may use: finite, no bigger than `max_radius`, and only
+    /// adding materials that exist
+    pub fn is_valid(&self, max_radius: f32) -> bool {
+        let material = match self.mode {
+            BrushMode::Add(material) | BrushMode::SmoothAdd(material) => Some(material),
+            BrushMode::Remove | BrushMode::SmoothRemove => None
+        };
+
+        self.center.iter().all(|value| value.is_finite())
+            && self.radius.is_finite() && self.radius > 0.0 && self.radius <= max_radius
+            && self.strength.is_finite() && self.strength >= 0.0 && self.strength <= 1.0
+            && material.map_or(true, |material| material != AIR && (material as usize) < MATERIAL_COUNT)
+    }
+
+    /// Whether the brush can change the voxel at a position. Past a voxel beyond its shape it
+    /// leaves every voxel as it was.
+    pub fn reaches(&self, position: VoxelPos) -> bool {
+        self.distance(position) < self.radius + 1.0
+    }
+
+    /// Whether the brush leaves the voxel at a position empty whatever was there before, so no
+    /// earlier edit to it matters any more
+    pub fn clears(&self, position: VoxelPos) -> bool {
+        self.mode == BrushMode::Remove && self.radius - self.distance(position) >= 1.0
+    }
+
+    /// The voxel the brush leaves at a position, given the voxel there now
+    pub fn apply(&self, position: VoxelPos, voxel: Voxel) -> Voxel {
+        let distance = self.distance(position);
+
+        // The brush's own density, positive inside the shape and falling off over a voxel past it
+        let inside = (self.radius - distance).max(-1.0).min(1.0);
+        // How strongly a smooth brush acts, from all of its strength at the centre to none at the edge
+        let falloff = if self.radius > 0.0 { (1.0 - distance / self.radius).max(0.0) } else { 0.0 };
+        let strength = self.strength.max(0.0).min(1.0) * falloff;
+
+        let density = voxel.density();
+
+        match self.mode {
+            BrushMode::Add(material) if inside > density => Voxel::from_density(material, inside),
+            BrushMode::Add(_) => voxel,
+            BrushMode::Remove => Voxel::from_density(voxel.material, density.min(-inside)),
+            BrushMode::SmoothAdd(material) => {
+                let material = if voxel.is_solid() { voxel.material } else { material };
+                Voxel::from_density(material, density + strength)
+            },
+            BrushMode::SmoothRemove => Voxel::from_density(voxel.material, density - strength)
+        }
+    }
+
+    /// How far a voxel's centre is from the brush's centre, by the brush's shape
+    fn distance(&self, position: VoxelPos) -> f32 {
+        // Voxels are measured from their centres
+        let offset = [
+            position.0 as f32 + 0.5 - self.center[0],
+            position.1 as f32 + 0.5 - self.center[1],
+            position.2 as f32 + 0.5 - self.center[2]
+        ];
+
+        match self.shape {
+            BrushShape::Sphere => (offset[0] * offset[0] + offset[1] * offset[1] + offset[2] * offset[2]).sqrt(),
+            BrushShape::Cube => offset[0].abs().max(offset[1].abs()).max(offset[2].abs())
+        }
+    }
+}
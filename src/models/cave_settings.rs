@@ -0,0 +1,83 @@
+use std::fs::File;
+use std::path::Path;
+use serde::{Serialize, Deserialize};
+
+/// Controls how caves are carved out of the terrain. Each kind of cave is only carved between
+/// its own heights, and none come closer to open air than `surface_margin`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CaveSettings {
+    /// How many voxels of ground are always left between a cave and open space above or beside
+    /// it, so caves only open where the terrain already has an overhang
+    pub surface_margin: usize,
+    /// Large open caverns where 3D noise is high
+    pub cheese: Option<NoiseCaves>,
+    /// Long winding tunnels where two 3D noise fields are both close to zero
+    pub spaghetti: Option<NoiseCaves>,
+    /// Tunnels which wander from a random start, placed per region of the world
+    pub worms: Option<WormCaves>
+}
+
+/// The heights a kind of cave is carved between, in voxels. Caves taper shut towards the ends
+/// of the range.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CaveRange {
+    pub min_height: i32,
+    pub max_height: i32
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NoiseCaves {
+    pub range: CaveRange,
+    /// The size of the noise features, as the number of features per voxel
+    pub frequency: f64,
+    /// For cheese caves, how high the noise must be to carve. For spaghetti caves, how close to
+    /// zero both noise fields must be, which sets how wide the tunnels are.
+    pub threshold: f64
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WormCaves {
+    pub range: CaveRange,
+    /// The chance each region of the world has of starting a worm
+    pub chance: f32,
+    /// How many voxels each worm travels, at most the size of a region
+    pub length: usize,
+    /// The radius of the tunnel, in voxels
+    pub radius: f32
+}
+
+impl Default for CaveSettings {
+    fn default() -> Self {
+        CaveSettings {
+            surface_margin: 6,
+            cheese: Some(NoiseCaves {
+                range: CaveRange { min_height: -512, max_height: -48 },
+                frequency: 1.0 / 48.0,
+                threshold: 0.45
+            }),
+            spaghetti: Some(NoiseCaves {
+                range: CaveRange { min_height: -256, max_height: 16 },
+                frequency: 1.0 / 64.0,
+                threshold: 0.05
+            }),
+            worms: Some(WormCaves {
+                range: CaveRange { min_height: -128, max_height: 24 },
+                chance: 0.5,
+                length: 96,
+                radius: 2.5
+            })
+        }
+    }
+}
+
+impl CaveSettings {
+    pub fn load(path: impl AsRef<Path>) -> ron::Result<CaveSettings> {
+        let file = File::open(path).map_err(ron::Error::from)?;
+        ron::de::from_reader(file)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> ron::Result<()> {
+        let serialized = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(path, serialized).map_err(ron::Error::from)
+    }
+}
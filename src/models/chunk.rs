@@ -0,0 +1,68 @@
+use crate::models::*;
+
+/// The voxels of one chunk
+#[derive(Clone, Debug, Default)]
+pub struct Chunk {
+    storage: ChunkStorage
+}
+
+impl Chunk {
+    pub fn filled(voxel: Voxel) -> Chunk {
+        Chunk {
+            storage: ChunkStorage::Uniform(voxel)
+        }
+    }
+
+    /// Builds a chunk by asking for the voxel at every local position
+    pub fn from_fn(mut f: impl FnMut(LocalVoxelPos) -> Voxel) -> Chunk {
+        let voxels: Vec<Voxel> = (0..CHUNK_VOLUME).map(|index| f(Chunk::position(index))).collect();
+
+        Chunk {
+            storage: ChunkStorage::from_voxels(&voxels)
+        }
+    }
+
+    pub fn from_storage(storage: ChunkStorage) -> Chunk {
+        Chunk {
+            storage
+        }
+    }
+
+    pub fn storage(&self) -> &ChunkStorage {
+        &self.storage
+    }
+
+    pub fn get(&self, position: LocalVoxelPos) -> Voxel {
+        self.storage.get(Chunk::index(position))
+    }
+
+    /// Replaces a voxel, returning the one that was there before
+    pub fn set(&mut self, position: LocalVoxelPos, voxel: Voxel) -> Voxel {
+        self.storage.set(Chunk::index(position), voxel)
+    }
+
+    /// Whether every voxel in the chunk is empty space
+    pub fn is_empty(&self) -> bool {
+        !self.storage.any(Voxel::is_solid)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (LocalVoxelPos, Voxel)> + '_ {
+        (0..CHUNK_VOLUME).map(move |index| (Chunk::position(index), self.storage.get(index)))
+    }
+
+    /// Voxels are stored with x varying fastest, then z, then y, so horizontal slices are contiguous
+    fn index(position: LocalVoxelPos) -> usize {
+        let LocalVoxelPos(x, y, z) = position;
+        debug_assert!(x < CHUNK_SIZE && y < CHUNK_SIZE && z < CHUNK_SIZE);
+
+        (y * CHUNK_SIZE + z) * CHUNK_SIZE + x
+    }
+
+    fn position(index: usize) -> LocalVoxelPos {
+        LocalVoxelPos(
+            index % CHUNK_SIZE,
+            index / (CHUNK_SIZE * CHUNK_SIZE),
+            (index / CHUNK_SIZE) % CHUNK_SIZE
+        )
+    }
+}
@@ -0,0 +1,133 @@
+use serde::{Serialize, Deserialize};
+use crate::models::*;
+
+/// The most bytes of a chunk's voxels sent in one message, which keeps each message inside a
+/// single packet
+pub const CHUNK_FRAGMENT_SIZE: usize = 960;
+
+/// The most fragments a chunk's voxels are split into
+pub const MAX_CHUNK_FRAGMENTS: usize = (MAX_ENCODED_CHUNK_SIZE + CHUNK_FRAGMENT_SIZE - 1) / CHUNK_FRAGMENT_SIZE;
+
+/// One piece of the voxels of a chunk the server is sending, which are palette compressed
+/// `ChunkStorage` encoded with bincode
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ChunkFragment {
+    pub position: ChunkPos,
+    /// The revision the chunk was at when it was sent
+    pub revision: u64,
+    pub index: u16,
+    pub count: u16,
+    pub bytes: Vec<u8>
+}
+
+impl ChunkFragment {
+    /// Splits a chunk's voxels into fragments to send in order
+    pub fn split(position: ChunkPos, revision: u64, chunk: &Chunk) -> Vec<ChunkFragment> {
+        let bytes = bincode::serialize(chunk.storage()).unwrap();
+        let count = (bytes.len() + CHUNK_FRAGMENT_SIZE - 1) / CHUNK_FRAGMENT_SIZE;
+
+        bytes.chunks(CHUNK_FRAGMENT_SIZE)
+            .enumerate()
+            .map(|(index, bytes)| ChunkFragment {
+                position,
+                revision,
+                index: index as u16,
+                count: count as u16,
+                bytes: bytes.to_vec()
+            })
+            .collect()
+    }
+}
+
+/// The fragments of one chunk received so far
+#[derive(Clone, Debug)]
+pub struct ChunkAssembly {
+    revision: u64,
+    fragments: Vec<Option<Vec<u8>>>
+}
+
+impl ChunkAssembly {
+    /// Starts putting a chunk together. No chunk takes more than `MAX_CHUNK_FRAGMENTS`, so an
+    /// assembly expecting more never accepts a fragment, and takes no more room than one that does.
+    pub fn new(revision: u64, count: u16) -> ChunkAssembly {
+        ChunkAssembly {
+            revision,
+            fragments: vec![None; (count as usize).min(MAX_CHUNK_FRAGMENTS)]
+        }
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Whether a fragment belongs to the chunk being put together
+    pub fn accepts(&self, fragment: &ChunkFragment) -> bool {
+        fragment.revision == self.revision
+            && fragment.count as usize == self.fragments.len()
+            && (fragment.index as usize) < self.fragments.len()
+            && fragment.bytes.len() <= CHUNK_FRAGMENT_SIZE
+    }
+
+    /// Adds a fragment, returning the chunk once every fragment has arrived, or an error if its
+    /// voxels are corrupt. Fragments the assembly doesn't accept are ignored.
+    pub fn add(&mut self, fragment: ChunkFragment) -> Option<bincode::Result<Chunk>> {
+        if !self.accepts(&fragment) {
+            return None;
+        }
+
+        self.fragments[fragment.index as usize] = Some(fragment.bytes);
+
+        if self.fragments.iter().any(Option::is_none) {
+            return None;
+        }
+
+        let bytes: Vec<u8> = self.fragments.iter().flatten().flatten().cloned().collect();
+        Some(bincode::deserialize::<ChunkStorage>(&bytes).map(Chunk::from_storage))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assemble(fragments: Vec<ChunkFragment>) -> Option<bincode::Result<Chunk>> {
+        let mut assembly = ChunkAssembly::new(fragments[0].revision, fragments[0].count);
+        fragments.into_iter().filter_map(|fragment| assembly.add(fragment)).next()
+    }
+
+    #[test]
+    fn fragments_put_the_chunk_back_together() {
+        let voxels: Vec<Voxel> = (0..CHUNK_VOLUME)
+            .map(|index| Voxel { material: (index % 7) as u8, density: (index % 200) as i8 })
+            .collect();
+        let chunk = Chunk::from_storage(ChunkStorage::from_voxels(&voxels));
+
+        let fragments = ChunkFragment::split(ChunkPos(1, 2, 3), 9, &chunk);
+        assert!(fragments.len() > 1 && fragments.len() <= MAX_CHUNK_FRAGMENTS);
+
+        let assembled = assemble(fragments).unwrap().unwrap();
+        assert!(assembled.iter().map(|(_, voxel)| voxel).eq(voxels.into_iter()));
+    }
+
+    #[test]
+    fn corrupt_or_oversized_chunks_are_turned_down() {
+        let voxels: Vec<Voxel> = (0..CHUNK_VOLUME).map(|index| Voxel::solid((index % 3) as u8)).collect();
+        let chunk = Chunk::from_storage(ChunkStorage::from_voxels(&voxels));
+
+        let mut fragments = ChunkFragment::split(ChunkPos(0, 0, 0), 1, &chunk);
+        let last = fragments.last_mut().unwrap();
+        last.bytes.truncate(last.bytes.len() - 8);
+        assert!(assemble(fragments).unwrap().is_err());
+
+        let fragment = ChunkFragment {
+            position: ChunkPos(0, 0, 0),
+            revision: 1,
+            index: 0,
+            count: u16::MAX,
+            bytes: vec![]
+        };
+        let mut assembly = ChunkAssembly::new(fragment.revision, fragment.count);
+        assert!(!assembly.accepts(&fragment));
+        assert!(assembly.add(fragment).is_none());
+    }
+}
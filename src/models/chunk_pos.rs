@@ -0,0 +1,100 @@
+use bevy::math::Vec3;
+use serde::{Serialize, Deserialize};
+
+/// The number of voxels along each edge of a chunk
+pub const CHUNK_SIZE: usize = 16;
+pub const CHUNK_VOLUME: usize = CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE;
+
+/// The position of a chunk, in chunks
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ChunkPos(pub i32, pub i32, pub i32);
+
+/// The position of a voxel in the world, in voxels
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct VoxelPos(pub i32, pub i32, pub i32);
+
+/// The position of a voxel within its chunk, each axis in `0..CHUNK_SIZE`
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct LocalVoxelPos(pub usize, pub usize, pub usize);
+
+impl ChunkPos {
+    /// The chunk containing a point in world space
+    pub fn from_world(position: Vec3) -> ChunkPos {
+        VoxelPos::from_world(position).chunk()
+    }
+
+    /// The voxel at this chunk's minimum corner
+    pub fn origin(&self) -> VoxelPos {
+        let size = CHUNK_SIZE as i32;
+        VoxelPos(self.0 * size, self.1 * size, self.2 * size)
+    }
+
+    /// The world space position of this chunk's minimum corner
+    pub fn translation(&self) -> Vec3 {
+        self.origin().translation()
+    }
+
+    pub fn offset(&self, x: i32, y: i32, z: i32) -> ChunkPos {
+        ChunkPos(self.0 + x, self.1 + y, self.2 + z)
+    }
+
+    /// The six chunks sharing a face with this one
+    pub fn neighbours(&self) -> [ChunkPos; 6] {
+        [
+            self.offset(-1, 0, 0),
+            self.offset(1, 0, 0),
+            self.offset(0, -1, 0),
+            self.offset(0, 1, 0),
+            self.offset(0, 0, -1),
+            self.offset(0, 0, 1)
+        ]
+    }
+
+    /// The largest distance along any axis to another chunk, in chunks
+    pub fn chebyshev_distance(&self, other: ChunkPos) -> i32 {
+        (self.0 - other.0).abs()
+            .max((self.1 - other.1).abs())
+            .max((self.2 - other.2).abs())
+    }
+}
+
+impl VoxelPos {
+    /// The voxel containing a point in world space
+    pub fn from_world(position: Vec3) -> VoxelPos {
+        VoxelPos(
+            position.x.floor() as i32,
+            position.y.floor() as i32,
+            position.z.floor() as i32
+        )
+    }
+
+    pub fn chunk(&self) -> ChunkPos {
+        let size = CHUNK_SIZE as i32;
+        ChunkPos(self.0.div_euclid(size), self.1.div_euclid(size), self.2.div_euclid(size))
+    }
+
+    pub fn local(&self) -> LocalVoxelPos {
+        let size = CHUNK_SIZE as i32;
+        LocalVoxelPos(
+            self.0.rem_euclid(size) as usize,
+            self.1.rem_euclid(size) as usize,
+            self.2.rem_euclid(size) as usize
+        )
+    }
+
+    /// The world space position of this voxel's minimum corner
+    pub fn translation(&self) -> Vec3 {
+        Vec3::new(self.0 as f32, self.1 as f32, self.2 as f32)
+    }
+
+    pub fn offset(&self, x: i32, y: i32, z: i32) -> VoxelPos {
+        VoxelPos(self.0 + x, self.1 + y, self.2 + z)
+    }
+}
+
+impl LocalVoxelPos {
+    /// The world position of this voxel within `chunk`
+    pub fn to_world(&self, chunk: ChunkPos) -> VoxelPos {
+        chunk.origin().offset(self.0 as i32, self.1 as i32, self.2 as i32)
+    }
+}
@@ -0,0 +1,489 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use serde::{Serialize, Deserialize};
+use crate::models::*;
+
+/// The fewest bits used for each packed palette index
+const MIN_BITS_PER_INDEX: u8 = 4;
+
+/// The most bits used for each packed palette index. A palette never needs more entries than a
+/// chunk has voxels, which fit in 13 bits.
+const MAX_BITS_PER_INDEX: u8 = 16;
+
+/// The most bytes a chunk's voxels take encoded with bincode: a palette entry and count for every
+/// voxel, every index packed as wide as it goes, and the lengths of each
+pub const MAX_ENCODED_CHUNK_SIZE: usize = 64
+    + CHUNK_VOLUME * (std::mem::size_of::<Voxel>() + std::mem::size_of::<u32>() + MAX_BITS_PER_INDEX as usize / 8);
+
+/// The voxels of a chunk, compressed with a palette.
+///
+/// Chunks made of a single voxel (all air, all stone) are stored as just that voxel. Otherwise
+/// each distinct voxel gets a palette entry, and voxels are stored as palette indices packed into
+/// `u64` words using only as many bits as the palette needs. A 16³ chunk with up to 16 distinct
+/// voxels takes about 2 KB instead of the 8 KB of a flat `Vec<Voxel>`. Every density near a smooth
+/// surface is a distinct voxel though, so generated surface chunks often need 6 to 8 bits per
+/// index, up to about 5.5 KB.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum ChunkStorage {
+    Uniform(Voxel),
+    Paletted(PalettedVoxels)
+}
+
+/// Voxels read from elsewhere are checked to be usable as they're deserialized, so a corrupt
+/// copy is an error rather than a panic on the first read
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "UncheckedPalettedVoxels")]
+pub struct PalettedVoxels {
+    palette: Vec<Voxel>,
+    /// How many voxels use each palette entry. Entries with a count of zero are free.
+    counts: Vec<u32>,
+    bits_per_index: u8,
+    words: Vec<u64>
+}
+
+/// Paletted voxels as they were deserialized, before they've been checked
+#[derive(Deserialize)]
+struct UncheckedPalettedVoxels {
+    palette: Vec<Voxel>,
+    counts: Vec<u32>,
+    bits_per_index: u8,
+    words: Vec<u64>
+}
+
+impl TryFrom<UncheckedPalettedVoxels> for PalettedVoxels {
+    type Error = String;
+
+    fn try_from(unchecked: UncheckedPalettedVoxels) -> Result<Self, Self::Error> {
+        let UncheckedPalettedVoxels { palette, counts, bits_per_index, words } = unchecked;
+
+        if bits_per_index < MIN_BITS_PER_INDEX || bits_per_index > MAX_BITS_PER_INDEX {
+            return Err(format!("{} bits per palette index is out of range", bits_per_index));
+        }
+
+        if counts.len() != palette.len() {
+            return Err(format!("{} palette entries have {} counts", palette.len(), counts.len()));
+        }
+
+        if words.len() != word_count(bits_per_index) {
+            return Err(format!("{} words of packed indices, {} expected", words.len(), word_count(bits_per_index)));
+        }
+
+        // Every index must point into the palette, and be counted by the entry it points at
+        let mut tallies = vec![0; palette.len()];
+        for position in 0..CHUNK_VOLUME {
+            let index = read_index(&words, bits_per_index, position);
+
+            match tallies.get_mut(index) {
+                Some(tally) => *tally += 1,
+                None => return Err(format!("palette index {} is past the {} entries", index, palette.len()))
+            }
+        }
+
+        if tallies != counts {
+            return Err("palette counts don't match the packed indices".to_string());
+        }
+
+        Ok(PalettedVoxels {
+            palette,
+            counts,
+            bits_per_index,
+            words
+        })
+    }
+}
+
+impl Default for ChunkStorage {
+    fn default() -> Self { ChunkStorage::Uniform(Voxel::AIR) }
+}
+
+impl ChunkStorage {
+    /// Compresses `CHUNK_VOLUME` voxels
+    pub fn from_voxels(voxels: &[Voxel]) -> ChunkStorage {
+        assert_eq!(voxels.len(), CHUNK_VOLUME);
+
+        let mut lookup = HashMap::new();
+        let mut palette = Vec::new();
+        let mut counts = Vec::new();
+        let mut indices = Vec::with_capacity(CHUNK_VOLUME);
+
+        for voxel in voxels {
+            let index = *lookup.entry(*voxel).or_insert_with(|| {
+                palette.push(*voxel);
+                counts.push(0);
+                palette.len() - 1
+            });
+
+            counts[index] += 1;
+            indices.push(index);
+        }
+
+        if palette.len() == 1 {
+            return ChunkStorage::Uniform(palette[0]);
+        }
+
+        let bits_per_index = bits_for_palette(palette.len());
+        let mut words = vec![0; word_count(bits_per_index)];
+        for (position, index) in indices.into_iter().enumerate() {
+            write_index(&mut words, bits_per_index, position, index);
+        }
+
+        ChunkStorage::Paletted(PalettedVoxels {
+            palette,
+            counts,
+            bits_per_index,
+            words
+        })
+    }
+
+    pub fn get(&self, index: usize) -> Voxel {
+        match self {
+            ChunkStorage::Uniform(voxel) => *voxel,
+            ChunkStorage::Paletted(paletted) => paletted.get(index)
+        }
+    }
+
+    /// Replaces the voxel at `index`, returning the voxel that was there before
+    pub fn set(&mut self, index: usize, voxel: Voxel) -> Voxel {
+        match self {
+            ChunkStorage::Uniform(uniform) => {
+                let previous = *uniform;
+                if previous != voxel {
+                    let mut paletted = PalettedVoxels::filled(previous);
+                    paletted.set(index, voxel);
+                    *self = ChunkStorage::Paletted(paletted);
+                }
+
+                previous
+            },
+            ChunkStorage::Paletted(paletted) => {
+                let previous = paletted.set(index, voxel);
+
+                // Writes which leave every voxel the same collapse back to the fast path
+                if let Some(uniform) = paletted.uniform() {
+                    *self = ChunkStorage::Uniform(uniform);
+                }
+
+                previous
+            }
+        }
+    }
+
+    /// The single voxel filling the chunk, if there is one
+    pub fn uniform(&self) -> Option<Voxel> {
+        match self {
+            ChunkStorage::Uniform(voxel) => Some(*voxel),
+            ChunkStorage::Paletted(_) => None
+        }
+    }
+
+    /// The distinct voxels in the chunk
+    pub fn palette(&self) -> Vec<Voxel> {
+        match self {
+            ChunkStorage::Uniform(voxel) => vec![*voxel],
+            ChunkStorage::Paletted(paletted) => paletted.palette.iter()
+                .zip(paletted.counts.iter())
+                .filter(|(_, count)| **count > 0)
+                .map(|(voxel, _)| *voxel)
+                .collect()
+        }
+    }
+
+    /// Whether any voxel matches `predicate`, checking each palette entry rather than each voxel
+    pub fn any(&self, predicate: impl Fn(&Voxel) -> bool) -> bool {
+        self.palette().iter().any(predicate)
+    }
+
+    /// The bytes used on the heap, for comparing layouts
+    pub fn heap_size(&self) -> usize {
+        match self {
+            ChunkStorage::Uniform(_) => 0,
+            ChunkStorage::Paletted(paletted) => {
+                paletted.palette.capacity() * std::mem::size_of::<Voxel>()
+                    + paletted.counts.capacity() * std::mem::size_of::<u32>()
+                    + paletted.words.capacity() * std::mem::size_of::<u64>()
+            }
+        }
+    }
+}
+
+impl PalettedVoxels {
+    fn filled(voxel: Voxel) -> PalettedVoxels {
+        PalettedVoxels {
+            palette: vec![voxel],
+            counts: vec![CHUNK_VOLUME as u32],
+            bits_per_index: MIN_BITS_PER_INDEX,
+            words: vec![0; word_count(MIN_BITS_PER_INDEX)]
+        }
+    }
+
+    pub fn bits_per_index(&self) -> u8 {
+        self.bits_per_index
+    }
+
+    fn get(&self, index: usize) -> Voxel {
+        self.palette[read_index(&self.words, self.bits_per_index, index)]
+    }
+
+    fn set(&mut self, index: usize, voxel: Voxel) -> Voxel {
+        let previous_index = read_index(&self.words, self.bits_per_index, index);
+        let previous = self.palette[previous_index];
+        if previous == voxel {
+            return previous;
+        }
+
+        let palette_index = self.palette_index(voxel);
+        write_index(&mut self.words, self.bits_per_index, index, palette_index);
+
+        self.counts[palette_index] += 1;
+        self.counts[previous_index] -= 1;
+
+        // Shrink once the palette would fit in two fewer bits, leaving room so a single write
+        // back doesn't immediately grow it again
+        if self.counts[previous_index] == 0 && self.bits_per_index > MIN_BITS_PER_INDEX {
+            let used = self.counts.iter().filter(|count| **count > 0).count();
+            if used <= 1 << (self.bits_per_index - 2) {
+                self.repack();
+            }
+        }
+
+        previous
+    }
+
+    /// The palette index of `voxel`, adding it to the palette and widening the packed indices
+    /// if necessary
+    fn palette_index(&mut self, voxel: Voxel) -> usize {
+        if let Some(index) = self.palette.iter().zip(self.counts.iter())
+            .position(|(entry, count)| *entry == voxel && *count > 0) {
+            return index;
+        }
+
+        if let Some(index) = self.counts.iter().position(|count| *count == 0) {
+            self.palette[index] = voxel;
+            return index;
+        }
+
+        self.palette.push(voxel);
+        self.counts.push(0);
+
+        if self.palette.len() > 1 << self.bits_per_index {
+            self.resize(self.bits_per_index + 1);
+        }
+
+        self.palette.len() - 1
+    }
+
+    fn uniform(&self) -> Option<Voxel> {
+        self.counts.iter()
+            .position(|count| *count as usize == CHUNK_VOLUME)
+            .map(|index| self.palette[index])
+    }
+
+    /// Rewrites the packed indices with a different number of bits each
+    fn resize(&mut self, bits_per_index: u8) {
+        let mut words = vec![0; word_count(bits_per_index)];
+        for position in 0..CHUNK_VOLUME {
+            let index = read_index(&self.words, self.bits_per_index, position);
+            write_index(&mut words, bits_per_index, position, index);
+        }
+
+        self.bits_per_index = bits_per_index;
+        self.words = words;
+    }
+
+    /// Drops unused palette entries and packs the indices as tightly as the palette allows
+    fn repack(&mut self) {
+        let mut remap = vec![0; self.palette.len()];
+        let mut palette = Vec::new();
+        let mut counts = Vec::new();
+
+        for (index, (voxel, count)) in self.palette.iter().zip(self.counts.iter()).enumerate() {
+            if *count > 0 {
+                remap[index] = palette.len();
+                palette.push(*voxel);
+                counts.push(*count);
+            }
+        }
+
+        let bits_per_index = bits_for_palette(palette.len());
+        let mut words = vec![0; word_count(bits_per_index)];
+        for position in 0..CHUNK_VOLUME {
+            let index = read_index(&self.words, self.bits_per_index, position);
+            write_index(&mut words, bits_per_index, position, remap[index]);
+        }
+
+        self.palette = palette;
+        self.counts = counts;
+        self.bits_per_index = bits_per_index;
+        self.words = words;
+    }
+}
+
+fn bits_for_palette(len: usize) -> u8 {
+    let bits = (usize::MAX.count_ones() - (len.max(2) - 1).leading_zeros()) as u8;
+    bits.max(MIN_BITS_PER_INDEX)
+}
+
+/// Indices never straddle two words, so some bits at the top of each word may go unused
+fn indices_per_word(bits_per_index: u8) -> usize {
+    64 / bits_per_index as usize
+}
+
+fn word_count(bits_per_index: u8) -> usize {
+    let per_word = indices_per_word(bits_per_index);
+    (CHUNK_VOLUME + per_word - 1) / per_word
+}
+
+fn read_index(words: &[u64], bits_per_index: u8, position: usize) -> usize {
+    let per_word = indices_per_word(bits_per_index);
+    let shift = (position % per_word) * bits_per_index as usize;
+    let mask = (1u64 << bits_per_index) - 1;
+
+    ((words[position / per_word] >> shift) & mask) as usize
+}
+
+fn write_index(words: &mut [u64], bits_per_index: u8, position: usize, index: usize) {
+    let per_word = indices_per_word(bits_per_index);
+    let shift = (position % per_word) * bits_per_index as usize;
+    let mask = (1u64 << bits_per_index) - 1;
+    let word = &mut words[position / per_word];
+
+    *word = (*word & !(mask << shift)) | ((index as u64 & mask) << shift);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::resources::WorldGenerator;
+    use super::*;
+
+    const FLAT_SIZE: usize = CHUNK_VOLUME * std::mem::size_of::<Voxel>();
+
+    /// A repeatable stream of voxel positions and voxels
+    fn writes(count: usize, distinct: u32) -> Vec<(usize, Voxel)> {
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        (0..count)
+            .map(|_| {
+                let index = next() as usize % CHUNK_VOLUME;
+                let value = (next() % distinct as u64) as u8;
+                (index, Voxel { material: value, density: value as i8 })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn matches_flat_layout() {
+        let writes = writes(50_000, 40);
+        let mut flat = vec![Voxel::AIR; CHUNK_VOLUME];
+        let mut storage = ChunkStorage::default();
+
+        for (index, voxel) in writes.iter() {
+            flat[*index] = *voxel;
+            storage.set(*index, *voxel);
+        }
+
+        for index in 0..CHUNK_VOLUME {
+            assert_eq!(storage.get(index), flat[index]);
+        }
+        assert!(storage.heap_size() < FLAT_SIZE);
+    }
+
+    #[test]
+    fn sixteen_distinct_voxels_take_about_two_kilobytes() {
+        let voxels: Vec<Voxel> = (0..CHUNK_VOLUME)
+            .map(|index| Voxel::solid((index % 16) as u8))
+            .collect();
+        let storage = ChunkStorage::from_voxels(&voxels);
+
+        match &storage {
+            ChunkStorage::Paletted(paletted) => assert_eq!(paletted.bits_per_index(), 4),
+            ChunkStorage::Uniform(_) => panic!("sixteen voxels aren't uniform")
+        }
+        assert!(storage.heap_size() <= 2 * 1024 + 16 * 8);
+    }
+
+    #[test]
+    fn palette_grows_and_shrinks_with_writes() {
+        let mut storage = ChunkStorage::default();
+
+        for material in 1..=40u8 {
+            storage.set(material as usize, Voxel::solid(material));
+        }
+        match &storage {
+            ChunkStorage::Paletted(paletted) => assert_eq!(paletted.bits_per_index(), 6),
+            ChunkStorage::Uniform(_) => panic!("storage should be paletted")
+        }
+
+        for material in 3..=40u8 {
+            storage.set(material as usize, Voxel::AIR);
+        }
+        match &storage {
+            ChunkStorage::Paletted(paletted) => assert_eq!(paletted.bits_per_index(), 4),
+            ChunkStorage::Uniform(_) => panic!("storage should be paletted")
+        }
+        assert_eq!(storage.palette().len(), 3);
+
+        storage.set(1, Voxel::AIR);
+        storage.set(2, Voxel::AIR);
+        assert_eq!(storage.uniform(), Some(Voxel::AIR));
+    }
+
+    /// Encodes paletted voxels, corrupted by `corrupt`, and decodes them again
+    fn decode_corrupted(corrupt: impl FnOnce(&mut PalettedVoxels)) -> bincode::Result<ChunkStorage> {
+        let voxels: Vec<Voxel> = (0..CHUNK_VOLUME)
+            .map(|index| Voxel::solid((index % 5) as u8))
+            .collect();
+
+        let mut paletted = match ChunkStorage::from_voxels(&voxels) {
+            ChunkStorage::Paletted(paletted) => paletted,
+            ChunkStorage::Uniform(_) => panic!("five voxels aren't uniform")
+        };
+        corrupt(&mut paletted);
+
+        bincode::deserialize(&bincode::serialize(&ChunkStorage::Paletted(paletted)).unwrap())
+    }
+
+    #[test]
+    fn corrupt_voxels_fail_to_decode() {
+        assert!(decode_corrupted(|_| {}).is_ok());
+
+        assert!(decode_corrupted(|paletted| paletted.bits_per_index = 0).is_err());
+        assert!(decode_corrupted(|paletted| paletted.bits_per_index = 40).is_err());
+        assert!(decode_corrupted(|paletted| { paletted.words.pop(); }).is_err());
+        assert!(decode_corrupted(|paletted| { paletted.counts.pop(); }).is_err());
+        assert!(decode_corrupted(|paletted| paletted.counts[0] += 1).is_err());
+
+        // An index past the end of the palette
+        assert!(decode_corrupted(|paletted| paletted.words[0] |= 0xf).is_err());
+    }
+
+    #[test]
+    fn the_widest_chunks_fit_the_encoded_size_limit() {
+        let voxels: Vec<Voxel> = (0..CHUNK_VOLUME)
+            .map(|index| Voxel { material: (index / 256) as u8, density: (index % 256) as u8 as i8 })
+            .collect();
+        let storage = ChunkStorage::from_voxels(&voxels);
+
+        assert_eq!(storage.palette().len(), CHUNK_VOLUME);
+        assert!(bincode::serialize(&storage).unwrap().len() <= MAX_ENCODED_CHUNK_SIZE);
+    }
+
+    #[test]
+    fn generated_chunks_are_smaller_than_flat_layout() {
+        let generator = WorldGenerator::new(7);
+
+        for y in -2..2 {
+            for x in 0..2 {
+                let chunk = generator.generate(ChunkPos(x, y, 0));
+                let storage = chunk.storage();
+
+                assert!(storage.heap_size() < FLAT_SIZE, "chunk ({}, {}, 0) takes {} bytes", x, y, storage.heap_size());
+            }
+        }
+    }
+}
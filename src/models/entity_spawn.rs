@@ -1,8 +1,22 @@
 use serde::{Serialize, Deserialize};
 use crate::models::*;
 
+/// What kind of entity a client should create for a spawn
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum EntityArchetype {
+    None,
+    Player(PlayerProfile)
+}
+
+impl Default for EntityArchetype {
+    fn default() -> Self { EntityArchetype::None }
+}
+
 /// A representation of an entity that is spawning
 #[derive(Default, Serialize, Deserialize)]
 pub struct EntitySpawn {
-    pub entity_id: u32
+    pub entity_id: u32,
+    pub archetype: EntityArchetype,
+    /// Whether the receiving client controls this entity
+    pub controlled: bool
 }
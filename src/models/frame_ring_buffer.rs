@@ -0,0 +1,513 @@
+use crate::models::*;
+
+/// Anything stamped with the simulation frame it belongs to
+pub trait Framed {
+    fn frame(&self) -> u32;
+}
+
+impl Framed for CommandFrame {
+    fn frame(&self) -> u32 { self.frame }
+}
+
+impl Framed for StateFrame {
+    fn frame(&self) -> u32 { self.frame }
+}
+
+/// Signed distance from frame `from` to frame `to`, correct across `u32` wraparound as long as
+/// the two frames are less than `i32::MAX` frames apart
+pub fn frame_offset(from: u32, to: u32) -> i32 {
+    to.wrapping_sub(from) as i32
+}
+
+/// A fixed window of the most recent frames, indexed by frame number.
+///
+/// Each frame maps to the slot `frame % capacity`. The capacity is always a power of two, so that
+/// mapping stays continuous when the frame counter wraps around. Frames may arrive out of order;
+/// anything newer than the latest frame slides the window forward, anything inside the window is
+/// inserted (or overwritten) in place, and anything older than the window is rejected.
+pub struct FrameRingBuffer<T: Framed> {
+    slots: Vec<Option<T>>,
+    latest_frame: Option<u32>
+}
+
+pub const DEFAULT_FRAME_RING_BUFFER_CAPACITY: usize = 64;
+
+pub type CommandFrameBuffer = FrameRingBuffer<CommandFrame>;
+pub type StateFrameBuffer = FrameRingBuffer<StateFrame>;
+
+impl<T: Framed> Default for FrameRingBuffer<T> {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_FRAME_RING_BUFFER_CAPACITY)
+    }
+}
+
+impl<T: Framed> FrameRingBuffer<T> {
+    /// Creates a buffer holding at least `capacity` frames
+    pub fn with_capacity(capacity: usize) -> Self {
+        let capacity = capacity.max(1).next_power_of_two();
+
+        Self {
+            slots: (0..capacity).map(|_| None).collect(),
+            latest_frame: None
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+
+    pub fn latest_frame(&self) -> Option<u32> {
+        self.latest_frame
+    }
+
+    /// The oldest frame still covered by the window (which may not have an entry)
+    pub fn earliest_frame(&self) -> Option<u32> {
+        self.latest_frame.map(|latest| latest.wrapping_sub(self.capacity() as u32 - 1))
+    }
+
+    /// Whether `frame` falls inside the window of frames this buffer can hold
+    pub fn in_window(&self, frame: u32) -> bool {
+        match self.latest_frame {
+            Some(latest) => {
+                let age = frame_offset(frame, latest);
+                age >= 0 && (age as usize) < self.capacity()
+            },
+            None => false
+        }
+    }
+
+    /// Inserts an entry at its frame, replacing any existing entry for that frame. Newer frames
+    /// advance the window and evict whatever falls out of it. Returns `false` if the entry is too
+    /// old to fit in the window.
+    pub fn insert(&mut self, item: T) -> bool {
+        let frame = item.frame();
+
+        if let Some(latest) = self.latest_frame {
+            let ahead = frame_offset(latest, frame);
+
+            if ahead > 0 {
+                let evicted = (ahead as usize).min(self.capacity());
+                for offset in 1..=evicted {
+                    let index = self.index(latest.wrapping_add(offset as u32));
+                    self.slots[index] = None;
+                }
+                self.latest_frame = Some(frame);
+            } else if !self.in_window(frame) {
+                return false;
+            }
+        } else {
+            self.latest_frame = Some(frame);
+        }
+
+        let index = self.index(frame);
+        self.slots[index] = Some(item);
+        true
+    }
+
+    /// Alias of `insert` for in-order producers
+    pub fn push(&mut self, item: T) -> bool {
+        self.insert(item)
+    }
+
+    pub fn get(&self, frame: u32) -> Option<&T> {
+        if !self.in_window(frame) {
+            return None;
+        }
+
+        self.slots[self.index(frame)]
+            .as_ref()
+            .filter(|item| item.frame() == frame)
+    }
+
+    pub fn get_mut(&mut self, frame: u32) -> Option<&mut T> {
+        if !self.in_window(frame) {
+            return None;
+        }
+
+        let index = self.index(frame);
+        self.slots[index]
+            .as_mut()
+            .filter(|item| item.frame() == frame)
+    }
+
+    pub fn contains(&self, frame: u32) -> bool {
+        self.get(frame).is_some()
+    }
+
+    pub fn latest(&self) -> Option<&T> {
+        self.latest_frame.and_then(move |frame| self.get(frame))
+    }
+
+    pub fn remove(&mut self, frame: u32) -> Option<T> {
+        if !self.contains(frame) {
+            return None;
+        }
+
+        let index = self.index(frame);
+        self.slots[index].take()
+    }
+
+    /// Entries from `start` to `end` inclusive, oldest first. Frames outside the window or
+    /// without an entry are skipped, so however far apart `start` and `end` are, no more than
+    /// `capacity` frames are looked at.
+    pub fn range(&self, start: u32, end: u32) -> impl Iterator<Item = &T> + '_ {
+        let count = frame_offset(start, end) as i64 + 1;
+
+        self.iter().filter(move |item| {
+            let offset = frame_offset(start, item.frame()) as i64;
+            offset >= 0 && offset < count
+        })
+    }
+
+    /// All entries in the window, oldest first
+    pub fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+        let earliest = self.earliest_frame();
+        let capacity = self.capacity() as u32;
+
+        earliest
+            .into_iter()
+            .flat_map(move |earliest| (0..capacity).map(move |offset| earliest.wrapping_add(offset)))
+            .filter_map(move |frame| self.get(frame))
+    }
+
+    /// Entries for the most recent `history_size` frames, oldest first
+    pub fn history_iter(&mut self, history_size: u32) -> impl Iterator<Item = &mut T> + '_ {
+        let history_size = (history_size as usize).min(self.capacity());
+        let skip = self.capacity() - history_size;
+        let start = match self.earliest_frame() {
+            Some(earliest) => self.index(earliest),
+            None => 0
+        };
+        let earliest = self.earliest_frame().unwrap_or(0);
+
+        let (wrapped, tail) = self.slots.split_at_mut(start);
+
+        tail.iter_mut()
+            .chain(wrapped.iter_mut())
+            .enumerate()
+            .skip(skip)
+            .filter_map(move |(offset, slot)| {
+                slot.as_mut().filter(|item| item.frame() == earliest.wrapping_add(offset as u32))
+            })
+    }
+
+    /// Grows the window by at least `size` frames
+    pub fn grow(&mut self, size: u32) {
+        self.resize(self.capacity() + size as usize);
+    }
+
+    /// Shrinks the window by `size` frames (rounded up to a power of two), keeping the most
+    /// recent entries
+    pub fn shrink(&mut self, size: u32) {
+        self.resize(self.capacity().saturating_sub(size as usize));
+    }
+
+    pub fn clear(&mut self) {
+        for slot in self.slots.iter_mut() {
+            *slot = None;
+        }
+        self.latest_frame = None;
+    }
+
+    fn resize(&mut self, capacity: usize) {
+        let mut resized = Self::with_capacity(capacity);
+        resized.latest_frame = self.latest_frame;
+
+        for slot in self.slots.iter_mut() {
+            if let Some(item) = slot.take() {
+                resized.insert(item);
+            }
+        }
+
+        *self = resized;
+    }
+
+    fn index(&self, frame: u32) -> usize {
+        frame as usize & (self.capacity() - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use proptest::prelude::*;
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Entry(u32);
+
+    impl Framed for Entry {
+        fn frame(&self) -> u32 { self.0 }
+    }
+
+    fn frames<'a>(entries: impl Iterator<Item = &'a Entry>) -> Vec<u32> {
+        entries.map(|entry| entry.0).collect()
+    }
+
+    #[test]
+    fn inserts_out_of_order() {
+        let mut buffer = FrameRingBuffer::with_capacity(8);
+
+        assert!(buffer.insert(Entry(10)));
+        assert!(buffer.insert(Entry(7)));
+        assert!(buffer.insert(Entry(12)));
+        assert!(buffer.insert(Entry(11)));
+
+        assert_eq!(buffer.latest_frame(), Some(12));
+        assert_eq!(frames(buffer.iter()), vec![7, 10, 11, 12]);
+        assert!(!buffer.contains(8));
+    }
+
+    #[test]
+    fn wraps_around_u32_max() {
+        let mut buffer = FrameRingBuffer::with_capacity(8);
+
+        for frame in (u32::MAX - 3)..=u32::MAX {
+            buffer.insert(Entry(frame));
+        }
+        buffer.insert(Entry(1));
+        buffer.insert(Entry(0));
+
+        assert_eq!(buffer.latest_frame(), Some(1));
+        assert_eq!(frames(buffer.iter()), vec![u32::MAX - 3, u32::MAX - 2, u32::MAX - 1, u32::MAX, 0, 1]);
+        assert_eq!(frames(buffer.range(u32::MAX - 1, 1)), vec![u32::MAX - 1, u32::MAX, 0, 1]);
+        assert!(buffer.get(u32::MAX).is_some());
+    }
+
+    #[test]
+    fn evicts_frames_that_leave_the_window() {
+        let mut buffer = FrameRingBuffer::with_capacity(4);
+
+        for frame in 0..4 {
+            buffer.insert(Entry(frame));
+        }
+        buffer.insert(Entry(5));
+
+        assert_eq!(buffer.earliest_frame(), Some(2));
+        assert_eq!(frames(buffer.iter()), vec![2, 3, 5]);
+        assert!(!buffer.contains(0));
+        assert!(!buffer.contains(1));
+
+        // Too old to fit in the window
+        assert!(!buffer.insert(Entry(1)));
+        assert_eq!(buffer.len(), 3);
+
+        // Jumping further ahead than the window clears everything else
+        buffer.insert(Entry(100));
+        assert_eq!(frames(buffer.iter()), vec![100]);
+    }
+
+    #[test]
+    fn range_and_history_skip_missing_frames() {
+        let mut buffer = FrameRingBuffer::with_capacity(8);
+
+        for frame in [3, 4, 6, 8, 9].iter() {
+            buffer.insert(Entry(*frame));
+        }
+
+        assert_eq!(frames(buffer.range(4, 8)), vec![4, 6, 8]);
+        assert_eq!(frames(buffer.range(8, 4)), Vec::<u32>::new());
+        assert_eq!(frames(buffer.range(9, 9)), vec![9]);
+
+        let history: Vec<u32> = buffer.history_iter(4).map(|entry| entry.0).collect();
+        assert_eq!(history, vec![6, 8, 9]);
+
+        let history: Vec<u32> = buffer.history_iter(100).map(|entry| entry.0).collect();
+        assert_eq!(history, vec![3, 4, 6, 8, 9]);
+    }
+
+    #[test]
+    fn range_between_far_apart_frames_only_looks_at_the_window() {
+        let mut buffer = FrameRingBuffer::with_capacity(8);
+
+        for frame in [3, 4, 6].iter() {
+            buffer.insert(Entry(*frame));
+        }
+
+        assert_eq!(frames(buffer.range(0, i32::MAX as u32)), vec![3, 4, 6]);
+        assert_eq!(frames(buffer.range(4, 4 + i32::MAX as u32)), vec![4, 6]);
+    }
+
+    #[test]
+    fn resizing_keeps_the_most_recent_frames() {
+        let mut buffer = FrameRingBuffer::with_capacity(8);
+
+        for frame in 10..18 {
+            buffer.insert(Entry(frame));
+        }
+
+        buffer.grow(5);
+        assert_eq!(buffer.capacity(), 16);
+        assert_eq!(frames(buffer.iter()), (10..18).collect::<Vec<_>>());
+
+        buffer.insert(Entry(20));
+        assert_eq!(frames(buffer.iter()), vec![10, 11, 12, 13, 14, 15, 16, 17, 20]);
+
+        buffer.shrink(12);
+        assert_eq!(buffer.capacity(), 4);
+        assert_eq!(buffer.latest_frame(), Some(20));
+        assert_eq!(frames(buffer.iter()), vec![17, 20]);
+    }
+
+    /// An entry carrying a value, so overwriting a frame can be told apart from keeping it
+    #[derive(Debug, PartialEq)]
+    struct Tagged(u32, u32);
+
+    impl Framed for Tagged {
+        fn frame(&self) -> u32 { self.0 }
+    }
+
+    #[derive(Clone, Debug)]
+    enum Operation {
+        Insert(u32, u32),
+        Remove(u32),
+        Grow(u32),
+        Shrink(u32)
+    }
+
+    /// The same window kept as a map of every frame, with none of the slot arithmetic
+    struct Model {
+        entries: HashMap<u32, u32>,
+        latest: Option<u32>,
+        capacity: usize
+    }
+
+    impl Model {
+        fn in_window(&self, frame: u32) -> bool {
+            self.latest.map_or(false, |latest| {
+                let age = latest.wrapping_sub(frame) as i32;
+                age >= 0 && (age as usize) < self.capacity
+            })
+        }
+
+        fn drop_outside_window(&mut self) {
+            let entries = std::mem::take(&mut self.entries);
+            self.entries = entries.into_iter().filter(|(frame, _)| self.in_window(*frame)).collect();
+        }
+
+        fn apply(&mut self, operation: &Operation) -> Option<bool> {
+            match *operation {
+                Operation::Insert(frame, value) => {
+                    let newer = self.latest.map_or(true, |latest| frame.wrapping_sub(latest) as i32 > 0);
+
+                    if newer {
+                        self.latest = Some(frame);
+                        self.drop_outside_window();
+                    } else if !self.in_window(frame) {
+                        return Some(false);
+                    }
+
+                    self.entries.insert(frame, value);
+                    Some(true)
+                },
+                Operation::Remove(frame) => {
+                    self.entries.remove(&frame);
+                    None
+                },
+                Operation::Grow(size) => {
+                    self.capacity = (self.capacity + size as usize).next_power_of_two();
+                    None
+                },
+                Operation::Shrink(size) => {
+                    self.capacity = self.capacity.saturating_sub(size as usize).max(1).next_power_of_two();
+                    self.drop_outside_window();
+                    None
+                }
+            }
+        }
+
+        /// Every entry, oldest first
+        fn frames(&self) -> Vec<u32> {
+            let mut frames: Vec<u32> = self.entries.keys().cloned().collect();
+            let latest = self.latest.unwrap_or(0);
+            frames.sort_by_key(|frame| std::cmp::Reverse(latest.wrapping_sub(*frame)));
+            frames
+        }
+    }
+
+    /// Frames close to a starting point, so sequences run into each other and across `u32::MAX`,
+    /// with the odd jump further than any window
+    fn frame_near(start: u32) -> impl Strategy<Value = u32> {
+        prop_oneof![
+            8 => (0u32..96).prop_map(move |offset| start.wrapping_add(offset)),
+            1 => any::<u32>()
+        ]
+    }
+
+    fn operation(start: u32) -> impl Strategy<Value = Operation> {
+        prop_oneof![
+            6 => (frame_near(start), any::<u32>()).prop_map(|(frame, value)| Operation::Insert(frame, value)),
+            1 => frame_near(start).prop_map(Operation::Remove),
+            1 => (0u32..40).prop_map(Operation::Grow),
+            1 => (0u32..40).prop_map(Operation::Shrink)
+        ]
+    }
+
+    fn operations() -> impl Strategy<Value = (u32, Vec<Operation>)> {
+        prop_oneof![Just(u32::MAX - 48), Just(0), any::<u32>()]
+            .prop_flat_map(|start| (Just(start), proptest::collection::vec(operation(start), 1..200)))
+    }
+
+    proptest! {
+        #[test]
+        fn behaves_like_a_map_of_the_latest_frames((start, operations) in operations()) {
+            let mut buffer = FrameRingBuffer::with_capacity(8);
+            let mut model = Model {
+                entries: HashMap::new(),
+                latest: None,
+                capacity: 8
+            };
+
+            for operation in operations.iter() {
+                // Each grow at least doubles the window, so it's kept small enough to check quickly
+                if let Operation::Grow(_) = operation {
+                    if model.capacity >= 256 {
+                        continue;
+                    }
+                }
+
+                let expected = model.apply(operation);
+
+                match *operation {
+                    Operation::Insert(frame, value) => prop_assert_eq!(Some(buffer.insert(Tagged(frame, value))), expected),
+                    Operation::Remove(frame) => { buffer.remove(frame); },
+                    Operation::Grow(size) => buffer.grow(size),
+                    Operation::Shrink(size) => buffer.shrink(size)
+                }
+
+                prop_assert_eq!(buffer.capacity(), model.capacity);
+                prop_assert_eq!(buffer.latest_frame(), model.latest);
+                prop_assert_eq!(buffer.iter().map(|entry| entry.0).collect::<Vec<_>>(), model.frames());
+
+                for offset in 0..96 {
+                    let frame = start.wrapping_add(offset);
+                    prop_assert_eq!(buffer.get(frame).map(|entry| entry.1), model.entries.get(&frame).cloned());
+                }
+            }
+        }
+
+        #[test]
+        fn range_matches_the_model(inserted in proptest::collection::vec(frame_near(u32::MAX - 16), 1..40), start in frame_near(u32::MAX - 24), length in 0u32..64) {
+            let mut buffer = FrameRingBuffer::with_capacity(16);
+            for frame in inserted.iter() {
+                buffer.insert(Entry(*frame));
+            }
+
+            let end = start.wrapping_add(length);
+            let expected: Vec<u32> = buffer.iter()
+                .map(|entry| entry.0)
+                .filter(|frame| frame.wrapping_sub(start) <= length)
+                .collect();
+
+            prop_assert_eq!(frames(buffer.range(start, end)), expected);
+        }
+    }
+}
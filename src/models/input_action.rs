@@ -0,0 +1,57 @@
+use serde::{Serialize, Deserialize};
+
+/// Something a player can do, independent of the key, button or stick it is bound to
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InputAction {
+    MoveForward,
+    MoveBackward,
+    MoveLeft,
+    MoveRight,
+    Jump,
+    /// Digs out the ground the player is looking at
+    Dig,
+    /// Builds onto the ground the player is looking at
+    Build,
+    LookYaw,
+    LookPitch
+}
+
+/// Where an action's state is stored within an `InputCommand`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InputActionKind {
+    /// A pressed/released action, stored as a bit of `InputCommand::buttons`
+    Button(u8),
+    /// An analog action, stored at an index of `InputCommand::axes`
+    Axis(u8)
+}
+
+/// The number of analog actions carried by each `InputCommand`
+pub const INPUT_AXIS_COUNT: usize = 2;
+
+impl InputAction {
+    pub const ALL: [InputAction; 9] = [
+        InputAction::MoveForward,
+        InputAction::MoveBackward,
+        InputAction::MoveLeft,
+        InputAction::MoveRight,
+        InputAction::Jump,
+        InputAction::Dig,
+        InputAction::Build,
+        InputAction::LookYaw,
+        InputAction::LookPitch
+    ];
+
+    pub fn kind(self) -> InputActionKind {
+        match self {
+            InputAction::MoveForward => InputActionKind::Button(0),
+            InputAction::MoveBackward => InputActionKind::Button(1),
+            InputAction::MoveLeft => InputActionKind::Button(2),
+            InputAction::MoveRight => InputActionKind::Button(3),
+            InputAction::Jump => InputActionKind::Button(4),
+            InputAction::Dig => InputActionKind::Button(5),
+            InputAction::Build => InputActionKind::Button(6),
+            InputAction::LookYaw => InputActionKind::Axis(0),
+            InputAction::LookPitch => InputActionKind::Axis(1)
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use bevy::input::{
+    gamepad::{GamepadAxisType, GamepadButtonType},
+    keyboard::KeyCode,
+    mouse::MouseButton
+};
+use serde::{Serialize, Deserialize};
+
+/// A physical input which can drive an `InputAction`.
+///
+/// Analog sources are multiplied by their scale. A button action is considered pressed when the
+/// scaled value of any of its bindings exceeds one half, so sticks can drive movement too.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum InputBinding {
+    Key(#[serde(with = "key_code")] KeyCode),
+    MouseButton(#[serde(with = "mouse_button")] MouseButton),
+    /// Horizontal mouse movement this frame, in pixels
+    MouseMotionX(f32),
+    /// Vertical mouse movement this frame, in pixels
+    MouseMotionY(f32),
+    /// A button on the first connected gamepad
+    GamepadButton(#[serde(with = "gamepad_button")] GamepadButtonType),
+    /// A stick or trigger axis on the first connected gamepad
+    GamepadAxis(#[serde(with = "gamepad_axis")] GamepadAxisType, f32)
+}
+
+/// Generates a module which (de)serializes a fieldless bevy enum by variant name, since bevy's
+/// input types don't implement serde's traits themselves
+macro_rules! serde_by_name {
+    ($module:ident, $type:ident, [$($variant:ident),* $(,)?]) => {
+        mod $module {
+            use serde::{Deserialize, Deserializer, Serializer, de::Error};
+            use super::*;
+
+            pub fn serialize<S: Serializer>(value: &$type, serializer: S) -> Result<S::Ok, S::Error> {
+                match value {
+                    $($type::$variant => serializer.serialize_str(stringify!($variant)),)*
+                    #[allow(unreachable_patterns)]
+                    other => Err(serde::ser::Error::custom(format!("{:?} can't be bound", other)))
+                }
+            }
+
+            pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<$type, D::Error> {
+                let name = String::deserialize(deserializer)?;
+                match name.as_str() {
+                    $(stringify!($variant) => Ok($type::$variant),)*
+                    _ => Err(D::Error::custom(format!("Unknown {} \"{}\"", stringify!($type), name)))
+                }
+            }
+        }
+    };
+}
+
+serde_by_name!(key_code, KeyCode, [
+    Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9, Key0,
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    Escape, F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+    Insert, Home, Delete, End, PageDown, PageUp,
+    Left, Up, Right, Down,
+    Back, Return, Space, Tab,
+    Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8, Numpad9,
+    LAlt, LControl, LShift, RAlt, RControl, RShift,
+    Grave, Minus, Equals, LBracket, RBracket, Backslash, Semicolon, Apostrophe, Comma, Period, Slash,
+]);
+
+serde_by_name!(mouse_button, MouseButton, [Left, Right, Middle]);
+
+serde_by_name!(gamepad_button, GamepadButtonType, [
+    South, East, North, West, C, Z,
+    LeftTrigger, LeftTrigger2, RightTrigger, RightTrigger2,
+    Select, Start, Mode, LeftThumb, RightThumb,
+    DPadUp, DPadDown, DPadLeft, DPadRight,
+]);
+
+serde_by_name!(gamepad_axis, GamepadAxisType, [
+    LeftStickX, LeftStickY, LeftZ, RightStickX, RightStickY, RightZ, DPadX, DPadY,
+]);
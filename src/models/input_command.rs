@@ -1,12 +1,51 @@
 use serde::{Serialize, Deserialize};
+use crate::models::*;
 
 /// A discrete representation of a player's inputs at a moment in time
 #[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
 pub struct InputCommand {
     pub frame: u32,
-    pub forward: bool,
-    pub backward: bool,
-    pub left: bool,
-    pub right: bool,
-    pub jump: bool
+    /// One bit per button action, see `InputActionKind::Button`
+    pub buttons: u32,
+    /// One value per analog action, see `InputActionKind::Axis`
+    pub axes: [f32; INPUT_AXIS_COUNT],
+    /// Where the player is looking, in radians around the vertical axis
+    pub yaw: f32,
+    /// Where the player is looking, in radians above the horizon
+    pub pitch: f32
+}
+
+impl InputCommand {
+    pub fn pressed(&self, action: InputAction) -> bool {
+        match action.kind() {
+            InputActionKind::Button(bit) => self.buttons & (1 << bit) != 0,
+            InputActionKind::Axis(index) => self.axes[index as usize] != 0.0
+        }
+    }
+
+    pub fn set_pressed(&mut self, action: InputAction, pressed: bool) {
+        match action.kind() {
+            InputActionKind::Button(bit) => if pressed {
+                self.buttons |= 1 << bit;
+            } else {
+                self.buttons &= !(1 << bit);
+            },
+            InputActionKind::Axis(index) => self.axes[index as usize] = if pressed { 1.0 } else { 0.0 }
+        }
+    }
+
+    /// The analog value of an action; buttons read as `1.0` when pressed and `0.0` otherwise
+    pub fn axis(&self, action: InputAction) -> f32 {
+        match action.kind() {
+            InputActionKind::Button(_) => if self.pressed(action) { 1.0 } else { 0.0 },
+            InputActionKind::Axis(index) => self.axes[index as usize]
+        }
+    }
+
+    pub fn set_axis(&mut self, action: InputAction, value: f32) {
+        match action.kind() {
+            InputActionKind::Button(_) => self.set_pressed(action, value > 0.5),
+            InputActionKind::Axis(index) => self.axes[index as usize] = value
+        }
+    }
 }
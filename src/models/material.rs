@@ -0,0 +1,42 @@
+// The materials voxels are made of. Each is an index into `MATERIAL_PALETTE`.
+
+pub const AIR: u8 = 0;
+pub const STONE: u8 = 1;
+pub const DIRT: u8 = 2;
+pub const GRASS: u8 = 3;
+pub const FOREST_GRASS: u8 = 4;
+pub const TAIGA_GRASS: u8 = 5;
+pub const DRY_GRASS: u8 = 6;
+pub const SAND: u8 = 7;
+pub const SANDSTONE: u8 = 8;
+pub const SNOW: u8 = 9;
+pub const MUD: u8 = 10;
+pub const WOOD: u8 = 11;
+pub const LEAVES: u8 = 12;
+pub const PINE_LEAVES: u8 = 13;
+pub const COAL_ORE: u8 = 14;
+pub const IRON_ORE: u8 = 15;
+pub const GOLD_ORE: u8 = 16;
+
+pub const MATERIAL_COUNT: usize = 17;
+
+/// The color of each material, as RGBA
+pub const MATERIAL_PALETTE: [[f32; 4]; MATERIAL_COUNT] = [
+    [0.0, 0.0, 0.0, 0.0],       // Air
+    [0.502, 0.502, 0.502, 1.0], // Stone
+    [0.545, 0.271, 0.075, 1.0], // Dirt
+    [0.196, 0.659, 0.321, 1.0], // Grass
+    [0.133, 0.490, 0.220, 1.0], // Forest grass
+    [0.278, 0.451, 0.337, 1.0], // Taiga grass
+    [0.706, 0.667, 0.357, 1.0], // Dry grass
+    [0.900, 0.894, 0.737, 1.0], // Sand
+    [0.800, 0.667, 0.451, 1.0], // Sandstone
+    [1.0, 0.98, 0.98, 1.0],     // Snow
+    [0.361, 0.290, 0.200, 1.0], // Mud
+    [0.400, 0.263, 0.129, 1.0], // Wood
+    [0.243, 0.580, 0.196, 1.0], // Leaves
+    [0.129, 0.341, 0.231, 1.0], // Pine leaves
+    [0.180, 0.180, 0.180, 1.0], // Coal ore
+    [0.690, 0.561, 0.463, 1.0], // Iron ore
+    [0.933, 0.780, 0.200, 1.0]  // Gold ore
+];
@@ -0,0 +1,42 @@
+use std::fmt;
+use std::str::FromStr;
+use serde::{Serialize, Deserialize};
+
+/// How chunks are turned into meshes. Chosen per world, as it changes how the whole world looks.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MeshingMethod {
+    /// A smooth surface through the voxel densities
+    MarchingCubes,
+    /// A surface through the voxel densities which keeps sharp edges and corners, with fewer
+    /// triangles than marching cubes
+    DualContouring,
+    /// Blocks, with the faces of neighbouring voxels of the same material merged
+    Greedy
+}
+
+impl Default for MeshingMethod {
+    fn default() -> Self { MeshingMethod::MarchingCubes }
+}
+
+impl fmt::Display for MeshingMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeshingMethod::MarchingCubes => write!(f, "marching-cubes"),
+            MeshingMethod::DualContouring => write!(f, "dual-contouring"),
+            MeshingMethod::Greedy => write!(f, "greedy")
+        }
+    }
+}
+
+impl FromStr for MeshingMethod {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "marching-cubes" => Ok(MeshingMethod::MarchingCubes),
+            "dual-contouring" => Ok(MeshingMethod::DualContouring),
+            "greedy" => Ok(MeshingMethod::Greedy),
+            _ => Err(format!("Unknown meshing method {}, expected marching-cubes, dual-contouring or greedy", value))
+        }
+    }
+}
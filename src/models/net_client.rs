@@ -3,14 +3,16 @@ use bevy_prototype_networking_laminar::{Connection};
 /// A representation of a connected player or device
 pub struct Client {
     id: u128,
-    connection: Connection
+    connection: Connection,
+    can_edit_terrain: bool
 }
 
 impl Client {
     pub fn new(id: u128, connection: Connection) -> Client {
         Client {
             id,
-            connection
+            connection,
+            can_edit_terrain: true
         }
     }
 
@@ -25,4 +27,13 @@ impl Client {
     pub fn is_authenticated(&self) -> bool {
         true
     }
+
+    /// Whether the client may dig and build. Everyone may until they're told otherwise.
+    pub fn can_edit_terrain(&self) -> bool {
+        self.can_edit_terrain
+    }
+
+    pub fn set_can_edit_terrain(&mut self, can_edit_terrain: bool) {
+        self.can_edit_terrain = can_edit_terrain;
+    }
 }
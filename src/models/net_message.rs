@@ -9,7 +9,16 @@ pub enum NetMessage {
     Authorize(String),
     CommandFrame(CommandFrame),
     AuthoritativeStateFrame(StateFrame),
-    EntitySpawn(EntitySpawn)
+    EntitySpawn(EntitySpawn),
+    WorldInfo(WorldInfo),
+    VoxelEditRequest(VoxelEditRequest),
+    VoxelEdit(VoxelEdit),
+    VoxelEditRejected(u32, VoxelEditRejection),
+    /// The revision of chunks coming into range of the client's player
+    ChunkRevisions(Vec<(ChunkPos, u64)>),
+    /// A client asking for the voxels of edited chunks it doesn't have
+    ChunkRequest(Vec<ChunkPos>),
+    ChunkFragment(ChunkFragment)
 }
 
 impl Default for NetMessage {
@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+use serde::{Serialize, Deserialize};
+
+/// A description of a terrain noise function as a graph of named noise modules, so terrain can
+/// be tuned from a RON file rather than in code. Build it into a sampler with
+/// `NoiseSampler::build`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NoiseGraph {
+    /// The node whose value is the terrain density
+    pub output: String,
+    pub nodes: HashMap<String, NoiseNode>
+}
+
+/// Settings shared by the fractal noise modules. Unset values use the module's own default.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FractalSettings {
+    /// Which seed stream to split from the world seed, so modules with different streams differ
+    pub seed: u64,
+    pub octaves: usize,
+    pub frequency: f64,
+    #[serde(default)]
+    pub lacunarity: Option<f64>,
+    #[serde(default)]
+    pub persistence: Option<f64>
+}
+
+/// One module of a `NoiseGraph`. Inputs name other nodes of the graph, so a node may feed
+/// several others.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum NoiseNode {
+    Constant(f64),
+    /// A linear ramp from 0 at the start point to 1 at the stop point, see `utilities::Gradient`
+    Gradient {
+        #[serde(default)] x_start: f64,
+        #[serde(default)] x_stop: f64,
+        #[serde(default)] y_start: f64,
+        #[serde(default)] y_stop: f64,
+        #[serde(default)] z_start: f64,
+        #[serde(default)] z_stop: f64
+    },
+    Perlin {
+        seed: u64
+    },
+    Fbm(FractalSettings),
+    Billow(FractalSettings),
+    RidgedMulti(FractalSettings),
+    Clamp {
+        source: String,
+        lower: f64,
+        upper: f64
+    },
+    /// Linearly remaps the range the source actually produces onto `lower..=upper`. The range
+    /// is measured when the graph is built.
+    AutoCorrect {
+        source: String,
+        lower: f64,
+        upper: f64
+    },
+    ScaleBias {
+        source: String,
+        scale: f64,
+        bias: f64
+    },
+    /// Scales the point before sampling the source
+    ScalePoint {
+        source: String,
+        #[serde(default = "one")] x_scale: f64,
+        #[serde(default = "one")] y_scale: f64,
+        #[serde(default = "one")] z_scale: f64
+    },
+    /// Offsets the point on each axis by the value of another node before sampling the source
+    Displace {
+        source: String,
+        #[serde(default)] x: Option<String>,
+        #[serde(default)] y: Option<String>,
+        #[serde(default)] z: Option<String>
+    },
+    /// Takes `inside` where `control` is between the bounds and `outside` elsewhere, blending
+    /// across `falloff` either side of each bound
+    Select {
+        outside: String,
+        inside: String,
+        control: String,
+        #[serde(default)] lower_bound: f64,
+        #[serde(default = "one")] upper_bound: f64,
+        #[serde(default)] falloff: f64
+    }
+}
+
+fn one() -> f64 { 1.0 }
+
+impl NoiseNode {
+    /// The names of the nodes this node reads from
+    pub fn inputs(&self) -> Vec<&String> {
+        match self {
+            NoiseNode::Constant(_)
+            | NoiseNode::Gradient { .. }
+            | NoiseNode::Perlin { .. }
+            | NoiseNode::Fbm(_)
+            | NoiseNode::Billow(_)
+            | NoiseNode::RidgedMulti(_) => vec![],
+            NoiseNode::Clamp { source, .. }
+            | NoiseNode::AutoCorrect { source, .. }
+            | NoiseNode::ScaleBias { source, .. }
+            | NoiseNode::ScalePoint { source, .. } => vec![source],
+            NoiseNode::Displace { source, x, y, z } => {
+                let mut inputs = vec![source];
+                inputs.extend(x.iter().chain(y.iter()).chain(z.iter()));
+                inputs
+            },
+            NoiseNode::Select { outside, inside, control, .. } => vec![outside, inside, control]
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum NoiseGraphError {
+    /// A node reads from a node that isn't in the graph
+    MissingNode(String),
+    /// A node depends on itself
+    Cycle(String)
+}
+
+impl std::fmt::Display for NoiseGraphError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NoiseGraphError::MissingNode(name) => write!(f, "No noise node named \"{}\"", name),
+            NoiseGraphError::Cycle(name) => write!(f, "Noise node \"{}\" depends on itself", name)
+        }
+    }
+}
+
+impl std::error::Error for NoiseGraphError {}
+
+/// The terrain graph the game ships with
+const DEFAULT_TERRAIN: &str = include_str!("../../assets/terrain/default.ron");
+
+impl NoiseGraph {
+    pub fn default_terrain() -> NoiseGraph {
+        ron::de::from_str(DEFAULT_TERRAIN).expect("The default terrain noise graph is invalid")
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> ron::Result<NoiseGraph> {
+        let file = File::open(path).map_err(ron::Error::from)?;
+        ron::de::from_reader(file)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> ron::Result<()> {
+        let serialized = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(path, serialized).map_err(ron::Error::from)
+    }
+
+    pub fn node(&self, name: &str) -> Result<&NoiseNode, NoiseGraphError> {
+        self.nodes.get(name).ok_or_else(|| NoiseGraphError::MissingNode(name.to_string()))
+    }
+}
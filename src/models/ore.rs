@@ -0,0 +1,39 @@
+use crate::models::*;
+
+/// Where an ore appears underground and how much of it there is
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct OreDefinition {
+    pub material: u8,
+    /// The heights veins start between, in voxels
+    pub min_height: i32,
+    pub max_height: i32,
+    /// How many veins start in each chunk in the height range, on average
+    pub veins_per_chunk: f32,
+    /// How many voxels of ore are in each vein
+    pub vein_size: usize
+}
+
+/// Every ore, which only ever replaces stone
+pub const ORES: [OreDefinition; 3] = [
+    OreDefinition {
+        material: COAL_ORE,
+        min_height: -512,
+        max_height: 16,
+        veins_per_chunk: 3.0,
+        vein_size: 12
+    },
+    OreDefinition {
+        material: IRON_ORE,
+        min_height: -512,
+        max_height: -32,
+        veins_per_chunk: 2.0,
+        vein_size: 8
+    },
+    OreDefinition {
+        material: GOLD_ORE,
+        min_height: -1024,
+        max_height: -96,
+        veins_per_chunk: 0.5,
+        vein_size: 6
+    }
+];
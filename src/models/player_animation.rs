@@ -0,0 +1,14 @@
+use serde::{Serialize, Deserialize};
+
+/// What a player's body is doing, so other clients can pose it
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlayerAnimation {
+    Idle,
+    Walking,
+    Jumping,
+    Falling
+}
+
+impl Default for PlayerAnimation {
+    fn default() -> Self { PlayerAnimation::Idle }
+}
@@ -0,0 +1,30 @@
+use serde::{Serialize, Deserialize};
+
+const PLAYER_COLORS: [[f32; 3]; 8] = [
+    [0.9, 0.2, 0.2],
+    [0.2, 0.6, 0.9],
+    [0.3, 0.8, 0.3],
+    [0.9, 0.7, 0.2],
+    [0.7, 0.3, 0.9],
+    [0.2, 0.8, 0.8],
+    [0.9, 0.5, 0.7],
+    [0.6, 0.6, 0.6]
+];
+
+/// How a player presents themselves to others, sent once when their entity spawns
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlayerProfile {
+    pub name: String,
+    /// Linear RGB
+    pub color: [f32; 3]
+}
+
+impl PlayerProfile {
+    /// A placeholder profile until clients can choose their own
+    pub fn for_client(client_id: u128) -> PlayerProfile {
+        PlayerProfile {
+            name: format!("Player {}", client_id),
+            color: PLAYER_COLORS[(client_id % PLAYER_COLORS.len() as u128) as usize]
+        }
+    }
+}
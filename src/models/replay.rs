@@ -0,0 +1,196 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, ErrorKind, Read, Write},
+    path::Path
+};
+use serde::{Serialize, Deserialize};
+use crate::models::*;
+
+/// Identifies a file as a craft replay
+pub const REPLAY_MAGIC: [u8; 4] = *b"CRPL";
+
+/// Bumped whenever the layout of `ReplayHeader` or `ReplayEvent` changes
+pub const REPLAY_VERSION: u16 = 3;
+
+/// Session-wide information needed to reproduce a recording
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct ReplayHeader {
+    pub version: u16,
+    pub world_seed: u64,
+    pub tick_rate: u16
+}
+
+/// A single recorded occurrence, in the order it was received
+#[derive(Clone, Serialize, Deserialize)]
+pub enum ReplayEvent {
+    ClientAuthorized {
+        frame: u32,
+        client_id: u128,
+        /// The entity spawned for the client, which later command and state frames refer to
+        entity_id: u32
+    },
+    CommandFrame {
+        from: u128,
+        command_frame: CommandFrame
+    },
+    StateFrame(StateFrame),
+    /// The world being played, at the start and whenever the terrain graph was reloaded
+    WorldInfo {
+        frame: u32,
+        world_info: WorldInfo
+    },
+    /// A brush the server accepted, in the order it was accepted
+    VoxelEdit {
+        frame: u32,
+        brush: Brush
+    }
+}
+
+impl ReplayEvent {
+    pub fn frame(&self) -> u32 {
+        match self {
+            ReplayEvent::ClientAuthorized { frame, .. } => *frame,
+            ReplayEvent::CommandFrame { command_frame, .. } => command_frame.frame,
+            ReplayEvent::StateFrame(state_frame) => state_frame.frame,
+            ReplayEvent::WorldInfo { frame, .. } => *frame,
+            ReplayEvent::VoxelEdit { frame, .. } => *frame
+        }
+    }
+}
+
+/// An in-memory recording of a session, ordered by simulation frame
+pub struct Replay {
+    header: ReplayHeader,
+    events: Vec<ReplayEvent>
+}
+
+impl Replay {
+    pub fn new(world_seed: u64, tick_rate: u16) -> Replay {
+        Replay {
+            header: ReplayHeader {
+                version: REPLAY_VERSION,
+                world_seed,
+                tick_rate
+            },
+            events: Vec::new()
+        }
+    }
+
+    pub fn header(&self) -> &ReplayHeader {
+        &self.header
+    }
+
+    pub fn events(&self) -> &[ReplayEvent] {
+        &self.events
+    }
+
+    pub fn first_frame(&self) -> u32 {
+        self.events.first().map(|event| event.frame()).unwrap_or(0)
+    }
+
+    pub fn last_frame(&self) -> u32 {
+        self.events.last().map(|event| event.frame()).unwrap_or(0)
+    }
+
+    /// Index of the first event at or after `frame`
+    pub fn index_of_frame(&self, frame: u32) -> usize {
+        match self.events.binary_search_by(|event| {
+            if event.frame() < frame { std::cmp::Ordering::Less } else { std::cmp::Ordering::Greater }
+        }) {
+            Ok(index) | Err(index) => index
+        }
+    }
+
+    /// Appends an event; events arriving out of order are moved into place
+    pub fn push(&mut self, event: ReplayEvent) {
+        let index = self.index_of_frame(event.frame().saturating_add(1));
+        self.events.insert(index, event);
+    }
+
+    pub fn read_from(reader: impl Read) -> bincode::Result<Replay> {
+        let mut reader = BufReader::new(reader);
+        let header = read_header(&mut reader)?;
+        let mut replay = Replay {
+            header,
+            events: Vec::new()
+        };
+
+        loop {
+            match bincode::deserialize_from::<_, ReplayEvent>(&mut reader) {
+                Ok(event) => replay.push(event),
+                Err(error) => match *error {
+                    bincode::ErrorKind::Io(ref io_error) if io_error.kind() == ErrorKind::UnexpectedEof => break,
+                    _ => return Err(error)
+                }
+            }
+        }
+
+        Ok(replay)
+    }
+
+    pub fn write_to(&self, writer: impl Write) -> bincode::Result<()> {
+        let mut writer = ReplayWriter::new(writer, self.header)?;
+
+        for event in self.events.iter() {
+            writer.write_event(event)?;
+        }
+
+        writer.flush()
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> bincode::Result<Replay> {
+        Replay::read_from(File::open(path)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> bincode::Result<()> {
+        self.write_to(File::create(path)?)
+    }
+}
+
+/// Streams replay events to a writer as they happen, so a crash loses at most the unflushed tail
+pub struct ReplayWriter<W: Write> {
+    writer: BufWriter<W>
+}
+
+impl<W: Write> ReplayWriter<W> {
+    pub fn new(writer: W, header: ReplayHeader) -> bincode::Result<ReplayWriter<W>> {
+        let mut writer = BufWriter::new(writer);
+        writer.write_all(&REPLAY_MAGIC)?;
+        writer.write_all(&header.version.to_le_bytes())?;
+        bincode::serialize_into(&mut writer, &header)?;
+
+        Ok(ReplayWriter {
+            writer
+        })
+    }
+
+    pub fn write_event(&mut self, event: &ReplayEvent) -> bincode::Result<()> {
+        bincode::serialize_into(&mut self.writer, event)
+    }
+
+    pub fn flush(&mut self) -> bincode::Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+fn read_header(reader: &mut impl Read) -> bincode::Result<ReplayHeader> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+
+    if magic != REPLAY_MAGIC {
+        return Err(Box::new(bincode::ErrorKind::Custom(String::from("Not a replay file"))));
+    }
+
+    let mut version = [0u8; 2];
+    reader.read_exact(&mut version)?;
+    let version = u16::from_le_bytes(version);
+
+    if version != REPLAY_VERSION {
+        return Err(Box::new(bincode::ErrorKind::Custom(format!(
+            "Unsupported replay version {} (expected {})", version, REPLAY_VERSION
+        ))));
+    }
+
+    bincode::deserialize_from(reader)
+}
@@ -0,0 +1,366 @@
+use bevy::math::Vec3;
+use crate::models::*;
+
+/// The material of empty space
+pub const EMPTY_MATERIAL: u32 = 0;
+
+/// Set on flattened nodes which have children; the remaining bits hold the index of the first
+/// of their eight consecutive children. Leaves hold their material instead.
+pub const OCTREE_BRANCH_FLAG: u32 = 0x8000_0000;
+
+#[derive(Clone, Debug, PartialEq)]
+enum OctreeNode {
+    Leaf(u32),
+    Branch {
+        children: Box<[OctreeNode; 8]>,
+        /// The material this node is drawn with when viewed at a coarser level of detail
+        lod_material: u32
+    }
+}
+
+/// Where a ray first enters a solid voxel
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct OctreeHit {
+    /// The voxel's position within the octree
+    pub position: [u32; 3],
+    /// The size of the solid node hit, in voxels
+    pub size: u32,
+    pub material: u32,
+    pub distance: f32,
+    pub normal: Vec3
+}
+
+/// A cube of `2^depth` voxels on each side, stored as an octree so that uniform regions take
+/// a single node. Positions are relative to the octree's minimum corner.
+///
+/// Children are numbered with bit 0 set for the upper half along x, bit 1 for y and bit 2 for z.
+#[derive(Clone, Debug)]
+pub struct SparseVoxelOctree {
+    depth: u32,
+    root: OctreeNode
+}
+
+impl SparseVoxelOctree {
+    pub fn new(depth: u32) -> SparseVoxelOctree {
+        assert!(depth < 31, "An octree can't be deeper than 30 levels");
+
+        SparseVoxelOctree {
+            depth,
+            root: OctreeNode::Leaf(EMPTY_MATERIAL)
+        }
+    }
+
+    /// An octree exactly covering one chunk, using each solid voxel's material
+    pub fn from_chunk(chunk: &Chunk) -> SparseVoxelOctree {
+        let mut octree = SparseVoxelOctree::new(CHUNK_SIZE.trailing_zeros());
+        octree.insert_chunk([0, 0, 0], chunk);
+        octree
+    }
+
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// The number of voxels along each edge
+    pub fn size(&self) -> u32 {
+        1 << self.depth
+    }
+
+    /// Copies a chunk's solid voxels into the octree with its minimum corner at `offset`
+    pub fn insert_chunk(&mut self, offset: [u32; 3], chunk: &Chunk) {
+        if let Some(voxel) = chunk.storage().uniform() {
+            if !voxel.is_solid() {
+                return;
+            }
+        }
+
+        for (LocalVoxelPos(x, y, z), voxel) in chunk.iter() {
+            if voxel.is_solid() {
+                self.insert([offset[0] + x as u32, offset[1] + y as u32, offset[2] + z as u32], voxel.material as u32);
+            }
+        }
+    }
+
+    /// The material at a position, `EMPTY_MATERIAL` if it's empty or outside the octree
+    pub fn get(&self, position: [u32; 3]) -> u32 {
+        self.get_lod(position, 0)
+    }
+
+    /// The material at a position as seen `lod` levels above full detail, where each voxel
+    /// covers `2^lod` voxels on each side
+    pub fn get_lod(&self, position: [u32; 3], lod: u32) -> u32 {
+        if !self.contains(position) {
+            return EMPTY_MATERIAL;
+        }
+
+        let mut node = &self.root;
+        let mut level = self.depth;
+
+        loop {
+            match node {
+                OctreeNode::Leaf(material) => return *material,
+                OctreeNode::Branch { children, lod_material } => {
+                    if level <= lod {
+                        return *lod_material;
+                    }
+
+                    level -= 1;
+                    node = &children[child_index(position, level)];
+                }
+            }
+        }
+    }
+
+    /// Fills a voxel with a material, returning the material that was there before
+    pub fn insert(&mut self, position: [u32; 3], material: u32) -> u32 {
+        assert!(self.contains(position), "{:?} is outside an octree of size {}", position, self.size());
+
+        set(&mut self.root, position, self.depth, material)
+    }
+
+    /// Empties a voxel, returning the material that was there before
+    pub fn remove(&mut self, position: [u32; 3]) -> u32 {
+        if !self.contains(position) {
+            return EMPTY_MATERIAL;
+        }
+
+        set(&mut self.root, position, self.depth, EMPTY_MATERIAL)
+    }
+
+    pub fn contains(&self, position: [u32; 3]) -> bool {
+        let size = self.size();
+        position[0] < size && position[1] < size && position[2] < size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root == OctreeNode::Leaf(EMPTY_MATERIAL)
+    }
+
+    /// The number of nodes, which is also the length of the flattened octree
+    pub fn node_count(&self) -> usize {
+        fn count(node: &OctreeNode) -> usize {
+            match node {
+                OctreeNode::Leaf(_) => 1,
+                OctreeNode::Branch { children, .. } => 1 + children.iter().map(count).sum::<usize>()
+            }
+        }
+
+        count(&self.root)
+    }
+
+    /// Finds the first solid voxel along a ray within `max_distance`. The ray is in the octree's
+    /// space, where each voxel is one unit.
+    pub fn raycast(&self, origin: Vec3, direction: Vec3, max_distance: f32) -> Option<OctreeHit> {
+        if direction.length_squared() == 0.0 {
+            return None;
+        }
+
+        let direction = direction.normalize();
+        // Axis-aligned rays would divide by zero, so nudge them to a huge but finite inverse
+        let inverse = |component: f32| 1.0 / if component == 0.0 { f32::MIN_POSITIVE } else { component };
+        let inverse_direction = Vec3::new(inverse(direction.x), inverse(direction.y), inverse(direction.z));
+        let ray = Ray { origin, direction, inverse_direction, max_distance };
+
+        raycast_node(&self.root, [0, 0, 0], self.size(), &ray)
+    }
+
+    /// Lays the octree out breadth first as it is uploaded to the GPU. Node 0 is the root. A
+    /// branch stores `OCTREE_BRANCH_FLAG` plus the index of the first of its eight children,
+    /// which are consecutive; a leaf stores its material.
+    pub fn flatten(&self) -> Vec<u32> {
+        let mut nodes = vec![0];
+        let mut queue = std::collections::VecDeque::new();
+        queue.push_back((&self.root, 0));
+
+        while let Some((node, index)) = queue.pop_front() {
+            match node {
+                OctreeNode::Leaf(material) => nodes[index] = *material,
+                OctreeNode::Branch { children, .. } => {
+                    let first_child = nodes.len();
+                    nodes[index] = OCTREE_BRANCH_FLAG | first_child as u32;
+                    nodes.resize(first_child + 8, 0);
+
+                    for (offset, child) in children.iter().enumerate() {
+                        queue.push_back((child, first_child + offset));
+                    }
+                }
+            }
+        }
+
+        nodes
+    }
+}
+
+struct Ray {
+    origin: Vec3,
+    direction: Vec3,
+    inverse_direction: Vec3,
+    max_distance: f32
+}
+
+impl Ray {
+    /// The distances at which the ray enters and leaves a box, if it hits it in range
+    fn intersect(&self, min: Vec3, max: Vec3) -> Option<(f32, f32)> {
+        let t0 = (min - self.origin) * self.inverse_direction;
+        let t1 = (max - self.origin) * self.inverse_direction;
+        let near = t0.min(t1);
+        let far = t0.max(t1);
+
+        let enter = near.x.max(near.y).max(near.z).max(0.0);
+        let exit = far.x.min(far.y).min(far.z).min(self.max_distance);
+
+        if enter <= exit { Some((enter, exit)) } else { None }
+    }
+}
+
+fn child_index(position: [u32; 3], level: u32) -> usize {
+    (((position[0] >> level) & 1) | (((position[1] >> level) & 1) << 1) | (((position[2] >> level) & 1) << 2)) as usize
+}
+
+/// Writes a material below `node`, which covers `2^level` voxels on each side, splitting leaves
+/// on the way down and merging children that end up identical on the way back up
+fn set(node: &mut OctreeNode, position: [u32; 3], level: u32, material: u32) -> u32 {
+    if level == 0 {
+        let previous = match node {
+            OctreeNode::Leaf(previous) => *previous,
+            OctreeNode::Branch { .. } => unreachable!("Voxels can't have children")
+        };
+
+        *node = OctreeNode::Leaf(material);
+        return previous;
+    }
+
+    if let OctreeNode::Leaf(filled) = *node {
+        if filled == material {
+            return material;
+        }
+
+        *node = OctreeNode::Branch {
+            children: Box::new([
+                OctreeNode::Leaf(filled), OctreeNode::Leaf(filled), OctreeNode::Leaf(filled), OctreeNode::Leaf(filled),
+                OctreeNode::Leaf(filled), OctreeNode::Leaf(filled), OctreeNode::Leaf(filled), OctreeNode::Leaf(filled)
+            ]),
+            lod_material: filled
+        };
+    }
+
+    let previous = match node {
+        OctreeNode::Branch { children, .. } => set(&mut children[child_index(position, level - 1)], position, level - 1, material),
+        OctreeNode::Leaf(_) => unreachable!()
+    };
+
+    let merged = match node {
+        OctreeNode::Branch { children, lod_material } => {
+            match children[0] {
+                OctreeNode::Leaf(first) if children.iter().all(|child| *child == OctreeNode::Leaf(first)) => Some(first),
+                _ => {
+                    *lod_material = representative_material(children);
+                    None
+                }
+            }
+        },
+        OctreeNode::Leaf(_) => unreachable!()
+    };
+
+    if let Some(material) = merged {
+        *node = OctreeNode::Leaf(material);
+    }
+
+    previous
+}
+
+/// The most common non-empty material among a node's children, so thin features stay visible
+/// at lower detail. Ties go to the lowest material.
+fn representative_material(children: &[OctreeNode; 8]) -> u32 {
+    let mut materials: Vec<u32> = children.iter()
+        .map(|child| match child {
+            OctreeNode::Leaf(material) => *material,
+            OctreeNode::Branch { lod_material, .. } => *lod_material
+        })
+        .filter(|material| *material != EMPTY_MATERIAL)
+        .collect();
+
+    materials.sort();
+
+    let mut best = EMPTY_MATERIAL;
+    let mut best_count = 0;
+    let mut index = 0;
+    while index < materials.len() {
+        let count = materials[index..].iter().take_while(|material| **material == materials[index]).count();
+        if count > best_count {
+            best = materials[index];
+            best_count = count;
+        }
+
+        index += count;
+    }
+
+    best
+}
+
+fn raycast_node(node: &OctreeNode, min: [u32; 3], size: u32, ray: &Ray) -> Option<OctreeHit> {
+    let min_corner = Vec3::new(min[0] as f32, min[1] as f32, min[2] as f32);
+    let max_corner = min_corner + Vec3::new(size as f32, size as f32, size as f32);
+    let (enter, _) = ray.intersect(min_corner, max_corner)?;
+
+    match node {
+        OctreeNode::Leaf(EMPTY_MATERIAL) => None,
+        OctreeNode::Leaf(material) => {
+            let point = ray.origin + ray.direction * enter;
+
+            Some(OctreeHit {
+                position: [
+                    (point.x.floor() as u32).max(min[0]).min(min[0] + size - 1),
+                    (point.y.floor() as u32).max(min[1]).min(min[1] + size - 1),
+                    (point.z.floor() as u32).max(min[2]).min(min[2] + size - 1)
+                ],
+                size,
+                material: *material,
+                distance: enter,
+                normal: entry_normal(point, min_corner, max_corner, ray.direction)
+            })
+        },
+        OctreeNode::Branch { children, .. } => {
+            let half = size / 2;
+
+            // Visit the children in the order the ray passes through them
+            let mut order: Vec<(f32, usize, [u32; 3])> = (0..8).filter_map(|index| {
+                let child_min = [
+                    min[0] + (index as u32 & 1) * half,
+                    min[1] + ((index as u32 >> 1) & 1) * half,
+                    min[2] + ((index as u32 >> 2) & 1) * half
+                ];
+                let child_min_corner = Vec3::new(child_min[0] as f32, child_min[1] as f32, child_min[2] as f32);
+                let child_max_corner = child_min_corner + Vec3::new(half as f32, half as f32, half as f32);
+
+                ray.intersect(child_min_corner, child_max_corner).map(|(enter, _)| (enter, index, child_min))
+            }).collect();
+
+            order.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+            order.into_iter().find_map(|(_, index, child_min)| raycast_node(&children[index], child_min, half, ray))
+        }
+    }
+}
+
+/// The normal of the box face a ray entered through, or the face opposite the ray if it started
+/// inside the box
+fn entry_normal(point: Vec3, min: Vec3, max: Vec3, direction: Vec3) -> Vec3 {
+    let mut best = (f32::MAX, Vec3::zero());
+    let candidates = [
+        ((point.x - min.x).abs(), Vec3::new(-1.0, 0.0, 0.0), direction.x > 0.0),
+        ((point.x - max.x).abs(), Vec3::new(1.0, 0.0, 0.0), direction.x < 0.0),
+        ((point.y - min.y).abs(), Vec3::new(0.0, -1.0, 0.0), direction.y > 0.0),
+        ((point.y - max.y).abs(), Vec3::new(0.0, 1.0, 0.0), direction.y < 0.0),
+        ((point.z - min.z).abs(), Vec3::new(0.0, 0.0, -1.0), direction.z > 0.0),
+        ((point.z - max.z).abs(), Vec3::new(0.0, 0.0, 1.0), direction.z < 0.0)
+    ];
+
+    for (distance, normal, facing) in candidates.iter() {
+        if *facing && *distance < best.0 {
+            best = (*distance, *normal);
+        }
+    }
+
+    best.1
+}
@@ -0,0 +1,47 @@
+use serde::{Serialize, Deserialize};
+
+/// A single cell of the voxel world.
+///
+/// `density` is a quantized signed distance to the surface: positive inside solid ground and
+/// negative in open space, which lets smooth meshers place vertices between voxels.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Voxel {
+    pub material: u8,
+    pub density: i8
+}
+
+impl Voxel {
+    pub const AIR: Voxel = Voxel { material: 0, density: -127 };
+
+    /// A voxel entirely filled with `material`
+    pub fn solid(material: u8) -> Voxel {
+        Voxel {
+            material,
+            density: 127
+        }
+    }
+
+    /// Builds a voxel from a density in the range `-1.0..=1.0`, which is clamped. Voxels which
+    /// end up empty lose their material.
+    pub fn from_density(material: u8, density: f32) -> Voxel {
+        let density = (density.max(-1.0).min(1.0) * 127.0).round() as i8;
+
+        Voxel {
+            material: if density > 0 { material } else { 0 },
+            density
+        }
+    }
+
+    /// The density in the range `-1.0..=1.0`
+    pub fn density(&self) -> f32 {
+        self.density as f32 / 127.0
+    }
+
+    pub fn is_solid(&self) -> bool {
+        self.density > 0
+    }
+}
+
+impl Default for Voxel {
+    fn default() -> Self { Voxel::AIR }
+}
@@ -0,0 +1,54 @@
+use serde::{Serialize, Deserialize};
+use crate::models::*;
+
+/// A client asking the server to apply a brush to the world
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VoxelEditRequest {
+    /// Picked by the client, to match the server's answer with the edit it predicted
+    pub id: u32,
+    pub brush: Brush
+}
+
+/// Why the server turned down an edit
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VoxelEditRejection {
+    /// The client isn't allowed to change the world
+    NotPermitted,
+    /// The client has no player in the world to edit from
+    NoPlayer,
+    /// The brush is further from the player's head than they can reach
+    OutOfReach,
+    /// The brush is bigger than players may use, or isn't a brush at all
+    InvalidBrush,
+    /// The client has sent too many edits too quickly
+    RateLimited
+}
+
+/// An edit the server accepted. Every client applies them in `sequence` order.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VoxelEdit {
+    /// Counts up from 1 with every edit the server accepts
+    pub sequence: u64,
+    pub brush: Brush,
+    /// Every chunk the brush reaches, with the revision this edit moves it on to
+    pub revisions: Vec<(ChunkPos, u64)>,
+    /// The request this answers, only in the copy sent to the client that made it
+    pub request: Option<u32>
+}
+
+/// The revision of a chunk no edit has reached, which every client can generate from the seed
+pub const UNEDITED_REVISION: u64 = 0;
+
+/// The revision of a chunk once an edit reaches it. Revisions hash every edit made to a chunk in
+/// order, so two copies of a chunk at the same revision hold the same voxels.
+pub fn next_revision(revision: u64, sequence: u64, brush: &Brush) -> u64 {
+    let bytes = bincode::serialize(&(revision, sequence, brush)).unwrap();
+
+    // FNV-1a, which hashes the same on every platform
+    let hash = bytes.iter().fold(0xCBF2_9CE4_8422_2325u64, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01B3)
+    });
+
+    // An edited chunk can never be mistaken for one to generate
+    hash.max(UNEDITED_REVISION + 1)
+}
@@ -0,0 +1,39 @@
+use crate::models::*;
+
+/// Which voxels a `VoxelWrite` may replace, so features don't cut into each other or the ground
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WriteRule {
+    /// Only fills empty space, like the leaves of a tree
+    IntoAir,
+    /// Only replaces one material, like ore replacing stone
+    IntoMaterial(u8)
+}
+
+/// A voxel a world feature places, which may be in a chunk other than the one placing it
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct VoxelWrite {
+    pub position: VoxelPos,
+    pub voxel: Voxel,
+    pub rule: WriteRule
+}
+
+impl VoxelWrite {
+    /// Applies the write to the chunk it falls in, returning whether the chunk changed.
+    /// Applying a write twice changes nothing the second time.
+    pub fn apply(&self, chunk: &mut Chunk) -> bool {
+        let local = self.position.local();
+        let current = chunk.get(local);
+
+        let allowed = match self.rule {
+            WriteRule::IntoAir => !current.is_solid(),
+            WriteRule::IntoMaterial(material) => current.is_solid() && current.material == material
+        };
+
+        if !allowed || current == self.voxel {
+            return false;
+        }
+
+        chunk.set(local, self.voxel);
+        true
+    }
+}
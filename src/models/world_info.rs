@@ -0,0 +1,14 @@
+use serde::{Serialize, Deserialize};
+use crate::models::{CaveSettings, MeshingMethod, NoiseGraph};
+
+/// What a client needs to know about the world it joined, sent once it is authorized and again
+/// whenever the server reloads its terrain graph. It goes out on the same stream as the chunk
+/// revisions, so none sent for the new terrain arrive before it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WorldInfo {
+    pub seed: u64,
+    pub meshing_method: MeshingMethod,
+    /// The server's terrain graph and caves, which the client's terrain has to match
+    pub graph: NoiseGraph,
+    pub caves: CaveSettings
+}
@@ -2,6 +2,8 @@ mod entity;
 mod graph;
 mod material;
 mod plugin;
+mod terrain_material;
+mod terrain_textures;
 mod voxel_volume;
 
 pub use self::{
@@ -9,5 +11,7 @@ pub use self::{
     graph::*,
     material::*,
     plugin::*,
+    terrain_material::*,
+    terrain_textures::*,
     voxel_volume::*
 };
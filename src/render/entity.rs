@@ -1,7 +1,7 @@
 use bevy::{ecs::{Bundle, ResMut}, prelude::{Assets, Draw, GlobalTransform, Handle, Mesh, RenderPipelines, Transform, shape::{self, Quad}}, render::{prelude::Visible, mesh::VertexAttributeValues, pipeline::{PrimitiveTopology, RenderPipeline}, render_graph::base::MainPass}, math::Vec2};
 
 use crate::render::material::VoxelMaterial;
-use crate::render::VoxelVolume;
+use crate::render::{VoxelVolume, TerrainMaterial};
 
 #[derive(Bundle)]
 pub struct VoxelBundle {
@@ -31,3 +31,31 @@ impl Default for VoxelBundle {
         }
     }
 }
+
+/// A terrain mesh drawn with a `TerrainMaterial`
+#[derive(Bundle)]
+pub struct TerrainBundle {
+    pub mesh: Handle<Mesh>,
+    pub material: Handle<TerrainMaterial>,
+    pub main_pass: MainPass,
+    pub draw: Draw,
+    pub visible: Visible,
+    pub render_pipelines: RenderPipelines,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+}
+
+impl Default for TerrainBundle {
+    fn default() -> Self {
+        Self {
+            render_pipelines: RenderPipelines::from_pipelines(vec![RenderPipeline::new(super::graph::pipeline::TERRAIN_PIPELINE_HANDLE.typed())]),
+            mesh: Default::default(),
+            material: Default::default(),
+            main_pass: Default::default(),
+            draw: Default::default(),
+            visible: Default::default(),
+            transform: Default::default(),
+            global_transform: Default::default()
+        }
+    }
+}
@@ -9,6 +9,7 @@ use bevy::{
     transform::prelude::GlobalTransform,
 };
 use super::voxel_volume::VoxelVolume;
+use super::terrain_material::TerrainMaterial;
 
 use nodes::TimeNode;
 use nodes::ResolutionNode;
@@ -21,6 +22,7 @@ pub mod node {
     pub const RESOLUTION: &str = "resolution";
     pub const TRANSFORM: &str = "transform";
     pub const VOXEL_VOLUME: &str = "voxel_volume";
+    pub const TERRAIN_MATERIAL: &str = "terrain_material";
     // pub const VOXEL_PASS: &str = "voxel_pass";
 }
 
@@ -50,3 +52,19 @@ pub(crate) fn add_voxel_graph(graph: &mut RenderGraph, resources: &Resources) {
     graph.add_node_edge(node::VOXEL_VOLUME, base::node::MAIN_PASS).unwrap();
     graph.add_node_edge(node::TRANSFORM, base::node::MAIN_PASS).unwrap();
 }
+
+pub(crate) fn add_terrain_graph(graph: &mut RenderGraph, resources: &Resources) {
+    graph.add_system_node(
+        node::TERRAIN_MATERIAL,
+        AssetRenderResourcesNode::<TerrainMaterial>::new(true)
+    );
+
+    let mut shaders = resources.get_mut::<Assets<Shader>>().unwrap();
+    let mut pipelines = resources.get_mut::<Assets<PipelineDescriptor>>().unwrap();
+    pipelines.set_untracked(
+        pipeline::TERRAIN_PIPELINE_HANDLE,
+        pipeline::build_terrain_pipeline(&mut shaders),
+    );
+
+    graph.add_node_edge(node::TERRAIN_MATERIAL, base::node::MAIN_PASS).unwrap();
+}
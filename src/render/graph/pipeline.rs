@@ -10,10 +10,36 @@ use bevy::{
     },
     reflect::{TypeUuid}
 };
+use crate::models::MATERIAL_COUNT;
 
 pub const PIPELINE_HANDLE: HandleUntyped =
     HandleUntyped::weak_from_u64(PipelineDescriptor::TYPE_UUID, 12585943984739023957);
 
+pub const TERRAIN_PIPELINE_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(PipelineDescriptor::TYPE_UUID, 605617248088294680);
+
+pub(crate) fn build_terrain_pipeline(shaders: &mut Assets<Shader>) -> PipelineDescriptor {
+    PipelineDescriptor::default_config(ShaderStages {
+        vertex: shaders.add(Shader::from_glsl(
+            ShaderStage::Vertex,
+            include_str!("terrain.vs"),
+        )),
+        fragment: Some(shaders.add(Shader::from_glsl(
+            ShaderStage::Fragment,
+            &terrain_fragment_shader(),
+        ))),
+    })
+}
+
+/// The terrain fragment shader, with its palette sized to hold every material
+fn terrain_fragment_shader() -> String {
+    include_str!("terrain.fs").replacen(
+        "#version 450",
+        &format!("#version 450\n#define MATERIAL_COUNT {}", MATERIAL_COUNT),
+        1
+    )
+}
+
 pub(crate) fn build_pipeline(shaders: &mut Assets<Shader>) -> PipelineDescriptor {
     PipelineDescriptor::default_config(ShaderStages {
         vertex: shaders.add(Shader::from_glsl(
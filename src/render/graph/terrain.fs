@@ -0,0 +1,45 @@
+#version 450
+
+layout(location = 0) in vec3 v_WorldPosition;
+layout(location = 1) in vec3 v_WorldNormal;
+layout(location = 2) flat in uint v_Material;
+
+layout(location = 0) out vec4 o_Target;
+
+// MATERIAL_COUNT is defined by `build_terrain_pipeline` from `models::MATERIAL_COUNT`
+layout(set = 2, binding = 0) uniform TerrainMaterial_palette {
+    vec4[MATERIAL_COUNT] terrain_material_palette;
+};
+
+layout(set = 2, binding = 1) uniform TerrainMaterial_texture_scale {
+    float texture_scale;
+};
+
+# ifdef TERRAINMATERIAL_TEXTURES
+layout(set = 2, binding = 2) uniform texture2DArray TerrainMaterial_textures;
+layout(set = 2, binding = 3) uniform sampler TerrainMaterial_textures_sampler;
+# endif
+
+vec3 SunDirection = normalize(vec3(0.4, 1.0, 0.3));
+float Ambient = 0.35;
+
+void main() {
+    vec3 Normal = normalize(v_WorldNormal);
+
+# ifdef TERRAINMATERIAL_TEXTURES
+    // Project the material's layer along each axis, weighted by how squarely the surface faces it
+    vec3 Weights = abs(Normal);
+    Weights /= Weights.x + Weights.y + Weights.z;
+    vec3 Position = v_WorldPosition / texture_scale;
+    float Layer = float(v_Material);
+    vec4 Color =
+        texture(sampler2DArray(TerrainMaterial_textures, TerrainMaterial_textures_sampler), vec3(Position.zy, Layer)) * Weights.x +
+        texture(sampler2DArray(TerrainMaterial_textures, TerrainMaterial_textures_sampler), vec3(Position.xz, Layer)) * Weights.y +
+        texture(sampler2DArray(TerrainMaterial_textures, TerrainMaterial_textures_sampler), vec3(Position.xy, Layer)) * Weights.z;
+# else
+    vec4 Color = terrain_material_palette[v_Material];
+# endif
+
+    float Light = Ambient + (1.0 - Ambient) * max(dot(Normal, SunDirection), 0.0);
+    o_Target = vec4(Color.rgb * Light, 1.0);
+}
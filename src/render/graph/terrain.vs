@@ -0,0 +1,24 @@
+#version 450
+
+layout(location = 0) in vec3 Vertex_Position;
+layout(location = 1) in vec3 Vertex_Normal;
+layout(location = 2) in float Vertex_Material;
+
+layout(location = 0) out vec3 v_WorldPosition;
+layout(location = 1) out vec3 v_WorldNormal;
+layout(location = 2) flat out uint v_Material;
+
+layout(set = 0, binding = 0) uniform Camera {
+    mat4 ViewProj;
+};
+
+layout(set = 1, binding = 0) uniform Transform {
+    mat4 Model;
+};
+
+void main() {
+    v_WorldPosition = (Model * vec4(Vertex_Position, 1.0)).xyz;
+    v_WorldNormal = mat3(Model) * Vertex_Normal;
+    v_Material = uint(Vertex_Material + 0.5);
+    gl_Position = ViewProj * vec4(v_WorldPosition, 1.0);
+}
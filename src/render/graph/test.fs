@@ -23,11 +23,13 @@ layout(set = 1, binding = 1) uniform Resolution {
     float ScreenResolutionY;
 };
 
-struct VoxelData {
-    uint material;
+// A flattened sparse voxel octree. Branches have the top bit set and the rest holds the index
+// of their first of eight consecutive children, leaves hold a palette index.
+struct VoxelNode {
+    uint data;
 };
-layout(set = 2, binding = 0) buffer VoxelVolume_data {
-    VoxelData[] voxel_volume_data;
+layout(set = 2, binding = 0) buffer VoxelVolume_nodes {
+    VoxelNode[] voxel_volume_nodes;
 };
 
 layout(set = 2, binding = 1) uniform VoxelVolume_size {
@@ -35,7 +37,7 @@ layout(set = 2, binding = 1) uniform VoxelVolume_size {
 };
 
 layout(set = 2, binding = 2) uniform VoxelVolume_palette {
-    vec4[6] voxel_volume_palette;
+    vec4[17] voxel_volume_palette;
 };
 
 vec3 LightPosition = vec3(0.0, 100.0, 0.0);
@@ -48,6 +50,23 @@ vec3 HSL2RGB(in vec3 HSL) {
     return HSL.z + HSL.y * (RGB - 0.5) * (1.0 - abs(2.0 * HSL.z - 1.0));
 }
 
+const uint BRANCH_FLAG = 0x80000000u;
+
+// Descend the octree to the leaf containing a voxel, returning its material.
+uint SampleOctree(in vec3 Position) {
+    uvec3 Voxel = uvec3(Position);
+    uint HalfSize = uint(voxel_volume_size.x) / 2u;
+    uint Node = voxel_volume_nodes[0].data;
+    while ((Node & BRANCH_FLAG) != 0u && HalfSize > 0u) {
+        uvec3 Upper = uvec3(greaterThanEqual(Voxel, uvec3(HalfSize)));
+        uint Child = Upper.x | (Upper.y << 1u) | (Upper.z << 2u);
+        Node = voxel_volume_nodes[(Node & ~BRANCH_FLAG) + Child].data;
+        Voxel -= Upper * HalfSize;
+        HalfSize /= 2u;
+    }
+    return Node;
+}
+
 // Sampling from the voxel volume.
 vec4 SampleVolume(in vec3 Position) {
     // The voxel data structure (as in Voxel.hpp).
@@ -62,7 +81,10 @@ vec4 SampleVolume(in vec3 Position) {
     // std::uint8_t Density : 2;
     // std::uint8_t Strength : 3;
     // std::uint8_t FillLevel : 3;
-    uint material = voxel_volume_data[uint(Position.z * voxel_volume_size.x * voxel_volume_size.y + Position.y * voxel_volume_size.x + Position.x)].material;
+    if (any(lessThan(Position, vec3(0.0))) || any(greaterThanEqual(Position, voxel_volume_size))) {
+        return vec4(0.0);
+    }
+    uint material = SampleOctree(Position);
     return voxel_volume_palette[material];
     // uint Data = texelFetch(BinarySampler, ivec2(Position.x , (Position.y + voxel_volume_size.y * floor(Position.z))), 0).r;
     // uint SaturationValue = (Data >> uint(0)) & uint(0x3);
@@ -18,3 +18,23 @@ impl Plugin for VoxelRenderPlugin {
         super::graph::add_voxel_graph(&mut render_graph, resources);
     }
 }
+
+/// Draws terrain meshes with a `TerrainMaterial`, textured if there are `TerrainTextures`
+#[derive(Debug, Default)]
+pub struct TerrainRenderPlugin;
+
+impl Plugin for TerrainRenderPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_asset::<super::terrain_material::TerrainMaterial>()
+            .init_resource::<super::terrain_textures::TerrainTextures>()
+            .add_startup_system(super::terrain_textures::load_terrain_textures_system.system())
+            .add_system(super::terrain_textures::terrain_textures_system.system())
+            .add_system_to_stage(
+                stage::POST_UPDATE,
+                shader::asset_shader_defs_system::<super::terrain_material::TerrainMaterial>.system(),
+            );
+        let resources = app.resources();
+        let mut render_graph = resources.get_mut::<RenderGraph>().unwrap();
+        super::graph::add_terrain_graph(&mut render_graph, resources);
+    }
+}
@@ -0,0 +1,40 @@
+use bevy::{
+    prelude::*,
+    render::{renderer::RenderResources, shader::ShaderDefs},
+    reflect::TypeUuid
+};
+use crate::models::*;
+
+/// How terrain meshes are colored. Each vertex carries the index of its voxel's material, which
+/// picks a layer of `textures` if there is one, or a color from `palette` otherwise.
+#[derive(Debug, RenderResources, ShaderDefs, TypeUuid)]
+#[uuid = "13e6ca8b-5142-405c-9be3-6ce6426ae267"]
+pub struct TerrainMaterial {
+    /// The color of each material, as `MATERIAL_PALETTE` orders them
+    pub palette: Vec<Vec4>,
+    /// A 2D texture array with a layer for each material, projected onto the terrain from all
+    /// three axes
+    #[shader_def]
+    pub textures: Option<Handle<Texture>>,
+    /// How many voxels one repeat of a texture covers
+    pub texture_scale: f32
+}
+
+impl Default for TerrainMaterial {
+    fn default() -> Self {
+        TerrainMaterial {
+            palette: MATERIAL_PALETTE.iter().map(|color| Vec4::from(*color)).collect(),
+            textures: None,
+            texture_scale: 4.0
+        }
+    }
+}
+
+impl TerrainMaterial {
+    pub fn with_textures(textures: Handle<Texture>) -> TerrainMaterial {
+        TerrainMaterial {
+            textures: Some(textures),
+            ..Default::default()
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use std::path::Path;
+use bevy::{
+    prelude::*,
+    asset::HandleId,
+    render::texture::{AddressMode, Extent3d}
+};
+use crate::models::*;
+use crate::render::TerrainMaterial;
+
+/// Where the terrain's textures are loaded from, relative to the assets folder. The image holds a
+/// square layer for every material, air's included, stacked top to bottom in the order of
+/// `MATERIAL_PALETTE`.
+pub const TERRAIN_TEXTURES_PATH: &str = "textures/terrain.png";
+
+/// The texture array terrain is drawn with. Terrain is colored from the palette if there's no
+/// image, and until it has loaded.
+#[derive(Default)]
+pub struct TerrainTextures {
+    /// The stacked image, while it's loading
+    pub image: Option<Handle<Texture>>,
+    /// The image once its layers have been split into an array
+    pub array: Option<Handle<Texture>>
+}
+
+impl TerrainTextures {
+    /// A material using the textures if they've loaded, or the palette otherwise
+    pub fn material(&self) -> TerrainMaterial {
+        match &self.array {
+            Some(array) => TerrainMaterial::with_textures(array.clone()),
+            None => TerrainMaterial::default()
+        }
+    }
+}
+
+/// Starts loading the terrain's textures, if there are any
+pub fn load_terrain_textures_system(asset_server: Res<AssetServer>, mut terrain_textures: ResMut<TerrainTextures>) {
+    if Path::new("assets").join(TERRAIN_TEXTURES_PATH).exists() {
+        terrain_textures.image = Some(asset_server.load(TERRAIN_TEXTURES_PATH));
+    }
+}
+
+/// Splits the terrain's image into an array with a layer per material once it has loaded, and
+/// gives it to every terrain material
+pub fn terrain_textures_system(
+    mut terrain_textures: ResMut<TerrainTextures>,
+    mut textures: ResMut<Assets<Texture>>,
+    mut materials: ResMut<Assets<TerrainMaterial>>
+) {
+    let image = match &terrain_textures.image {
+        Some(image) => image.clone(),
+        None => return
+    };
+
+    let texture = match textures.get_mut(&image) {
+        Some(texture) => texture,
+        None => return
+    };
+
+    terrain_textures.image = None;
+
+    let Extent3d { width, height, .. } = texture.size;
+    let layers = MATERIAL_COUNT as u32;
+
+    if height != width * layers {
+        println!(
+            "Terrain textures {} should be {} square layers stacked, but they're {}x{}",
+            TERRAIN_TEXTURES_PATH, layers, width, height
+        );
+        return;
+    }
+
+    // A 2D texture deeper than one layer is bound as an array, and each layer tiles across the terrain
+    texture.size = Extent3d::new(width, width, layers);
+    texture.sampler.address_mode_u = AddressMode::Repeat;
+    texture.sampler.address_mode_v = AddressMode::Repeat;
+
+    let ids: Vec<HandleId> = materials.ids().collect();
+    for id in ids {
+        if let Some(material) = materials.get_mut(id) {
+            material.textures = Some(image.clone());
+        }
+    }
+
+    terrain_textures.array = Some(image);
+}
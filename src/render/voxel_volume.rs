@@ -6,13 +6,16 @@ use bevy::{
     },
     reflect::TypeUuid,
 };
+use crate::models::*;
 
+/// A sparse voxel octree uploaded for raymarching. `nodes` is the octree flattened by
+/// `SparseVoxelOctree::flatten` and `size` is its edge length on every axis.
 #[derive(Debug, RenderResources, RenderResource, TypeUuid)]
 #[uuid = "9c15ff5b-12ae-4f62-a489-c3a71ebda138"]
 pub struct VoxelVolume {
     pub palette: Vec<Vec4>,
     #[render_resources(buffer)]
-    pub data: Vec<VoxelData>,
+    pub nodes: Vec<VoxelNode>,
     pub size: Vec3,
 }
 
@@ -22,23 +25,29 @@ impl Default for VoxelVolume {
     fn default() -> VoxelVolume {
         VoxelVolume {
             palette: Vec::new(),
-            data: Vec::new(),
+            nodes: vec![VoxelNode::default()],
             size: Vec3::zero()
         }
     }
 }
 
-#[derive(Debug)]
-pub struct VoxelData {
-    pub material: u32,
-}
-
-unsafe impl Byteable for VoxelData {}
+impl VoxelVolume {
+    pub fn from_octree(octree: &SparseVoxelOctree, palette: Vec<Vec4>) -> VoxelVolume {
+        let size = octree.size() as f32;
 
-impl Default for VoxelData {
-    fn default() -> Self {
-        Self {
-            material: 0,
+        VoxelVolume {
+            palette,
+            nodes: octree.flatten().into_iter().map(|data| VoxelNode { data }).collect(),
+            size: Vec3::new(size, size, size)
         }
     }
-}
\ No newline at end of file
+}
+
+/// One flattened octree node: a material for leaves, or `OCTREE_BRANCH_FLAG` plus the index of
+/// the first child for branches
+#[derive(Debug, Default, Clone, Copy)]
+pub struct VoxelNode {
+    pub data: u32,
+}
+
+unsafe impl Byteable for VoxelNode {}
\ No newline at end of file
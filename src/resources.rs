@@ -1,13 +1,35 @@
+mod chunk_cache;
+mod chunk_streaming_settings;
 mod clients;
+mod input_map;
 mod network_event_listener_state;
+mod pending_voxel_writes;
+mod replay_player;
+mod replay_recorder;
 mod simulation_time;
+mod terrain_graph_watcher;
+mod voxel_edit_log;
+mod voxel_edit_prediction;
+mod voxel_edit_settings;
+mod voxel_world;
 mod world_generator;
 mod window_resize_event_listener_state;
 
 pub use self::{
+    chunk_cache::*,
+    chunk_streaming_settings::*,
     clients::*,
+    input_map::*,
     network_event_listener_state::*,
+    pending_voxel_writes::*,
+    replay_player::*,
+    replay_recorder::*,
     simulation_time::*,
+    terrain_graph_watcher::*,
+    voxel_edit_log::*,
+    voxel_edit_prediction::*,
+    voxel_edit_settings::*,
+    voxel_world::*,
     world_generator::*,
     window_resize_event_listener_state::*
 };
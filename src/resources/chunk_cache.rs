@@ -0,0 +1,112 @@
+use std::collections::{HashMap, HashSet};
+use crate::models::*;
+
+/// Client only: what the server has told us about the chunks around our player. Unedited chunks
+/// are generated from the seed as before, while edited ones load from the voxels the server sent.
+/// Those are kept after their chunk unloads, so coming back to a chunk nobody has changed since
+/// costs nothing. Only edited chunks are kept, which are few.
+#[derive(Default)]
+pub struct ChunkCache {
+    /// The revision the server says each chunk is at
+    revisions: HashMap<ChunkPos, u64>,
+    /// The voxels of edited chunks, at the revision they were sent at
+    chunks: HashMap<ChunkPos, (u64, Chunk)>,
+    /// Edited chunks on their way from the server
+    assemblies: HashMap<ChunkPos, ChunkAssembly>,
+    /// Edited chunks asked for, with the revision they were at when we asked
+    requested: HashMap<ChunkPos, u64>,
+    /// Edited chunks to ask the server for
+    wanted: HashSet<ChunkPos>,
+    /// Chunks whose revision or cached voxels have changed
+    changed: HashSet<ChunkPos>
+}
+
+impl ChunkCache {
+    /// The revision the server says a chunk is at, or nothing if it hasn't said yet
+    pub fn revision(&self, position: ChunkPos) -> Option<u64> {
+        self.revisions.get(&position).cloned()
+    }
+
+    pub fn set_revision(&mut self, position: ChunkPos, revision: u64) {
+        if self.revisions.insert(position, revision) != Some(revision) {
+            self.changed.insert(position);
+        }
+    }
+
+    /// The voxels of an edited chunk at its current revision, if we have them
+    pub fn chunk(&self, position: ChunkPos) -> Option<&Chunk> {
+        let revision = self.revision(position)?;
+
+        match self.chunks.get(&position) {
+            Some((cached, chunk)) if *cached == revision => Some(chunk),
+            _ => None
+        }
+    }
+
+    /// Asks for an edited chunk's voxels, unless they're already on their way at its current
+    /// revision
+    pub fn want(&mut self, position: ChunkPos) {
+        if self.revision(position) != self.requested.get(&position).cloned() {
+            self.wanted.insert(position);
+        }
+    }
+
+    /// The chunks to ask the server for, which are counted as asked for from then on
+    pub fn take_wanted(&mut self) -> Vec<ChunkPos> {
+        let wanted: Vec<ChunkPos> = self.wanted.drain().collect();
+
+        for position in wanted.iter() {
+            if let Some(revision) = self.revisions.get(position) {
+                self.requested.insert(*position, *revision);
+            }
+        }
+
+        wanted
+    }
+
+    /// Adds a fragment of an edited chunk, caching the chunk once all of it has arrived. It's kept
+    /// whatever revision it's at, as an edit on its way may move our revision on to it.
+    pub fn receive(&mut self, fragment: ChunkFragment) {
+        let position = fragment.position;
+
+        let assembly = self.assemblies.entry(position)
+            .or_insert_with(|| ChunkAssembly::new(fragment.revision, fragment.count));
+
+        // A newer copy replaces one we were part way through
+        if !assembly.accepts(&fragment) {
+            *assembly = ChunkAssembly::new(fragment.revision, fragment.count);
+        }
+
+        let revision = assembly.revision();
+
+        match assembly.add(fragment) {
+            Some(Ok(chunk)) => {
+                self.assemblies.remove(&position);
+                self.requested.remove(&position);
+                self.chunks.insert(position, (revision, chunk));
+                self.changed.insert(position);
+            },
+            Some(Err(error)) => {
+                println!("Failed to read the voxels of chunk {:?}: {}", position, error);
+                self.assemblies.remove(&position);
+                self.requested.remove(&position);
+            },
+            None => {}
+        }
+    }
+
+    /// The chunks whose revision or cached voxels have changed since this was last called
+    pub fn take_changed(&mut self) -> HashSet<ChunkPos> {
+        std::mem::take(&mut self.changed)
+    }
+
+    /// Forgets everything, as it belongs to another world
+    pub fn clear(&mut self) {
+        self.revisions.clear();
+        self.chunks.clear();
+        self.assemblies.clear();
+        self.requested.clear();
+        self.wanted.clear();
+        self.changed.clear();
+    }
+}
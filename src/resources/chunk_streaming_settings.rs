@@ -0,0 +1,88 @@
+use std::time::Duration;
+use crate::models::*;
+
+/// Controls how many chunks are kept loaded around each local player and how much work loading
+/// them may do per frame
+#[derive(Clone, Debug)]
+pub struct ChunkStreamingSettings {
+    /// How far chunks load around a player horizontally, in chunks
+    pub view_distance: i32,
+    /// How far chunks load above and below a player, in chunks
+    pub vertical_view_distance: i32,
+    /// How far from a player each coarser level of detail starts, in chunks. Chunks nearer than
+    /// the first are generated in full and meshed from every voxel. Past it they're sampled every
+    /// 2, then 4, then 8 voxels straight from the world generator, or from the voxels the server
+    /// sent where someone has edited them.
+    pub lod_distances: [i32; 3],
+    /// How much further than the view distance a chunk must be before it unloads, so chunks at
+    /// the edge don't load and unload repeatedly as a player moves back and forth
+    pub unload_margin: i32,
+    /// The most generated chunks added to the world in a single frame
+    pub chunks_per_frame: usize,
+    /// The most finished meshes swapped in for the chunks they draw in a single frame
+    pub meshes_per_frame: usize,
+    /// The most terrain colliders swapped in or taken out in a single frame. Each one changes the
+    /// physics world, so they're spread over frames rather than all landing at once.
+    pub colliders_per_frame: usize,
+    /// Adding chunks and meshes stops for the frame once it has taken this long, even if more are
+    /// ready
+    pub frame_budget: Duration,
+    /// The most chunks being generated and meshed in the background at once
+    pub max_tasks: usize
+}
+
+impl Default for ChunkStreamingSettings {
+    fn default() -> Self {
+        Self {
+            view_distance: 10,
+            vertical_view_distance: 3,
+            lod_distances: [3, 5, 8],
+            unload_margin: 2,
+            chunks_per_frame: 8,
+            meshes_per_frame: 8,
+            colliders_per_frame: 2,
+            frame_budget: Duration::from_millis(4),
+            max_tasks: 32
+        }
+    }
+}
+
+impl ChunkStreamingSettings {
+    /// Whether a chunk is close enough to `center` to load
+    pub fn in_view(&self, center: ChunkPos, position: ChunkPos) -> bool {
+        self.within(center, position, 0)
+    }
+
+    /// Whether a loaded chunk is close enough to `center` to stay loaded
+    pub fn in_retention(&self, center: ChunkPos, position: ChunkPos) -> bool {
+        self.within(center, position, self.unload_margin)
+    }
+
+    /// The level of detail a chunk is drawn at for the nearest player, 0 being full detail. Each
+    /// level doubles the distance between the voxels sampled.
+    pub fn lod(&self, centers: &[ChunkPos], position: ChunkPos) -> u8 {
+        let distance = centers.iter()
+            .map(|center| center.chebyshev_distance(position))
+            .min()
+            .unwrap_or(i32::MAX);
+
+        self.lod_distances.iter().filter(|start| distance >= **start).count() as u8
+    }
+
+    /// Whether a chunk's voxels are needed near `center`, which they are for the chunks drawn at
+    /// full detail and the ring around them that their meshes reach into
+    pub fn needs_voxels(&self, center: ChunkPos, position: ChunkPos) -> bool {
+        self.in_view(center, position) && center.chebyshev_distance(position) <= self.lod_distances[0]
+    }
+
+    /// Whether a chunk's voxels are close enough to `center` to stay loaded
+    pub fn retains_voxels(&self, center: ChunkPos, position: ChunkPos) -> bool {
+        self.in_retention(center, position) && center.chebyshev_distance(position) <= self.lod_distances[0] + self.unload_margin
+    }
+
+    fn within(&self, center: ChunkPos, position: ChunkPos, margin: i32) -> bool {
+        (position.0 - center.0).abs() <= self.view_distance + margin
+            && (position.2 - center.2).abs() <= self.view_distance + margin
+            && (position.1 - center.1).abs() <= self.vertical_view_distance + margin
+    }
+}
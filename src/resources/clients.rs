@@ -24,6 +24,14 @@ impl Clients {
         self.connections.get(&connection)
     }
 
+    pub fn get(&self, client_id: u128) -> Option<&Client> {
+        self.clients.get(&client_id)
+    }
+
+    pub fn get_mut(&mut self, client_id: u128) -> Option<&mut Client> {
+        self.clients.get_mut(&client_id)
+    }
+
     pub fn add(&mut self, connection: Connection, client: Client) {
         self.connections.insert(connection, client.id());
         self.clients.insert(client.id(), client);
@@ -0,0 +1,99 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    path::Path
+};
+use bevy::input::{
+    gamepad::{GamepadAxisType, GamepadButtonType},
+    keyboard::KeyCode,
+    mouse::MouseButton
+};
+use serde::{Serialize, Deserialize};
+use crate::models::*;
+
+/// Maps each `InputAction` to the inputs bound to it
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InputMap {
+    bindings: HashMap<InputAction, Vec<InputBinding>>
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        let mut input_map = InputMap {
+            bindings: HashMap::new()
+        };
+
+        input_map.bind(InputAction::MoveForward, InputBinding::Key(KeyCode::W));
+        input_map.bind(InputAction::MoveForward, InputBinding::GamepadAxis(GamepadAxisType::LeftStickY, 1.0));
+        input_map.bind(InputAction::MoveBackward, InputBinding::Key(KeyCode::S));
+        input_map.bind(InputAction::MoveBackward, InputBinding::GamepadAxis(GamepadAxisType::LeftStickY, -1.0));
+        input_map.bind(InputAction::MoveLeft, InputBinding::Key(KeyCode::A));
+        input_map.bind(InputAction::MoveLeft, InputBinding::GamepadAxis(GamepadAxisType::LeftStickX, -1.0));
+        input_map.bind(InputAction::MoveRight, InputBinding::Key(KeyCode::D));
+        input_map.bind(InputAction::MoveRight, InputBinding::GamepadAxis(GamepadAxisType::LeftStickX, 1.0));
+        input_map.bind(InputAction::Jump, InputBinding::Key(KeyCode::Space));
+        input_map.bind(InputAction::Jump, InputBinding::GamepadButton(GamepadButtonType::South));
+        input_map.bind(InputAction::Dig, InputBinding::MouseButton(MouseButton::Left));
+        input_map.bind(InputAction::Dig, InputBinding::GamepadButton(GamepadButtonType::RightTrigger2));
+        input_map.bind(InputAction::Build, InputBinding::MouseButton(MouseButton::Right));
+        input_map.bind(InputAction::Build, InputBinding::GamepadButton(GamepadButtonType::LeftTrigger2));
+        input_map.bind(InputAction::LookYaw, InputBinding::MouseMotionX(-0.002));
+        input_map.bind(InputAction::LookYaw, InputBinding::GamepadAxis(GamepadAxisType::RightStickX, -0.04));
+        input_map.bind(InputAction::LookPitch, InputBinding::MouseMotionY(-0.002));
+        input_map.bind(InputAction::LookPitch, InputBinding::GamepadAxis(GamepadAxisType::RightStickY, 0.04));
+
+        input_map
+    }
+}
+
+impl InputMap {
+    /// Reads bindings from a RON file
+    pub fn load(path: impl AsRef<Path>) -> ron::Result<InputMap> {
+        let file = File::open(path).map_err(ron::Error::from)?;
+        ron::de::from_reader(file)
+    }
+
+    /// Reads bindings from a RON file, falling back to the default bindings if the file is
+    /// missing or invalid
+    pub fn load_or_default(path: impl AsRef<Path>) -> InputMap {
+        if !path.as_ref().exists() {
+            return InputMap::default();
+        }
+
+        match InputMap::load(&path) {
+            Ok(input_map) => input_map,
+            Err(error) => {
+                println!("Failed to load input bindings from {:?}, using defaults: {}", path.as_ref(), error);
+                InputMap::default()
+            }
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> ron::Result<()> {
+        let serialized = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(path, serialized).map_err(ron::Error::from)
+    }
+
+    pub fn bindings(&self, action: InputAction) -> &[InputBinding] {
+        self.bindings.get(&action).map(|bindings| &bindings[..]).unwrap_or(&[])
+    }
+
+    pub fn bind(&mut self, action: InputAction, binding: InputBinding) {
+        let bindings = self.bindings.entry(action).or_insert_with(Vec::new);
+
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn unbind(&mut self, action: InputAction, binding: InputBinding) {
+        if let Some(bindings) = self.bindings.get_mut(&action) {
+            bindings.retain(|bound| *bound != binding);
+        }
+    }
+
+    /// Replaces every binding of an action with a single new one
+    pub fn rebind(&mut self, action: InputAction, binding: InputBinding) {
+        self.bindings.insert(action, vec![binding]);
+    }
+}
@@ -8,5 +8,9 @@ pub struct NetworkEventListenerState {
     pub network_events: EventReader<NetworkEvent>,
     pub command_frame_events: EventReader<CommandFrameEvent>,
     pub state_frame_events: EventReader<StateFrameEvent>,
-    pub entity_spawn_events: EventReader<EntitySpawnEvent>
+    pub entity_spawn_events: EventReader<EntitySpawnEvent>,
+    pub voxel_edit_request_events: EventReader<VoxelEditRequestEvent>,
+    pub voxel_edit_events: EventReader<VoxelEditEvent>,
+    pub voxel_edit_rejected_events: EventReader<VoxelEditRejectedEvent>,
+    pub chunk_transfer_events: EventReader<ChunkTransferEvent>
 }
@@ -0,0 +1,201 @@
+use std::collections::{HashMap, HashSet};
+use crate::models::*;
+
+/// Voxels that features placed, kept by the chunk they land in, including a chunk's writes into
+/// itself. A chunk picks up every write into it, however many of the chunks around it were
+/// decorated first.
+///
+/// Where features overlap, the voxel they both write is resolved the same way whichever order the
+/// chunks were decorated in. Writes are applied to the generated voxels by priority, lowest source
+/// chunk first, so a write arriving late resolves its chunk again from scratch.
+#[derive(Default)]
+pub struct PendingVoxelWrites {
+    /// The writes into each chunk, by the chunk whose features made them
+    writes: HashMap<ChunkPos, HashMap<ChunkPos, Vec<VoxelWrite>>>,
+    /// The generated voxels under the writes already resolved into each chunk, to start from when
+    /// resolving it again
+    generated: HashMap<ChunkPos, HashMap<VoxelPos, Voxel>>,
+    /// The chunks whose writes have been added and not removed since
+    decorated: HashSet<ChunkPos>
+}
+
+impl PendingVoxelWrites {
+    /// Records a decorated chunk's writes, returning the chunks they land in
+    pub fn add(&mut self, source: ChunkPos, writes: Vec<VoxelWrite>) -> Vec<ChunkPos> {
+        let mut by_target: HashMap<ChunkPos, Vec<VoxelWrite>> = HashMap::new();
+
+        for write in writes {
+            by_target.entry(write.position.chunk()).or_default().push(write);
+        }
+
+        let targets: Vec<ChunkPos> = by_target.keys().cloned().collect();
+        self.decorated.insert(source);
+
+        for (target, writes) in by_target {
+            self.writes.entry(target).or_default().insert(source, writes);
+        }
+
+        targets
+    }
+
+    /// Every write into a chunk in priority order: by source chunk, lowest first, then in the
+    /// order the source made them
+    pub fn writes_into(&self, target: ChunkPos) -> Vec<VoxelWrite> {
+        let sources = match self.writes.get(&target) {
+            Some(sources) => sources,
+            None => return vec![]
+        };
+
+        let mut positions: Vec<&ChunkPos> = sources.keys().collect();
+        positions.sort();
+
+        positions.into_iter()
+            .flat_map(|source| sources[source].iter().cloned())
+            .collect()
+    }
+
+    /// Applies every write into a chunk in priority order, first putting back the generated voxels
+    /// under writes resolved into it before. Returns the voxels that changed.
+    ///
+    /// The chunk must be as it was generated, or as the last call left it.
+    pub fn resolve(&mut self, target: ChunkPos, chunk: &mut Chunk) -> Vec<VoxelPos> {
+        let writes = self.writes_into(target);
+        let generated = self.generated.entry(target).or_default();
+
+        // Voxels no write has touched yet are still as generated
+        for write in writes.iter() {
+            generated.entry(write.position).or_insert_with(|| chunk.get(write.position.local()));
+        }
+
+        let before: Vec<(VoxelPos, Voxel)> = generated.iter()
+            .map(|(position, voxel)| {
+                (*position, chunk.set(position.local(), *voxel))
+            })
+            .collect();
+
+        for write in writes.iter() {
+            write.apply(chunk);
+        }
+
+        before.into_iter()
+            .filter(|(position, voxel)| chunk.get(position.local()) != *voxel)
+            .map(|(position, _)| position)
+            .collect()
+    }
+
+    /// Drops every write into a chunk whose voxels came with the features already in them, like
+    /// an edited chunk the server sent
+    pub fn discard_writes_into(&mut self, target: ChunkPos) {
+        self.writes.remove(&target);
+        self.generated.remove(&target);
+    }
+
+    /// Forgets a chunk that unloaded. Its writes into chunks still resolved are kept, so resolving
+    /// those again doesn't take its features away, and the rest are made again if it's decorated
+    /// again. Writes into it are kept for when it loads again, unless their source unloaded too.
+    pub fn remove_chunk(&mut self, position: ChunkPos) {
+        self.decorated.remove(&position);
+        self.generated.remove(&position);
+
+        let generated = &self.generated;
+        for (target, sources) in self.writes.iter_mut() {
+            if !generated.contains_key(target) {
+                sources.remove(&position);
+            }
+        }
+
+        let decorated = &self.decorated;
+        if let Some(sources) = self.writes.get_mut(&position) {
+            sources.retain(|source, _| decorated.contains(source));
+        }
+
+        self.writes.retain(|_, sources| !sources.is_empty());
+    }
+
+    pub fn clear(&mut self) {
+        self.writes.clear();
+        self.generated.clear();
+        self.decorated.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(position: VoxelPos, material: u8) -> VoxelWrite {
+        VoxelWrite {
+            position,
+            voxel: Voxel::solid(material),
+            rule: WriteRule::IntoAir
+        }
+    }
+
+    fn voxels(chunk: &Chunk) -> Vec<Voxel> {
+        chunk.iter().map(|(_, voxel)| voxel).collect()
+    }
+
+    /// Resolves the writes of each source into the chunk at the origin as each one arrives
+    fn resolve_in_order(sources: &[(ChunkPos, Vec<VoxelWrite>)]) -> Chunk {
+        let mut pending = PendingVoxelWrites::default();
+        let mut chunk = Chunk::default();
+
+        for (source, writes) in sources.iter() {
+            pending.add(*source, writes.clone());
+            pending.resolve(ChunkPos(0, 0, 0), &mut chunk);
+        }
+
+        chunk
+    }
+
+    #[test]
+    fn overlapping_writes_resolve_the_same_in_any_order() {
+        let shared = VoxelPos(3, 4, 5);
+        let own = (ChunkPos(0, 0, 0), vec![write(shared, WOOD), write(VoxelPos(3, 5, 5), WOOD)]);
+        let below = (ChunkPos(0, -1, 0), vec![write(shared, LEAVES), write(VoxelPos(2, 4, 5), LEAVES)]);
+        let beside = (ChunkPos(1, 0, 0), vec![write(shared, STONE)]);
+
+        let expected = resolve_in_order(&[below.clone(), own.clone(), beside.clone()]);
+        assert_eq!(expected.get(shared.local()), Voxel::solid(LEAVES));
+
+        for order in [[&own, &beside, &below], [&beside, &own, &below], [&own, &below, &beside]].iter() {
+            let sources: Vec<(ChunkPos, Vec<VoxelWrite>)> = order.iter().map(|source| (*source).clone()).collect();
+            assert!(voxels(&resolve_in_order(&sources)) == voxels(&expected));
+        }
+    }
+
+    #[test]
+    fn unloading_a_source_keeps_its_writes_into_resolved_chunks() {
+        let mut pending = PendingVoxelWrites::default();
+        let mut chunk = Chunk::default();
+
+        pending.add(ChunkPos(0, 0, 0), vec![write(VoxelPos(0, 0, 0), WOOD)]);
+        pending.add(ChunkPos(1, 0, 0), vec![write(VoxelPos(15, 0, 0), LEAVES), write(VoxelPos(16, 0, 0), LEAVES)]);
+        pending.resolve(ChunkPos(0, 0, 0), &mut chunk);
+        pending.remove_chunk(ChunkPos(1, 0, 0));
+
+        // A late write resolves the chunk again without losing the unloaded chunk's leaves
+        pending.add(ChunkPos(-1, 0, 0), vec![write(VoxelPos(0, 1, 0), WOOD)]);
+        pending.resolve(ChunkPos(0, 0, 0), &mut chunk);
+
+        assert_eq!(chunk.get(LocalVoxelPos(15, 0, 0)), Voxel::solid(LEAVES));
+        assert_eq!(chunk.get(LocalVoxelPos(0, 1, 0)), Voxel::solid(WOOD));
+        assert!(pending.writes_into(ChunkPos(1, 0, 0)).is_empty());
+    }
+
+    #[test]
+    fn discarding_writes_into_a_chunk_leaves_its_own_writes_elsewhere() {
+        let mut pending = PendingVoxelWrites::default();
+
+        pending.add(ChunkPos(0, 0, 0), vec![write(VoxelPos(0, 0, 0), WOOD), write(VoxelPos(16, 0, 0), LEAVES)]);
+        pending.add(ChunkPos(1, 0, 0), vec![write(VoxelPos(15, 0, 0), LEAVES)]);
+        pending.discard_writes_into(ChunkPos(0, 0, 0));
+
+        assert!(pending.writes_into(ChunkPos(0, 0, 0)).is_empty());
+        assert_eq!(pending.writes_into(ChunkPos(1, 0, 0)).len(), 1);
+
+        // Resolving it again leaves it as it came
+        let mut chunk = Chunk::default();
+        assert!(pending.resolve(ChunkPos(0, 0, 0), &mut chunk).is_empty());
+    }
+}
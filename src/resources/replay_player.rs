@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use bevy::ecs::Entity;
+use crate::models::*;
+
+/// Steps through a recorded replay one simulation frame at a time
+pub struct ReplayPlayer {
+    replay: Replay,
+    frame: u32,
+    cursor: usize,
+    speed: f32,
+    paused: bool,
+    /// Recorded entity ids mapped to the entities spawned for them during playback
+    entities: HashMap<u32, Entity>,
+    pending_authorizations: Vec<ReplayEvent>,
+    pending_snapshots: Vec<StateFrame>,
+    pending_world_info: Option<WorldInfo>,
+    pending_edits: Vec<Brush>
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> ReplayPlayer {
+        let frame = replay.first_frame();
+
+        ReplayPlayer {
+            replay,
+            frame,
+            cursor: 0,
+            speed: 1.0,
+            paused: false,
+            entities: HashMap::new(),
+            pending_authorizations: Vec::new(),
+            pending_snapshots: Vec::new(),
+            pending_world_info: None,
+            pending_edits: Vec::new()
+        }
+    }
+
+    pub fn header(&self) -> &ReplayHeader {
+        self.replay.header()
+    }
+
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Playback speed as a multiple of the recorded tick rate. A speed of zero pauses playback, as
+    /// the simulation can't tick any slower than once a second.
+    pub fn set_speed(&mut self, speed: f32) {
+        if speed > 0.0 {
+            self.speed = speed;
+        } else {
+            self.paused = true;
+        }
+    }
+
+    /// The tick rate the simulation should run at to honour the playback speed
+    pub fn tick_rate(&self) -> u16 {
+        ((self.replay.header().tick_rate as f32 * self.speed).round() as u16).max(1)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.cursor >= self.replay.events().len()
+    }
+
+    /// Moves playback to `frame`. The clients authorized before that frame are queued to be
+    /// spawned, along with the most recent authoritative state of every entity, the world played
+    /// at the time and every edit made to it, so the simulation can be restored before commands
+    /// resume.
+    pub fn seek(&mut self, frame: u32) {
+        let cursor = self.replay.index_of_frame(frame);
+        let mut authorizations = Vec::new();
+        let mut snapshots = HashMap::<(u32, u8), StateFrame>::new();
+        let mut world_info = None;
+        let mut edits = Vec::new();
+
+        for event in self.replay.events()[..cursor].iter() {
+            match event {
+                ReplayEvent::ClientAuthorized { .. } => authorizations.push(event.clone()),
+                ReplayEvent::StateFrame(state_frame) => {
+                    snapshots.insert((state_frame.entity_id, state_frame.component_type_id), state_frame.clone());
+                },
+                ReplayEvent::WorldInfo { world_info: info, .. } => world_info = Some(info.clone()),
+                ReplayEvent::VoxelEdit { brush, .. } => edits.push(*brush),
+                ReplayEvent::CommandFrame { .. } => {}
+            }
+        }
+
+        self.frame = frame;
+        self.cursor = cursor;
+        self.pending_authorizations = authorizations;
+        self.pending_snapshots = snapshots.into_iter().map(|(_, state_frame)| state_frame).collect();
+        self.pending_world_info = world_info;
+        self.pending_edits = edits;
+    }
+
+    /// The world being played at the frame the last `seek` moved to
+    pub fn take_world_info(&mut self) -> Option<WorldInfo> {
+        self.pending_world_info.take()
+    }
+
+    /// Edits made before the frame the last `seek` moved to, in the order they were accepted
+    pub fn take_edits(&mut self) -> Vec<Brush> {
+        std::mem::take(&mut self.pending_edits)
+    }
+
+    /// Authorizations skipped over by the last `seek`, whose players still need to be spawned
+    pub fn take_authorizations(&mut self) -> Vec<ReplayEvent> {
+        std::mem::take(&mut self.pending_authorizations)
+    }
+
+    /// Whether state queued by the last `seek` is still waiting to be applied
+    pub fn is_restoring(&self) -> bool {
+        !self.pending_authorizations.is_empty() || !self.pending_snapshots.is_empty()
+    }
+
+    /// State frames of one component type queued by the last `seek`. Any that can't be applied
+    /// yet should be handed back with `defer_snapshot`.
+    pub fn take_snapshots(&mut self, component_type_id: u8) -> Vec<StateFrame> {
+        let (taken, remaining) = std::mem::take(&mut self.pending_snapshots)
+            .into_iter()
+            .partition(|state_frame| state_frame.component_type_id == component_type_id);
+
+        self.pending_snapshots = remaining;
+        taken
+    }
+
+    pub fn defer_snapshot(&mut self, state_frame: StateFrame) {
+        self.pending_snapshots.push(state_frame);
+    }
+
+    /// Remembers which entity was spawned during playback for a recorded entity id
+    pub fn map_entity(&mut self, recorded_id: u32, entity: Entity) {
+        self.entities.insert(recorded_id, entity);
+    }
+
+    /// The entity spawned during playback for a recorded entity id
+    pub fn entity(&self, recorded_id: u32) -> Option<Entity> {
+        self.entities.get(&recorded_id).cloned()
+    }
+
+    /// Returns the events recorded for the current frame and moves on to the next one
+    pub fn advance(&mut self) -> &[ReplayEvent] {
+        let start = self.cursor;
+        let end = self.replay.index_of_frame(self.frame.saturating_add(1));
+
+        self.cursor = end;
+        self.frame += 1;
+
+        &self.replay.events()[start..end]
+    }
+}
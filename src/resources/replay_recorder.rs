@@ -0,0 +1,47 @@
+use std::{
+    fs::File,
+    path::Path
+};
+use crate::models::*;
+
+/// Streams network traffic to a replay file while enabled
+#[derive(Default)]
+pub struct ReplayRecorder {
+    writer: Option<ReplayWriter<File>>
+}
+
+impl ReplayRecorder {
+    pub fn create(path: impl AsRef<Path>, world_seed: u64, tick_rate: u16) -> bincode::Result<ReplayRecorder> {
+        let header = ReplayHeader {
+            version: REPLAY_VERSION,
+            world_seed,
+            tick_rate
+        };
+
+        Ok(ReplayRecorder {
+            writer: Some(ReplayWriter::new(File::create(path)?, header)?)
+        })
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.writer.is_some()
+    }
+
+    pub fn record(&mut self, event: ReplayEvent) {
+        if let Some(writer) = self.writer.as_mut() {
+            if let Err(error) = writer.write_event(&event) {
+                println!("Failed to write replay event, recording stopped: {}", error);
+                self.writer = None;
+            }
+        }
+    }
+
+    pub fn flush(&mut self) {
+        if let Some(writer) = self.writer.as_mut() {
+            if let Err(error) = writer.flush() {
+                println!("Failed to flush replay, recording stopped: {}", error);
+                self.writer = None;
+            }
+        }
+    }
+}
@@ -3,6 +3,7 @@ use std::time::{Duration, Instant};
 
 pub struct SimulationTime {
     frame: u32,
+    tick_rate: u16,
     last_execution: Instant,
     initial_frame_duration: f32,
     current_frame_duration: f32
@@ -14,6 +15,7 @@ impl SimulationTime {
 
         SimulationTime {
             frame: 0,
+            tick_rate: speed,
             last_execution: Instant::now(),
             initial_frame_duration: frame_duration,
             current_frame_duration: frame_duration
@@ -28,6 +30,17 @@ impl SimulationTime {
         self.frame
     }
 
+    /// The number of frames per second the simulation was created with
+    pub fn tick_rate(&self) -> u16 {
+        self.tick_rate
+    }
+
+    /// The length of a frame at the original tick rate, regardless of any speed adjustment, so
+    /// simulation steps stay identical on every peer
+    pub fn fixed_delta_seconds(&self) -> f32 {
+        1.0 / self.tick_rate as f32
+    }
+
     pub fn frame_duration(&self) -> f32 {
         self.current_frame_duration
     }
@@ -0,0 +1,56 @@
+use std::path::PathBuf;
+use std::time::SystemTime;
+use bevy::core::Timer;
+
+/// Where the terrain noise graph is read from
+pub const TERRAIN_GRAPH_PATH: &str = "assets/terrain/default.ron";
+
+/// Where the cave settings used with the terrain graph are read from
+pub const CAVE_SETTINGS_PATH: &str = "assets/terrain/caves.ron";
+
+/// Watches the terrain graph and cave settings files so changes to them can be seen in game
+/// without restarting
+pub struct TerrainGraphWatcher {
+    pub path: PathBuf,
+    pub caves_path: PathBuf,
+    /// When each file was last changed, as of the last check
+    pub modified: (Option<SystemTime>, Option<SystemTime>),
+    /// How often the file is checked
+    pub timer: Timer
+}
+
+impl Default for TerrainGraphWatcher {
+    fn default() -> Self {
+        TerrainGraphWatcher::new(TERRAIN_GRAPH_PATH, CAVE_SETTINGS_PATH)
+    }
+}
+
+impl TerrainGraphWatcher {
+    pub fn new(path: impl Into<PathBuf>, caves_path: impl Into<PathBuf>) -> TerrainGraphWatcher {
+        let (path, caves_path) = (path.into(), caves_path.into());
+        let modified = (TerrainGraphWatcher::read_modified(&path), TerrainGraphWatcher::read_modified(&caves_path));
+
+        TerrainGraphWatcher {
+            path,
+            caves_path,
+            modified,
+            timer: Timer::from_seconds(1.0, true)
+        }
+    }
+
+    /// Whether either file has changed since the last time this returned true
+    pub fn poll_changed(&mut self) -> bool {
+        let modified = (TerrainGraphWatcher::read_modified(&self.path), TerrainGraphWatcher::read_modified(&self.caves_path));
+
+        if modified.0.is_none() || modified == self.modified {
+            return false;
+        }
+
+        self.modified = modified;
+        true
+    }
+
+    fn read_modified(path: &PathBuf) -> Option<SystemTime> {
+        std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+    }
+}
@@ -0,0 +1,218 @@
+use std::collections::{BTreeMap, HashMap};
+use crate::models::*;
+
+/// Server only: every edit the server has accepted that still matters, with the revision each
+/// chunk is at. Edits are replayed onto chunks generated after them, so each chunk keeps the edits
+/// that reached it in order. An edit is dropped from a chunk once later edits clear every voxel
+/// it changed there, and forgotten once it's been dropped from every chunk it reached.
+#[derive(Default)]
+pub struct VoxelEditLog {
+    /// The edits still kept by a chunk, by sequence number, with how many chunks keep each
+    edits: BTreeMap<u64, (VoxelEdit, usize)>,
+    /// The sequence numbers of the edits each chunk keeps, in order
+    chunks: HashMap<ChunkPos, Vec<u64>>,
+    revisions: HashMap<ChunkPos, u64>,
+    last_sequence: u64
+}
+
+impl VoxelEditLog {
+    /// Records an accepted brush, giving it the next sequence number and moving every chunk it
+    /// reaches on to its next revision
+    pub fn accept(&mut self, brush: Brush) -> VoxelEdit {
+        self.last_sequence += 1;
+        let sequence = self.last_sequence;
+
+        let revisions: Vec<(ChunkPos, u64)> = brush.chunks()
+            .into_iter()
+            .map(|chunk| {
+                let revision = self.revisions.entry(chunk).or_insert(UNEDITED_REVISION);
+                *revision = next_revision(*revision, sequence, &brush);
+                (chunk, *revision)
+            })
+            .collect();
+
+        let edit = VoxelEdit {
+            sequence,
+            brush,
+            revisions,
+            request: None
+        };
+
+        self.edits.insert(sequence, (edit.clone(), edit.revisions.len()));
+
+        for (chunk, _) in edit.revisions.iter() {
+            self.chunks.entry(*chunk).or_default().push(sequence);
+
+            if brush.mode == BrushMode::Remove {
+                self.compact(*chunk);
+            }
+        }
+
+        edit
+    }
+
+    /// The revision a chunk is at, which is `UNEDITED_REVISION` if no edit has reached it
+    pub fn revision(&self, chunk: ChunkPos) -> u64 {
+        self.revisions.get(&chunk).cloned().unwrap_or(UNEDITED_REVISION)
+    }
+
+    /// The sequence number of the latest edit, or 0 before the first
+    pub fn last_sequence(&self) -> u64 {
+        self.last_sequence
+    }
+
+    /// The edits a chunk needs replayed onto it, in the order they were made. Applying them to the
+    /// chunk as generated leaves it as if every edit that reached it had been.
+    pub fn edits_in(&self, chunk: ChunkPos) -> impl Iterator<Item = &VoxelEdit> {
+        let edits = &self.edits;
+
+        self.chunks.get(&chunk)
+            .into_iter()
+            .flatten()
+            .map(move |sequence| &edits[sequence].0)
+    }
+
+    /// How many edits are kept
+    pub fn len(&self) -> usize {
+        self.edits.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+
+    /// Drops the edits to a chunk whose every voxel there is cleared by a later edit. Each voxel
+    /// only depends on the edits made to it, so the chunk comes out the same without them.
+    fn compact(&mut self, chunk: ChunkPos) {
+        let sequences = match self.chunks.get(&chunk) {
+            Some(sequences) => sequences.clone(),
+            None => return
+        };
+
+        let (origin, size) = (chunk.origin(), CHUNK_SIZE as i32);
+        let mut kept = vec![];
+
+        for (index, sequence) in sequences.iter().enumerate() {
+            let brush = &self.edits[sequence].0.brush;
+            let later: Vec<&Brush> = sequences[index + 1..].iter()
+                .map(|later| &self.edits[later].0.brush)
+                .filter(|later| later.mode == BrushMode::Remove)
+                .collect();
+
+            // Only the voxels of this chunk the brush can change matter here
+            let (min, max) = brush.bounds();
+            let (min, max) = (
+                VoxelPos(min.0.max(origin.0), min.1.max(origin.1), min.2.max(origin.2)),
+                VoxelPos(max.0.min(origin.0 + size - 1), max.1.min(origin.1 + size - 1), max.2.min(origin.2 + size - 1))
+            );
+
+            let covered = !later.is_empty() && (min.2..=max.2).all(|z| (min.1..=max.1).all(|y| (min.0..=max.0).all(|x| {
+                let position = VoxelPos(x, y, z);
+                !brush.reaches(position) || later.iter().any(|later| later.clears(position))
+            })));
+
+            if covered {
+                self.forget(*sequence);
+            } else {
+                kept.push(*sequence);
+            }
+        }
+
+        self.chunks.insert(chunk, kept);
+    }
+
+    /// Counts an edit as dropped from one of its chunks, forgetting it once no chunk keeps it
+    fn forget(&mut self, sequence: u64) {
+        let remaining = match self.edits.get_mut(&sequence) {
+            Some((_, remaining)) => {
+                *remaining -= 1;
+                *remaining
+            },
+            None => return
+        };
+
+        if remaining == 0 {
+            self.edits.remove(&sequence);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::VoxelWorld;
+
+    fn brush(mode: BrushMode, center: [f32; 3], radius: f32) -> Brush {
+        Brush {
+            shape: BrushShape::Sphere,
+            mode,
+            center,
+            radius,
+            strength: 1.0
+        }
+    }
+
+    /// A chunk of ground with every brush applied to it in turn
+    fn edited<'a>(brushes: impl Iterator<Item = &'a Brush>) -> Vec<Voxel> {
+        let mut world = VoxelWorld::default();
+        world.insert_chunk(ChunkPos(0, 0, 0), Chunk::from_fn(|LocalVoxelPos(_, y, _)| {
+            if y < 8 { Voxel::solid(STONE) } else { Voxel::AIR }
+        }));
+
+        for brush in brushes {
+            world.apply_brush(brush);
+        }
+
+        world.chunk(ChunkPos(0, 0, 0)).unwrap().iter().map(|(_, voxel)| voxel).collect()
+    }
+
+    #[test]
+    fn edits_are_kept_by_the_chunks_they_reach() {
+        let mut log = VoxelEditLog::default();
+        log.accept(brush(BrushMode::Add(WOOD), [8.0, 8.0, 8.0], 2.0));
+        log.accept(brush(BrushMode::Add(WOOD), [16.0, 8.0, 8.0], 2.0));
+
+        let sequences = |chunk| log.edits_in(chunk).map(|edit| edit.sequence).collect::<Vec<u64>>();
+        assert_eq!(sequences(ChunkPos(0, 0, 0)), vec![1, 2]);
+        assert_eq!(sequences(ChunkPos(1, 0, 0)), vec![2]);
+        assert!(sequences(ChunkPos(5, 0, 0)).is_empty());
+        assert_eq!(log.last_sequence(), 2);
+    }
+
+    #[test]
+    fn edits_later_ones_clear_are_dropped() {
+        let mut log = VoxelEditLog::default();
+        let brushes = vec![
+            brush(BrushMode::Add(WOOD), [8.0, 8.0, 8.0], 1.5),
+            brush(BrushMode::Remove, [8.0, 8.0, 8.0], 2.0),
+            brush(BrushMode::SmoothAdd(DIRT), [8.0, 8.0, 8.0], 2.0),
+            brush(BrushMode::Remove, [8.0, 8.0, 8.0], 4.0),
+            brush(BrushMode::Add(LEAVES), [6.0, 8.0, 8.0], 3.0)
+        ];
+
+        for brush in brushes.iter() {
+            log.accept(*brush);
+        }
+
+        // Only the big hole and what was built in it after matter
+        let kept: Vec<u64> = log.edits_in(ChunkPos(0, 0, 0)).map(|edit| edit.sequence).collect();
+        assert_eq!(kept, vec![4, 5]);
+        assert_eq!(log.len(), 2);
+
+        let replayed: Vec<Brush> = log.edits_in(ChunkPos(0, 0, 0)).map(|edit| edit.brush).collect();
+        assert!(edited(replayed.iter()) == edited(brushes.iter()));
+
+        // Revisions still count every edit
+        assert_eq!(log.last_sequence(), 5);
+        assert_ne!(log.revision(ChunkPos(0, 0, 0)), UNEDITED_REVISION);
+    }
+
+    #[test]
+    fn edits_only_partly_cleared_are_kept() {
+        let mut log = VoxelEditLog::default();
+        log.accept(brush(BrushMode::Add(WOOD), [8.0, 8.0, 8.0], 3.0));
+        log.accept(brush(BrushMode::Remove, [9.0, 8.0, 8.0], 3.0));
+
+        assert_eq!(log.edits_in(ChunkPos(0, 0, 0)).count(), 2);
+    }
+}
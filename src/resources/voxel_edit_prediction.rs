@@ -0,0 +1,86 @@
+use crate::models::*;
+use crate::resources::VoxelWorld;
+
+/// An edit applied ahead of the server's answer, with the voxels it replaced
+struct PredictedEdit {
+    id: u32,
+    brush: Brush,
+    replaced: Vec<(VoxelPos, Voxel)>
+}
+
+/// Client only: edits applied before the server answers them, so digging and building feel
+/// immediate. The server's edits always land in the server's order: predictions are undone
+/// beneath each one and made again on top, and any the server turns down are dropped.
+#[derive(Default)]
+pub struct VoxelEditPrediction {
+    next_id: u32,
+    predicted: Vec<PredictedEdit>,
+    /// The sequence number of the last edit applied from the server
+    sequence: u64
+}
+
+impl VoxelEditPrediction {
+    /// Applies a brush straight away, returning the request asking the server for it
+    pub fn predict(&mut self, world: &mut VoxelWorld, brush: Brush) -> VoxelEditRequest {
+        self.next_id = self.next_id.wrapping_add(1);
+
+        let replaced = world.apply_brush(&brush);
+        self.predicted.push(PredictedEdit {
+            id: self.next_id,
+            brush,
+            replaced
+        });
+
+        VoxelEditRequest {
+            id: self.next_id,
+            brush
+        }
+    }
+
+    /// Applies an edit the server accepted, dropping the prediction it answers. Edits already
+    /// applied are ignored, returning false.
+    pub fn apply_authoritative(&mut self, world: &mut VoxelWorld, edit: &VoxelEdit) -> bool {
+        if edit.sequence <= self.sequence {
+            return false;
+        }
+
+        self.sequence = edit.sequence;
+        self.undo_predictions(world);
+
+        if let Some(id) = edit.request {
+            self.predicted.retain(|predicted| predicted.id != id);
+        }
+
+        world.apply_brush(&edit.brush);
+        self.redo_predictions(world);
+        true
+    }
+
+    /// Rolls back a prediction the server turned down
+    pub fn reject(&mut self, world: &mut VoxelWorld, id: u32) {
+        self.undo_predictions(world);
+        self.predicted.retain(|predicted| predicted.id != id);
+        self.redo_predictions(world);
+    }
+
+    /// How many edits are still waiting on the server
+    pub fn predicted_count(&self) -> usize {
+        self.predicted.len()
+    }
+
+    /// Puts back the voxels every prediction replaced, latest first. Chunks unloaded since are
+    /// left alone.
+    fn undo_predictions(&mut self, world: &mut VoxelWorld) {
+        for predicted in self.predicted.iter().rev() {
+            for (position, voxel) in predicted.replaced.iter().rev() {
+                world.set_voxel(*position, *voxel);
+            }
+        }
+    }
+
+    fn redo_predictions(&mut self, world: &mut VoxelWorld) {
+        for predicted in self.predicted.iter_mut() {
+            predicted.replaced = world.apply_brush(&predicted.brush);
+        }
+    }
+}
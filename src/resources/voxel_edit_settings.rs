@@ -0,0 +1,93 @@
+use bevy::math::Vec3;
+use crate::components::PLAYER_HEAD_HEIGHT;
+
+/// How much further than their reach a player's edits may be, as they may have moved since the
+/// client aimed
+const REACH_TOLERANCE: f32 = 1.5;
+
+/// The limits the server holds players' voxel edits to
+#[derive(Clone, Debug)]
+pub struct VoxelEditSettings {
+    /// How far from a player's head a brush's centre may be
+    pub reach: f32,
+    /// The largest brush a player may use
+    pub max_radius: f32,
+    /// How many edits a player may make each second, on average
+    pub edits_per_second: f32,
+    /// How many edits a player may make at once after not editing for a while
+    pub edit_burst: f32
+}
+
+impl Default for VoxelEditSettings {
+    fn default() -> Self {
+        Self {
+            reach: 6.0,
+            max_radius: 4.0,
+            edits_per_second: 5.0,
+            edit_burst: 5.0
+        }
+    }
+}
+
+impl VoxelEditSettings {
+    /// Whether a player whose body is at `position` can reach a brush centred at `center`,
+    /// measured from their head
+    pub fn in_reach(&self, position: Vec3, center: [f32; 3]) -> bool {
+        let head = position + Vec3::new(0.0, PLAYER_HEAD_HEIGHT, 0.0);
+        let [x, y, z] = center;
+
+        (Vec3::new(x, y, z) - head).length() <= self.reach + REACH_TOLERANCE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::*;
+    use crate::models::*;
+
+    /// Solid ground everywhere below `y = 0`
+    struct FlatGround;
+
+    impl CharacterCollisionWorld for FlatGround {
+        fn cast_box(&self, center: Vec3, half_extents: Vec3, direction: Vec3, max_distance: f32) -> Option<CharacterHit> {
+            let height = center.y - half_extents.y;
+
+            if direction.y >= 0.0 || height < 0.0 {
+                return None;
+            }
+
+            let distance = height / -direction.y;
+
+            if distance > max_distance {
+                return None;
+            }
+
+            Some(CharacterHit {
+                distance,
+                normal: Vec3::unit_y()
+            })
+        }
+    }
+
+    #[test]
+    fn players_standing_on_the_ground_reach_the_voxels_around_them() {
+        let settings = VoxelEditSettings::default();
+        let mut controller = CharacterController::default();
+        let [x, y, z] = PLAYER_SPAWN_POINT;
+
+        // Where the server has the player once they've landed after spawning
+        let position = (0..120).fold(Vec3::new(x, y, z), |position, _| {
+            controller.step(position, &InputCommand::default(), 1.0 / 60.0, &FlatGround)
+        });
+        assert!(controller.grounded);
+
+        // The ground at their feet and a few voxels away
+        assert!(settings.in_reach(position, [x, -0.5, z]));
+        assert!(settings.in_reach(position, [x + 4.0, -0.5, z - 2.0]));
+        assert!(settings.in_reach(position, [x, position.y + 5.0, z]));
+
+        assert!(!settings.in_reach(position, [x + 8.0, -0.5, z]));
+        assert!(!settings.in_reach(position, [x, -8.0, z]));
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::{Iter, Keys};
+use bevy::math::Vec3;
+use crate::meshing::ChunkNeighbourhood;
+use crate::models::*;
+use crate::resources::PendingVoxelWrites;
+
+/// Where a ray met the ground
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct VoxelHit {
+    /// The solid voxel the ray hit
+    pub position: VoxelPos,
+    /// The voxel the ray passed through before it, which is where anything built onto the hit
+    /// voxel goes
+    pub previous: VoxelPos,
+    /// How far along the ray the hit voxel starts
+    pub distance: f32
+}
+
+/// The voxel data of every loaded chunk. The world is unbounded in every direction; only the
+/// chunks in the map exist.
+///
+/// A world built with `tracking_changes` marks every chunk whose mesh reaches a changed voxel as
+/// dirty, for the systems meshing it to pick up, which draw it on clients and collide with it on
+/// the server. Worlds nothing is meshed from don't keep track.
+#[derive(Default)]
+pub struct VoxelWorld {
+    chunks: HashMap<ChunkPos, Chunk>,
+    dirty: Option<HashSet<ChunkPos>>
+}
+
+impl VoxelWorld {
+    /// An empty world which keeps track of the chunks that need meshing again
+    pub fn tracking_changes() -> VoxelWorld {
+        VoxelWorld {
+            chunks: HashMap::new(),
+            dirty: Some(HashSet::new())
+        }
+    }
+
+    pub fn chunk(&self, position: ChunkPos) -> Option<&Chunk> {
+        self.chunks.get(&position)
+    }
+
+    pub fn chunk_mut(&mut self, position: ChunkPos) -> Option<&mut Chunk> {
+        self.chunks.get_mut(&position)
+    }
+
+    pub fn contains_chunk(&self, position: ChunkPos) -> bool {
+        self.chunks.contains_key(&position)
+    }
+
+    /// Adds a chunk, returning the chunk it replaced. The chunk and every chunk around it are
+    /// marked dirty, as their meshes all reach into it.
+    pub fn insert_chunk(&mut self, position: ChunkPos, chunk: Chunk) -> Option<Chunk> {
+        for x in -1..=1 {
+            for y in -1..=1 {
+                for z in -1..=1 {
+                    self.mark_dirty(position.offset(x, y, z));
+                }
+            }
+        }
+
+        self.chunks.insert(position, chunk)
+    }
+
+    pub fn remove_chunk(&mut self, position: ChunkPos) -> Option<Chunk> {
+        self.chunks.remove(&position)
+    }
+
+    pub fn chunk_positions(&self) -> Keys<'_, ChunkPos, Chunk> {
+        self.chunks.keys()
+    }
+
+    pub fn chunks(&self) -> Iter<'_, ChunkPos, Chunk> {
+        self.chunks.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// The voxel at a world position, or `None` if its chunk isn't loaded
+    pub fn voxel(&self, position: VoxelPos) -> Option<Voxel> {
+        self.chunk(position.chunk()).map(|chunk| chunk.get(position.local()))
+    }
+
+    /// The voxel at a world position, treating unloaded chunks as empty space
+    pub fn voxel_or_air(&self, position: VoxelPos) -> Voxel {
+        self.voxel(position).unwrap_or(Voxel::AIR)
+    }
+
+    /// Replaces the voxel at a world position, returning the voxel that was there before. Voxels
+    /// in chunks that aren't loaded are left alone, as generating the chunk would overwrite them.
+    pub fn set_voxel(&mut self, position: VoxelPos, voxel: Voxel) -> Option<Voxel> {
+        let previous = self.chunks.get_mut(&position.chunk())?.set(position.local(), voxel);
+
+        if previous != voxel {
+            self.mark_voxel_dirty(position);
+        }
+
+        Some(previous)
+    }
+
+    /// Resolves the features' writes into a loaded chunk again, returning whether it changed
+    pub fn resolve_writes(&mut self, position: ChunkPos, writes: &mut PendingVoxelWrites) -> bool {
+        let changed = match self.chunks.get_mut(&position) {
+            Some(chunk) => writes.resolve(position, chunk),
+            None => vec![]
+        };
+
+        for position in changed.iter() {
+            self.mark_voxel_dirty(*position);
+        }
+
+        !changed.is_empty()
+    }
+
+    /// Applies a brush to every loaded voxel it covers. Voxels in chunks that aren't loaded are
+    /// left alone, as generating the chunk would overwrite them. Returns the voxels it changed, as
+    /// they were before, so the edit can be undone.
+    pub fn apply_brush(&mut self, brush: &Brush) -> Vec<(VoxelPos, Voxel)> {
+        let (min, max) = brush.bounds();
+        let mut changed = vec![];
+
+        for z in min.2..=max.2 {
+            for y in min.1..=max.1 {
+                for x in min.0..=max.0 {
+                    let position = VoxelPos(x, y, z);
+
+                    let voxel = match self.voxel(position) {
+                        Some(voxel) => voxel,
+                        None => continue
+                    };
+
+                    let edited = brush.apply(position, voxel);
+
+                    if edited != voxel {
+                        self.set_voxel(position, edited);
+                        changed.push((position, voxel));
+                    }
+                }
+            }
+        }
+
+        changed
+    }
+
+    /// The first solid voxel along a ray within `max_distance`, with the empty voxel the ray
+    /// passed through just before it. Unloaded chunks count as empty.
+    pub fn raycast(&self, origin: Vec3, direction: Vec3, max_distance: f32) -> Option<VoxelHit> {
+        let direction = direction.normalize();
+        let origin = [origin.x, origin.y, origin.z];
+        let direction = [direction.x, direction.y, direction.z];
+
+        // Steps from voxel to voxel along the ray, crossing whichever boundary comes first
+        let mut voxel = [origin[0].floor() as i32, origin[1].floor() as i32, origin[2].floor() as i32];
+        let mut step = [0; 3];
+        let mut next = [std::f32::INFINITY; 3];
+        let mut delta = [std::f32::INFINITY; 3];
+
+        for axis in 0..3 {
+            if direction[axis] > 0.0 {
+                step[axis] = 1;
+                delta[axis] = 1.0 / direction[axis];
+                next[axis] = (voxel[axis] as f32 + 1.0 - origin[axis]) * delta[axis];
+            } else if direction[axis] < 0.0 {
+                step[axis] = -1;
+                delta[axis] = -1.0 / direction[axis];
+                next[axis] = (origin[axis] - voxel[axis] as f32) * delta[axis];
+            }
+        }
+
+        let mut previous = VoxelPos(voxel[0], voxel[1], voxel[2]);
+        let mut distance = 0.0;
+
+        while distance <= max_distance {
+            let position = VoxelPos(voxel[0], voxel[1], voxel[2]);
+
+            if self.voxel_or_air(position).is_solid() {
+                return Some(VoxelHit {
+                    position,
+                    previous,
+                    distance
+                });
+            }
+
+            let axis = if next[0] < next[1] && next[0] < next[2] { 0 } else if next[1] < next[2] { 1 } else { 2 };
+            previous = position;
+            distance = next[axis];
+            voxel[axis] += step[axis];
+            next[axis] += delta[axis];
+        }
+
+        None
+    }
+
+    /// The chunks whose meshes reach voxels changed since this was last called. Always empty for
+    /// a world that isn't tracking changes.
+    pub fn take_dirty_chunks(&mut self) -> HashSet<ChunkPos> {
+        self.dirty.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    fn mark_dirty(&mut self, position: ChunkPos) {
+        if let Some(dirty) = &mut self.dirty {
+            dirty.insert(position);
+        }
+    }
+
+    fn mark_voxel_dirty(&mut self, position: VoxelPos) {
+        if let Some(dirty) = &mut self.dirty {
+            dirty.extend(ChunkNeighbourhood::chunks_containing(position));
+        }
+    }
+}
@@ -1,190 +1,430 @@
-use bevy::{
-    prelude::*,
-    render::{
-        mesh::*,
-        pipeline::PrimitiveTopology
-    }
-};
-use noise::{
-    *,
-    utils::*
-};
-use isosurface::source::Source;
-use isosurface::source::HermiteSource;
-use isosurface::marching_cubes::MarchingCubes;
-use isosurface::source::CentralDifference;
-
-use crate::utilities::Gradient;
-
-pub struct Terrain<'a> {
-    chunk_size: i32,
-    x: i32,
-    y: i32,
-    z: i32,
-    generator: &'a Select<'a, [f64; 3]>,
-    // generator: Perlin
+use std::path::Path;
+use std::sync::Arc;
+use noise::NoiseFn;
+
+use crate::models::*;
+use crate::utilities::{NoiseSampler, BiomeMap, CaveCarver, FeaturePlacer};
+
+/// A world's seed, with the terrain noise graph and biome map built for it
+#[derive(Clone)]
+pub struct WorldGenerator {
+    seed: u64,
+    meshing_method: MeshingMethod,
+    graph: Arc<NoiseGraph>,
+    sampler: Arc<NoiseSampler>,
+    biome_map: Arc<BiomeMap>,
+    caves: Arc<CaveCarver>,
+    features: Arc<FeaturePlacer>
 }
 
-impl<'a> Terrain<'a> {
-    pub fn new(generator: &'a Select<'a, [f64; 3]>, chunk_size: i32, x: i32, y: i32, z: i32) -> Terrain<'a> {
-        Terrain {
-            generator,
-            chunk_size,
-            x,
-            y,
-            z
-        }
+/// The shape and materials of one column of terrain
+struct TerrainColumn {
+    /// The column's position, in chunks
+    x: f64,
+    z: f64,
+    height_offset: f64,
+    height_scale: f64,
+    biome: BiomeDefinition
+}
+
+/// The terrain columns over a chunk and a border around it, built once per chunk
+struct ColumnGrid {
+    /// The position of the first column, in voxels
+    first_x: i32,
+    first_z: i32,
+    span: usize,
+    columns: Vec<TerrainColumn>
+}
+
+impl ColumnGrid {
+    fn get(&self, x: i32, z: i32) -> &TerrainColumn {
+        let (x, z) = ((x - self.first_x) as usize, (z - self.first_z) as usize);
+        &self.columns[z * self.span + x]
     }
 }
 
-impl<'a> Source for Terrain<'a> {
-    fn sample(&self, x: f32, y: f32, z: f32) -> f32 {
-        // println!("x: {}", (self.x as f32 + x as f32));
-        // println!("y: {}", (self.y as f32 + y as f32));
-        // println!("z: {}", (self.z as f32 + z as f32));
-        self.generator.get([
-            (self.x as f32 + x as f32) as f64,
-            (self.y as f32 + y as f32) as f64,
-            (self.z as f32 + z as f32) as f64,
-        ]) as f32
+impl TerrainColumn {
+    /// The material of ground with `above` solid voxels directly over it
+    fn material(&self, above: usize) -> u8 {
+        match above {
+            0 => self.biome.surface_material,
+            above if above <= self.biome.subsurface_depth => self.biome.subsurface_material,
+            _ => STONE
+        }
     }
 }
 
-pub struct WorldGenerator {
-    chunk_size: usize
+impl Default for WorldGenerator {
+    fn default() -> Self {
+        WorldGenerator::new(0)
+    }
 }
 
-/// Generates terrain features in chunks
+/// Generates terrain features in chunks. The same world seed and graph always generate the same
+/// voxels.
 impl WorldGenerator {
-    pub fn new(chunk_size: usize) -> WorldGenerator {
-        
+    /// A generator for the default terrain graph and caves
+    pub fn new(seed: u64) -> WorldGenerator {
+        WorldGenerator::with_graph(seed, NoiseGraph::default_terrain(), CaveSettings::default())
+            .expect("The default terrain noise graph is invalid")
+    }
+
+    pub fn with_graph(seed: u64, graph: NoiseGraph, caves: CaveSettings) -> Result<WorldGenerator, NoiseGraphError> {
+        let sampler = NoiseSampler::build(&graph, seed)?;
+
+        Ok(WorldGenerator {
+            seed,
+            meshing_method: MeshingMethod::default(),
+            graph: Arc::new(graph),
+            sampler: Arc::new(sampler),
+            biome_map: Arc::new(BiomeMap::new(seed)),
+            caves: Arc::new(CaveCarver::new(seed, caves)),
+            features: Arc::new(FeaturePlacer::new(seed))
+        })
+    }
+
+    /// The same terrain graph for another world
+    pub fn with_seed(&self, seed: u64) -> WorldGenerator {
+        let sampler = NoiseSampler::build(&self.graph, seed)
+            .expect("A graph that built once always builds");
+
+        WorldGenerator {
+            seed,
+            meshing_method: self.meshing_method,
+            graph: self.graph.clone(),
+            sampler: Arc::new(sampler),
+            biome_map: Arc::new(BiomeMap::new(seed)),
+            caves: Arc::new(CaveCarver::new(seed, self.caves.settings().clone())),
+            features: Arc::new(FeaturePlacer::new(seed))
+        }
+    }
+
+    /// The same world with different caves
+    pub fn with_cave_settings(&self, settings: CaveSettings) -> WorldGenerator {
         WorldGenerator {
-            chunk_size
+            caves: Arc::new(CaveCarver::new(self.seed, settings)),
+            ..self.clone()
+        }
+    }
+
+    /// The same world meshed another way
+    pub fn with_meshing_method(&self, meshing_method: MeshingMethod) -> WorldGenerator {
+        WorldGenerator {
+            meshing_method,
+            ..self.clone()
+        }
+    }
+
+    /// Loads the terrain graph and cave settings from RON files, falling back to the defaults for
+    /// either file that's missing or invalid
+    pub fn load_or_default(seed: u64, graph_path: impl AsRef<Path>, caves_path: impl AsRef<Path>) -> WorldGenerator {
+        let (graph_path, caves_path) = (graph_path.as_ref(), caves_path.as_ref());
+
+        let caves = if caves_path.exists() {
+            CaveSettings::load(caves_path).unwrap_or_else(|error| {
+                println!("Failed to load cave settings {}: {}", caves_path.display(), error);
+                CaveSettings::default()
+            })
+        } else {
+            CaveSettings::default()
+        };
+
+        if graph_path.exists() {
+            match NoiseGraph::load(graph_path) {
+                Ok(graph) => match WorldGenerator::with_graph(seed, graph, caves.clone()) {
+                    Ok(world_generator) => return world_generator,
+                    Err(error) => println!("Failed to build terrain graph {}: {}", graph_path.display(), error)
+                },
+                Err(error) => println!("Failed to load terrain graph {}: {}", graph_path.display(), error)
+            }
+        }
+
+        WorldGenerator::new(seed).with_cave_settings(caves)
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn meshing_method(&self) -> MeshingMethod {
+        self.meshing_method
+    }
+
+    pub fn graph(&self) -> &NoiseGraph {
+        &self.graph
+    }
+
+    pub fn sampler(&self) -> &NoiseSampler {
+        &self.sampler
+    }
+
+    pub fn biome_map(&self) -> &BiomeMap {
+        &self.biome_map
+    }
+
+    /// The biome in the middle of a chunk
+    pub fn biome(&self, position: ChunkPos) -> Biome {
+        let middle = position.origin().offset(CHUNK_SIZE as i32 / 2, 0, CHUNK_SIZE as i32 / 2);
+        self.biome_at(middle)
+    }
+
+    /// The biome of the column a voxel is in
+    pub fn biome_at(&self, position: VoxelPos) -> Biome {
+        let scale = 1.0 / CHUNK_SIZE as f64;
+        self.biome_map.biome(position.0 as f64 * scale, position.2 as f64 * scale)
+    }
+
+    pub fn caves(&self) -> &CaveCarver {
+        &self.caves
+    }
+
+    /// What clients need to generate the same terrain
+    pub fn world_info(&self) -> WorldInfo {
+        WorldInfo {
+            seed: self.seed,
+            meshing_method: self.meshing_method,
+            graph: (*self.graph).clone(),
+            caves: self.caves.settings().clone()
         }
     }
 
-    pub fn generate(&self, chunk_x: i32, chunk_y: i32, chunk_z: i32) -> Mesh {
-        let ground_gradient = Gradient::new()
-            .set_x_start(0.0)
-            .set_y_stop(1.0);
+    /// Whether both generators generate the same terrain, without comparing graphs
+    pub fn is_same_world(&self, other: &WorldGenerator) -> bool {
+        self.seed == other.seed
+            && Arc::ptr_eq(&self.sampler, &other.sampler)
+            && Arc::ptr_eq(&self.caves, &other.caves)
+    }
 
-        let lowland_shape_fractal = Billow::new()
-            .set_octaves(2)
-            .set_frequency(0.25);
+    /// Fills a chunk with terrain. The noise is sampled with one unit per chunk.
+    ///
+    /// Each column is shaped by a blend of the biomes around it, and takes its materials from
+    /// the one with the most weight. Ground is covered by the biome's surface material, then
+    /// its subsurface material, then stone. Caves are carved out of the ground afterwards.
+    pub fn generate(&self, position: ChunkPos) -> Chunk {
+        let origin = position.origin();
+        let carving = self.caves.carve(position);
+        let margin = self.caves.settings().surface_margin;
+
+        // Carving looks `margin` voxels to the side of every voxel, so those columns are kept too
+        let border = if carving.is_some() { margin } else { 0 };
+        let columns = self.column_grid(origin, border);
+
+        // Sampled a little above the chunk as well, to know how deep its top voxels are
+        let column_height = CHUNK_SIZE + MAX_SUBSURFACE_DEPTH.max(margin) + 1;
+        let mut densities = vec![0.0; column_height];
+        let mut voxels = vec![Voxel::AIR; CHUNK_VOLUME];
+
+        for z in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                let column = columns.get(origin.0 + x as i32, origin.2 + z as i32);
+
+                for (y, density) in densities.iter_mut().enumerate() {
+                    *density = self.density(column, origin.1 + y as i32);
+                }
+
+                // How many solid voxels are directly above, counting down from the top
+                let mut depth = 0;
+
+                for y in (0..column_height).rev() {
+                    if densities[y] <= 0.0 {
+                        depth = 0;
+                        continue;
+                    }
+
+                    let above = depth;
+                    depth += 1;
+
+                    if y >= CHUNK_SIZE {
+                        continue;
+                    }
+
+                    let material = column.material(above);
+                    let mut density = densities[y];
+
+                    if let Some(carving) = &carving {
+                        let cave = carving.get(LocalVoxelPos(x, y, z));
+
+                        // Caves stay sealed under `margin` voxels of ground, narrowing to fit
+                        if cave > -1.0 && above > margin && self.is_buried(&columns, origin.offset(x as i32, y as i32, z as i32), margin) {
+                            density = density.min(-cave.min((above - margin) as f32 - 0.5));
+                        }
+                    }
+
+                    voxels[(z * CHUNK_SIZE + x) * CHUNK_SIZE + y] = Voxel::from_density(material, density);
+                }
+            }
+        }
+
+        Chunk::from_fn(|LocalVoxelPos(x, y, z)| voxels[(z * CHUNK_SIZE + x) * CHUNK_SIZE + y])
+    }
+
+    /// Samples the terrain at every `scale`th voxel from `first`, `count` samples along each axis,
+    /// for drawing chunks far away. Samples are ordered by z, then y, then x.
+    ///
+    /// Caves and features are left out. Caves never break the surface, and features are too small
+    /// to make out from where coarse chunks are seen.
+    pub fn sample_coarse(&self, first: VoxelPos, scale: i32, count: usize) -> Vec<Voxel> {
+        // Every voxel of a column is sampled, and a little above, to know how deep samples are
+        let height = (count - 1) * scale as usize + 1;
+        let column_height = height + MAX_SUBSURFACE_DEPTH + 1;
+        let mut densities = vec![0.0; column_height];
+        let mut voxels = vec![Voxel::AIR; count * count * count];
+
+        for z in 0..count {
+            for x in 0..count {
+                let column = self.column(first.0 + x as i32 * scale, first.2 + z as i32 * scale);
+
+                for (y, density) in densities.iter_mut().enumerate() {
+                    *density = self.density(&column, first.1 + y as i32);
+                }
+
+                let mut depth = 0;
+
+                for y in (0..column_height).rev() {
+                    if densities[y] <= 0.0 {
+                        depth = 0;
+                        continue;
+                    }
+
+                    let above = depth;
+                    depth += 1;
+
+                    if y >= height || y % scale as usize != 0 {
+                        continue;
+                    }
+
+                    let sample = y / scale as usize;
+                    voxels[(z * count + sample) * count + x] = Voxel::from_density(column.material(above), densities[y]);
+                }
+            }
+        }
+
+        voxels
+    }
+
+    /// Places trees, boulders and ore on a generated chunk, returning the voxels they write. Writes
+    /// land in the chunk and the chunks around it, and are resolved with the writes of those
+    /// chunks' features by `PendingVoxelWrites`.
+    pub fn decorate(&self, position: ChunkPos, chunk: &Chunk) -> Vec<VoxelWrite> {
+        let scale = 1.0 / CHUNK_SIZE as f64;
+
+        self.features.place(position, chunk, |x, z| {
+            self.biome_map.biome(x as f64 * scale, z as f64 * scale).definition()
+        })
+    }
+
+    fn column(&self, x: i32, z: i32) -> TerrainColumn {
+        let scale = 1.0 / CHUNK_SIZE as f64;
+        let (x, z) = (x as f64 * scale, z as f64 * scale);
+        let blend = self.biome_map.blend(x, z);
+
+        TerrainColumn {
+            x,
+            z,
+            height_offset: blend.height_offset(),
+            height_scale: blend.height_scale(),
+            biome: blend.dominant().definition()
+        }
+    }
+
+    /// The columns from `border` voxels before a chunk's origin to `border` voxels past its end
+    fn column_grid(&self, origin: VoxelPos, border: usize) -> ColumnGrid {
+        let span = CHUNK_SIZE + 2 * border;
+        let (first_x, first_z) = (origin.0 - border as i32, origin.2 - border as i32);
+        let columns = (0..span * span)
+            .map(|index| self.column(first_x + (index % span) as i32, first_z + (index / span) as i32))
+            .collect();
+
+        ColumnGrid {
+            first_x,
+            first_z,
+            span,
+            columns
+        }
+    }
+
+    /// The density of the terrain before caves are carved, in the range `-1.0..=1.0`
+    fn density(&self, column: &TerrainColumn, y: i32) -> f32 {
+        let y = y as f64 / CHUNK_SIZE as f64;
+        let value = self.sampler.get([column.x, (y - column.height_offset) / column.height_scale, column.z]);
+
+        // The terrain graph selects between 0 for open space and 1 for ground
+        (value as f32 - 0.5) * 2.0
+    }
+
+    /// Whether there's ground half and all of `margin` voxels away on each side of a voxel, so
+    /// a cave there won't break through a cliff
+    fn is_buried(&self, columns: &ColumnGrid, position: VoxelPos, margin: usize) -> bool {
+        let distances = [(margin as i32 + 1) / 2, margin as i32];
+
+        distances.iter().all(|distance| {
+            [(*distance, 0), (-*distance, 0), (0, *distance), (0, -*distance)].iter().all(|(x, z)| {
+                let column = columns.get(position.0 + x, position.2 + z);
+                self.density(column, position.1) > 0.0
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The hash of `generated_bytes` for seed 7. Changes to generation that are meant to change the
+    /// terrain update this, anything else changing it breaks worlds already being played.
+    const SEED_7_HASH: u64 = 0x15B8_442B_EBE3_A8E0;
+
+    /// Generates and decorates a block of chunks around the surface, as bytes
+    fn generated_bytes(generator: &WorldGenerator) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        for x in -1..=1 {
+            for y in -2..=1 {
+                for z in -1..=1 {
+                    let position = ChunkPos(x, y, z);
+                    let chunk = generator.generate(position);
+                    let writes = generator.decorate(position, &chunk);
+
+                    for (_, voxel) in chunk.iter() {
+                        bytes.extend_from_slice(&[voxel.material, voxel.density as u8]);
+                    }
+
+                    for write in writes {
+                        let VoxelPos(x, y, z) = write.position;
+                        for coordinate in [x, y, z].iter() {
+                            bytes.extend_from_slice(&coordinate.to_le_bytes());
+                        }
+
+                        bytes.extend_from_slice(&[write.voxel.material, write.voxel.density as u8]);
+                        bytes.extend_from_slice(&match write.rule {
+                            WriteRule::IntoAir => [0, 0],
+                            WriteRule::IntoMaterial(material) => [1, material]
+                        });
+                    }
+                }
+            }
+        }
+
+        bytes
+    }
+
+    fn fnv1a(bytes: &[u8]) -> u64 {
+        bytes.iter().fold(0xCBF2_9CE4_8422_2325, |hash, byte| {
+            (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01B3)
+        })
+    }
+
+    #[test]
+    fn same_seed_generates_same_bytes() {
+        let first = generated_bytes(&WorldGenerator::new(7));
+        let second = generated_bytes(&WorldGenerator::new(7));
+
+        assert!(first == second, "Two generators with the same seed generated different terrain");
+        assert_ne!(first, generated_bytes(&WorldGenerator::new(8)));
+    }
 
-        let lowland_autocorrect = Clamp::<[f64; 3]>::new(&lowland_shape_fractal) // TODO: Should use AutoCorrect not Clamp
-            .set_bounds(-1.0, 1.0);
-
-        let lowland_scale = ScaleBias::new(&lowland_autocorrect)
-            .set_bias(-0.45)
-            .set_scale(0.125);
-
-        let lowland_y_scale = ScalePoint::new(&lowland_scale)
-            .set_y_scale(0.0);
-        
-        let lowland_terrain = Displace::new(
-            &ground_gradient,
-            Constant::new(0.0),
-            &lowland_y_scale,
-            Constant::new(0.0),
-            Constant::new(0.0)
-        );
-
-        let highland_shape_fractal = Fbm::new()
-            .set_octaves(4)
-            .set_frequency(2.0);
-
-        let highland_autocorrect = Clamp::<[f64; 3]>::new(&highland_shape_fractal) // TODO: Should use AutoCorrect not Clamp
-            .set_bounds(-1.0, 1.0);
-
-        let highland_scale = ScaleBias::new(&highland_autocorrect)
-            .set_bias(0.0)
-            .set_scale(0.25);
-
-        let highland_y_scale = ScalePoint::new(&highland_scale)
-            .set_y_scale(0.0);
-
-        let highland_terrain = Displace::new(
-            &ground_gradient,
-            Constant::new(0.0),
-            &highland_y_scale,
-            Constant::new(0.0),
-            Constant::new(0.0)
-        );
-
-        let mountain_shape_fractal = RidgedMulti::new()
-            .set_octaves(4)
-            .set_frequency(2.0);
-        
-        let mountain_autocorrect = Clamp::new(&mountain_shape_fractal) // TODO: Should use AutoCorrect not Clamp
-            .set_bounds(-1.0, 1.0);
-
-        let mountain_scale = ScaleBias::new(&mountain_autocorrect)
-            .set_bias(0.15)
-            .set_scale(0.45);
-
-        let mountain_y_scale = ScalePoint::new(&mountain_scale)
-            .set_y_scale(0.25);
-
-        let mountain_terrain = Displace::new(
-            &ground_gradient,
-            Constant::new(0.0),
-            &mountain_y_scale,
-            Constant::new(0.0),
-            Constant::new(0.0)
-        );
-
-        let terrain_type_fractal = Fbm::new()
-            .set_octaves(3)
-            .set_frequency(0.125);
-
-        let terrain_type_autocorrect = Clamp::new(&terrain_type_fractal) // TODO: Should use AutoCorrect not Clamp
-            .set_bounds(-1.0, 1.0);
-
-        let terrain_type_y_scale = ScalePoint::new(&terrain_type_autocorrect)
-            .set_y_scale(0.0);
-
-        let terrain_type_cache = Cache::new(&terrain_type_y_scale);
-
-        let highland_mountain_select = Select::new(&highland_terrain, &mountain_terrain, &terrain_type_cache)
-            .set_falloff(0.2);
-
-        let highland_lowland_select = Select::new(&lowland_terrain, &highland_mountain_select, &terrain_type_cache)
-            .set_falloff(0.15);
-
-        let highland_lowland_select_cache = Cache::new(&highland_lowland_select);
-
-        let source1 = Constant::new(0.0);
-        let source2 = Constant::new(1.0);
-        let generator = Select::new(&source1, &source2, &highland_lowland_select_cache);
-
-        let terrain = CentralDifference::new_with_epsilon(Terrain::new(&generator, self.chunk_size as i32, chunk_x, chunk_y, chunk_z), 0.1);
-
-        let mut vertices = vec![];
-        let mut indices = vec![];
-        let mut normals = vec![];
-
-        let mut marching_cubes = MarchingCubes::new(self.chunk_size);
-
-        marching_cubes.extract_with(
-            &terrain,
-            |v: isosurface::math::Vec3| {
-                let n = terrain.sample_normal(v.x, v.y, v.z);
-                // if n != isosurface::math::Vec3::zero() {
-                //     println!("{:?}", n);
-                // }
-                vertices.push([v.x, v.y, v.z]);
-                normals.push([n.x, n.y, n.z]);
-            },
-            &mut indices
-        );
-
-        // indices.reverse();
-
-        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
-        mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, VertexAttributeValues::Float3(vertices));
-        mesh.set_attribute(Mesh::ATTRIBUTE_NORMAL, VertexAttributeValues::Float3(normals));
-        mesh.set_indices(Some(Indices::U32(indices)));
-        mesh
+    #[test]
+    fn generation_matches_stored_hash() {
+        assert_eq!(fnv1a(&generated_bytes(&WorldGenerator::new(7))), SEED_7_HASH);
     }
 }
@@ -1,31 +1,51 @@
 mod command_accumulator;
 mod local_player_camera;
 mod local_player_movement;
+mod player_presentation;
 mod player_movement;
+mod player_animation;
 mod client_prediction;
 mod network_message_listener;
 mod server_player_movement;
 mod chunk_loading_system;
+mod chunk_meshing_system;
+mod terrain_graph_reload;
 mod server_state_preauthoring;
 mod server_state_authoring;
 mod server_entity_spawning;
 mod client_entity_spawning;
 mod client_authoratative_state_consumption;
 mod window_resolution;
+mod replay_playback;
+mod server_voxel_editing;
+mod voxel_editing;
+mod server_chunk_transfer;
+mod client_chunk_transfer;
+mod server_terrain_collision;
 
 pub use self::{
     command_accumulator::*,
     local_player_camera::*,
     local_player_movement::*,
+    player_presentation::*,
     player_movement::*,
+    player_animation::*,
     client_prediction::*,
     network_message_listener::*,
     server_player_movement::*,
     chunk_loading_system::*,
+    chunk_meshing_system::*,
+    terrain_graph_reload::*,
     server_state_preauthoring::*,
     server_state_authoring::*,
     server_entity_spawning::*,
     client_entity_spawning::*,
     client_authoratative_state_consumption::*,
-    window_resolution::*
+    window_resolution::*,
+    replay_playback::*,
+    server_voxel_editing::*,
+    voxel_editing::*,
+    server_chunk_transfer::*,
+    client_chunk_transfer::*,
+    server_terrain_collision::*
 };
@@ -1,38 +1,296 @@
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
 use bevy::{
     prelude::*,
+    tasks::{AsyncComputeTaskPool, Task}
 };
+use futures_lite::future;
+use crate::components::*;
+use crate::models::*;
 use crate::resources::*;
+use crate::systems::ChunkMeshingState;
 
+/// A generated chunk, with the voxels its features placed in other chunks
+pub type ChunkTaskOutput = (Chunk, Vec<VoxelWrite>);
 
-/// This system prints out all mouse events as they come in
+#[derive(Default)]
+pub struct ChunkLoadingState {
+    /// Chunks whose voxels are in the voxel world
+    loaded: HashSet<ChunkPos>,
+    /// Chunks being generated in the background. Dropping a task cancels it.
+    tasks: HashMap<ChunkPos, Task<ChunkTaskOutput>>,
+    /// Generated chunks waiting for room in a frame, or for the voxels the server sends of edited
+    /// ones
+    generated: HashMap<ChunkPos, ChunkTaskOutput>,
+    /// Loaded chunks the server hasn't told us the revision of yet, which are taken to be unedited
+    /// until it does
+    unconfirmed: HashSet<ChunkPos>,
+    /// The level of detail every chunk in view should be drawn at
+    lods: HashMap<ChunkPos, u8>,
+    /// Chunks waiting to load, furthest first so the closest can be popped off the end
+    pending: Vec<ChunkPos>,
+    /// The chunks the players were in when `pending` and `lods` were built
+    centers: Vec<ChunkPos>,
+    /// The world the loaded chunks were generated for
+    world: Option<WorldGenerator>
+}
+
+impl ChunkLoadingState {
+    pub fn is_loaded(&self, position: ChunkPos) -> bool {
+        self.loaded.contains(&position)
+    }
+
+    pub fn loaded_count(&self) -> usize {
+        self.loaded.len()
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn task_count(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// The chunks the players were in when the levels of detail were last picked
+    pub fn centers(&self) -> &[ChunkPos] {
+        &self.centers
+    }
+
+    /// The level of detail a chunk should be drawn at, or nothing if it's out of view
+    pub fn lod(&self, position: ChunkPos) -> Option<u8> {
+        self.lods.get(&position).cloned()
+    }
+
+    /// Every chunk in view, with the level of detail it should be drawn at
+    pub fn lods(&self) -> &HashMap<ChunkPos, u8> {
+        &self.lods
+    }
+
+    /// Whether a chunk is loaded or on its way, so it mustn't be requested again
+    fn is_requested(&self, position: ChunkPos) -> bool {
+        self.loaded.contains(&position) || self.tasks.contains_key(&position) || self.generated.contains_key(&position)
+    }
+}
+
+/// Orders chunks by the ring around the nearest player they fall in, then by straight line
+/// distance within the ring
+pub fn load_priority(centers: &[ChunkPos], position: ChunkPos) -> (i32, i32) {
+    centers.iter()
+        .map(|center| {
+            let (x, y, z) = (position.0 - center.0, position.1 - center.1, position.2 - center.2);
+            (center.chebyshev_distance(position), x * x + y * y + z * z)
+        })
+        .min()
+        .unwrap_or((i32::MAX, i32::MAX))
+}
+
+/// Generates and decorates a chunk, off the main thread. The features' writes are resolved once
+/// the chunk is added to the world.
+pub async fn build_chunk(world_generator: WorldGenerator, position: ChunkPos) -> ChunkTaskOutput {
+    let chunk = world_generator.generate(position);
+    let writes = world_generator.decorate(position, &chunk);
+
+    (chunk, writes)
+}
+
+/// Streams chunks in and out of the voxel world around every local player, generating them on the
+/// `AsyncComputeTaskPool`. Only chunks near a player are generated in full. Every chunk in view is
+/// given a level of detail by its distance, which is picked again as players move between chunks,
+/// for `chunk_meshing_system` to draw it at.
+///
+/// Chunks are added as generated until the server tells us their revision, as it may never
+/// arrive. Chunks someone has edited are still generated, for the features they place around them,
+/// but their voxels are swapped for the ones the server sends, once they arrive. The server's
+/// copy already has every feature, so the writes features make into edited chunks are dropped.
 pub fn chunk_loading_system(
-    commands: &mut Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut state: ResMut<ChunkLoadingState>,
+    meshing: Res<ChunkMeshingState>,
+    settings: Res<ChunkStreamingSettings>,
+    task_pool: Res<AsyncComputeTaskPool>,
+    mut voxel_world: ResMut<VoxelWorld>,
+    mut pending_writes: ResMut<PendingVoxelWrites>,
+    mut cache: ResMut<ChunkCache>,
     world_generator: Res<WorldGenerator>,
-    // mut player_body_query: Query<(&LocalPlayerBody, &mut Translation)>,
+    player_body_query: Query<&Transform, With<LocalPlayerBody>>,
 ) {
-    // commands.spawn(PbrComponents {
-    //     mesh: meshes.add(world_generator.generate(0, 0, 0)),
-    //     material: materials.add(Color::rgb(0.2, 0.1, 0.1).into()),
-    //     translation: Translation::new(0.0, 0.0, 0.0),
-    //     ..Default::default()
-    // });
-
-    // layer below
-
-    let world_chunk_size = 5;
-
-    for x in 0..world_chunk_size {
-        for y in 0..world_chunk_size {
-            for z in 0..world_chunk_size {
-                commands.spawn(PbrBundle {
-                    mesh: meshes.add(world_generator.generate(x, y, z)),
-                    material: materials.add(Color::rgb(1.0, 0.1, 0.1).into()),
-                    transform: Transform::from_translation(Vec3::new((16 * x) as f32, (16 * y) as f32, (16 * z) as f32)),
-                    ..Default::default()
-                });
+    let mut centers: Vec<ChunkPos> = player_body_query.iter()
+        .map(|transform| ChunkPos::from_world(transform.translation))
+        .collect();
+    centers.sort();
+    centers.dedup();
+
+    if centers.is_empty() {
+        return;
+    }
+
+    // A different world was joined or the terrain graph was reloaded, so everything loaded so far
+    // belongs to the old one
+    if !state.world.as_ref().map_or(false, |world| world.is_same_world(&world_generator)) {
+        for position in state.loaded.drain() {
+            voxel_world.remove_chunk(position);
+        }
+
+        state.tasks.clear();
+        state.generated.clear();
+        state.unconfirmed.clear();
+        state.lods.clear();
+        state.pending.clear();
+        state.centers.clear();
+        pending_writes.clear();
+        state.world = Some(world_generator.clone());
+    }
+
+    if centers != state.centers {
+        let out_of_range = |position: &ChunkPos| !centers.iter().any(|center| settings.retains_voxels(*center, *position));
+
+        // Unload the voxels of chunks no player is near enough to see in full
+        let unloading: Vec<ChunkPos> = state.loaded.iter().filter(|position| out_of_range(position)).cloned().collect();
+        for position in unloading {
+            state.loaded.remove(&position);
+            state.unconfirmed.remove(&position);
+            voxel_world.remove_chunk(position);
+            pending_writes.remove_chunk(position);
+        }
+
+        // Cancel work on chunks nobody will see
+        state.tasks.retain(|position, _| !out_of_range(position));
+        state.generated.retain(|position, _| !out_of_range(position));
+
+        // Pick the level of detail of everything in view, and queue the voxels of everything near
+        // enough to be drawn in full that isn't loaded or on its way
+        let mut lods = HashMap::new();
+        let mut pending = HashSet::new();
+        for center in centers.iter() {
+            for x in -settings.view_distance..=settings.view_distance {
+                for y in -settings.vertical_view_distance..=settings.vertical_view_distance {
+                    for z in -settings.view_distance..=settings.view_distance {
+                        let position = center.offset(x, y, z);
+                        lods.insert(position, settings.lod(&centers, position));
+
+                        if settings.needs_voxels(*center, position) && !state.is_requested(position) {
+                            pending.insert(position);
+                        }
+                    }
+                }
             }
         }
+
+        let mut pending: Vec<ChunkPos> = pending.into_iter().collect();
+        pending.sort_by_key(|position| std::cmp::Reverse(load_priority(&centers, *position)));
+
+        state.lods = lods;
+        state.pending = pending;
+        state.centers = centers;
+    }
+
+    // Collect finished chunks
+    let positions: Vec<ChunkPos> = state.tasks.keys().cloned().collect();
+
+    for position in positions {
+        let finished = match state.tasks.get_mut(&position) {
+            Some(task) => future::block_on(future::poll_once(task)),
+            None => None
+        };
+
+        if let Some(output) = finished {
+            state.tasks.remove(&position);
+            state.generated.insert(position, output);
+        }
+    }
+
+    // Add generated chunks to the world, closest first and within the frame's budget
+    let started = Instant::now();
+    let mut loaded_this_frame = 0;
+    let mut positions: Vec<ChunkPos> = state.generated.keys().cloned().collect();
+    positions.sort_by_key(|position| load_priority(&state.centers, *position));
+
+    for position in positions {
+        if loaded_this_frame >= settings.chunks_per_frame || started.elapsed() >= settings.frame_budget {
+            break;
+        }
+
+        // Wait for the voxels of chunks the server says have been edited
+        let edited = match cache.revision(position) {
+            None | Some(UNEDITED_REVISION) => None,
+            Some(_) => match cache.chunk(position) {
+                Some(chunk) => Some(chunk.clone()),
+                None => {
+                    cache.want(position);
+                    continue;
+                }
+            }
+        };
+
+        if cache.revision(position).is_none() {
+            state.unconfirmed.insert(position);
+        }
+
+        let (mut chunk, writes) = state.generated.remove(&position).unwrap();
+        let targets = pending_writes.add(position, writes);
+
+        match edited {
+            Some(edited) => {
+                chunk = edited;
+                pending_writes.discard_writes_into(position);
+            },
+            // Its own features, and those of chunks which finished while it was being built
+            None => {
+                pending_writes.resolve(position, &mut chunk);
+            }
+        }
+
+        voxel_world.insert_chunk(position, chunk);
+        state.loaded.insert(position);
+        loaded_this_frame += 1;
+
+        // Resolve loaded neighbours again with this chunk's features, unless they were edited
+        for target in targets {
+            if target == position || !voxel_world.contains_chunk(target) {
+                continue;
+            }
+
+            match cache.revision(target) {
+                None | Some(UNEDITED_REVISION) => {
+                    voxel_world.resolve_writes(target, &mut pending_writes);
+                },
+                Some(_) => pending_writes.discard_writes_into(target)
+            }
+        }
+    }
+
+    // Swap in the voxels of chunks loaded as generated that we've since heard were edited
+    let unconfirmed: Vec<ChunkPos> = state.unconfirmed.iter().cloned().collect();
+
+    for position in unconfirmed {
+        match cache.revision(position) {
+            None => {},
+            Some(UNEDITED_REVISION) => {
+                state.unconfirmed.remove(&position);
+            },
+            Some(_) => match cache.chunk(position) {
+                Some(chunk) => {
+                    voxel_world.insert_chunk(position, chunk.clone());
+                    pending_writes.discard_writes_into(position);
+                    state.unconfirmed.remove(&position);
+                },
+                None => cache.want(position)
+            }
+        }
+    }
+
+    // Start generating the closest chunks with whatever room meshing left
+    while state.task_count() + meshing.task_count() < settings.max_tasks {
+        let position = match state.pending.pop() {
+            Some(position) => position,
+            None => break
+        };
+
+        if state.is_requested(position) {
+            continue;
+        }
+
+        let task = task_pool.spawn(build_chunk(world_generator.clone(), position));
+        state.tasks.insert(position, task);
     }
 }
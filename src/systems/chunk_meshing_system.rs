@@ -0,0 +1,359 @@
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
+use bevy::{
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task}
+};
+use bevy_rapier3d::rapier::dynamics::{BodyStatus, RigidBodyBuilder};
+use bevy_rapier3d::rapier::geometry::ColliderBuilder;
+use futures_lite::future;
+use crate::components::*;
+use crate::meshing::*;
+use crate::models::*;
+use crate::render::*;
+use crate::resources::*;
+use crate::systems::{ChunkLoadingState, load_priority};
+
+/// The mesh of a chunk's surface if it has one, with a collider for it if it's drawn in full detail
+type MeshTaskOutput = Option<(Mesh, Option<ColliderBuilder>)>;
+
+#[derive(Default)]
+pub struct ChunkMeshingState {
+    /// Every chunk drawn, with the level of detail it was meshed at and the entity displaying it
+    /// if it has a surface
+    drawn: HashMap<ChunkPos, (u8, Option<Entity>)>,
+    /// Chunks being meshed in the background at a level of detail. Dropping a task cancels it.
+    tasks: HashMap<ChunkPos, (u8, Task<MeshTaskOutput>)>,
+    /// Chunks whose mesh is missing, out of date or at the wrong level of detail
+    dirty: HashSet<ChunkPos>,
+    /// Colliders waiting to be swapped in, or nothing where a chunk's collider is to be taken out
+    queued_colliders: HashMap<ChunkPos, Option<ColliderBuilder>>,
+    /// The static body colliding with each chunk drawn in full detail
+    colliders: HashMap<ChunkPos, Entity>,
+    /// The players' chunks when the drawn chunks were last checked against their levels of detail
+    centers: Vec<ChunkPos>,
+    /// The world the drawn chunks were meshed for
+    world: Option<WorldGenerator>,
+    material: Option<Handle<TerrainMaterial>>
+}
+
+impl ChunkMeshingState {
+    pub fn drawn_count(&self) -> usize {
+        self.drawn.len()
+    }
+
+    pub fn dirty_count(&self) -> usize {
+        self.dirty.len()
+    }
+
+    pub fn task_count(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Swaps a chunk's collider for another once there's room in a frame's budget
+    fn queue_collider(&mut self, position: ChunkPos, collider: Option<ColliderBuilder>) {
+        if collider.is_some() || self.colliders.contains_key(&position) {
+            self.queued_colliders.insert(position, collider);
+        } else {
+            self.queued_colliders.remove(&position);
+        }
+    }
+}
+
+/// Meshes a chunk's surface off the main thread, from a copy of the voxels it reaches. Only chunks
+/// drawn in full detail collide, as players never get near the coarser ones.
+async fn mesh_chunk(neighbourhood: ChunkNeighbourhood, method: MeshingMethod) -> MeshTaskOutput {
+    let mesh = build_mesh(&neighbourhood, method);
+
+    if mesh.is_empty() {
+        return None;
+    }
+
+    let collider = if neighbourhood.scale() == 1 { Some(mesh.collider()) } else { None };
+    Some((mesh.into_mesh(), collider))
+}
+
+/// Samples a far away chunk straight from the world generator and meshes its surface at a level
+/// of detail, off the main thread. The edited chunks it reaches are sampled from the voxels the
+/// server sent instead.
+async fn mesh_coarse_chunk(world_generator: WorldGenerator, position: ChunkPos, lod: u8, edited: Vec<(ChunkPos, Chunk)>) -> MeshTaskOutput {
+    let mut neighbourhood = ChunkNeighbourhood::from_generator(&world_generator, position, 1 << lod);
+
+    for (edited_position, chunk) in edited.iter() {
+        neighbourhood.overlay_chunk(*edited_position, chunk);
+    }
+
+    if neighbourhood.has_surface() {
+        mesh_chunk(neighbourhood, world_generator.meshing_method()).await
+    } else {
+        None
+    }
+}
+
+fn spawn_chunk_entity(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    material: &Handle<TerrainMaterial>,
+    world_generator: &WorldGenerator,
+    position: ChunkPos,
+    mesh: Mesh
+) -> Entity {
+    commands
+        .spawn(TerrainBundle {
+            mesh: meshes.add(mesh),
+            material: material.clone(),
+            transform: Transform::from_translation(position.translation()),
+            ..Default::default()
+        })
+        .with(Terrain)
+        .with(TerrainChunk {
+            position,
+            biome: world_generator.biome(position)
+        });
+
+    commands.current_entity().unwrap()
+}
+
+/// Spawns the static body a chunk's terrain collides through
+pub fn spawn_collider_entity(commands: &mut Commands, position: ChunkPos, collider: ColliderBuilder) -> Entity {
+    let origin = position.translation();
+
+    commands
+        .spawn((Terrain,))
+        .with(RigidBodyBuilder::new(BodyStatus::Static).translation(origin.x, origin.y, origin.z))
+        .with(collider);
+
+    commands.current_entity().unwrap()
+}
+
+/// Keeps the meshes and colliders of the chunks in view up to date. Chunks are meshed on the
+/// `AsyncComputeTaskPool` at the level of detail `chunk_loading_system` picked for them, closest
+/// first and only as many per frame as the streaming settings allow. A chunk drawn in full detail
+/// is meshed again whenever the voxel world reports a change it reaches, so edits only cost the
+/// chunks they touch. Coarser chunks are meshed again when the server sends the voxels of an
+/// edited chunk they reach.
+pub fn chunk_meshing_system(
+    commands: &mut Commands,
+    mut state: ResMut<ChunkMeshingState>,
+    loading: Res<ChunkLoadingState>,
+    settings: Res<ChunkStreamingSettings>,
+    task_pool: Res<AsyncComputeTaskPool>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<TerrainMaterial>>,
+    terrain_textures: Res<TerrainTextures>,
+    mut voxel_world: ResMut<VoxelWorld>,
+    mut cache: ResMut<ChunkCache>,
+    world_generator: Res<WorldGenerator>
+) {
+    let material = match &state.material {
+        Some(material) => material.clone(),
+        None => {
+            let material = materials.add(terrain_textures.material());
+            state.material = Some(material.clone());
+            material
+        }
+    };
+
+    // Everything drawn so far belongs to a world that's gone
+    if !state.world.as_ref().map_or(false, |world| world.is_same_world(&world_generator)) {
+        for (_, (_, entity)) in state.drawn.drain() {
+            if let Some(entity) = entity {
+                commands.despawn(entity);
+            }
+        }
+
+        for (_, entity) in state.colliders.drain() {
+            commands.despawn(entity);
+        }
+
+        state.tasks.clear();
+        state.dirty.clear();
+        state.queued_colliders.clear();
+        state.centers.clear();
+        state.world = Some(world_generator.clone());
+    }
+
+    // The same terrain meshed another way only needs meshing again
+    if state.world.as_ref().map_or(false, |world| world.meshing_method() != world_generator.meshing_method()) {
+        state.tasks.clear();
+        state.dirty.extend(loading.lods().keys().cloned());
+        state.world = Some(world_generator.clone());
+    }
+
+    if loading.centers() != state.centers.as_slice() {
+        let centers = loading.centers();
+        let out_of_range = |position: &ChunkPos| !centers.iter().any(|center| settings.in_retention(*center, *position));
+
+        // Stop drawing chunks no player is near any more
+        let hiding: Vec<ChunkPos> = state.drawn.keys().filter(|position| out_of_range(position)).cloned().collect();
+        for position in hiding {
+            if let Some((_, Some(entity))) = state.drawn.remove(&position) {
+                commands.despawn(entity);
+            }
+
+            state.tasks.remove(&position);
+            state.dirty.remove(&position);
+            state.queue_collider(position, None);
+        }
+
+        state.tasks.retain(|position, _| !out_of_range(position));
+
+        // Mesh again whatever isn't drawn or on its way at the level it should be. Chunks keep
+        // their old mesh until the new one is ready.
+        for (position, lod) in loading.lods().iter() {
+            let drawn = state.drawn.get(position).map(|(drawn, _)| *drawn);
+            let meshing = state.tasks.get(position).map(|(meshing, _)| *meshing);
+
+            if drawn != Some(*lod) && meshing != Some(*lod) {
+                state.dirty.insert(*position);
+            }
+        }
+
+        state.centers = centers.to_vec();
+    }
+
+    // Only chunks drawn in full detail are meshed from the voxel world, so coarser ones don't
+    // change with it
+    for position in voxel_world.take_dirty_chunks() {
+        if loading.lod(position) == Some(0) {
+            state.dirty.insert(position);
+        }
+    }
+
+    // Coarser chunks reaching an edited chunk whose voxels or revision changed are sampled again
+    for position in cache.take_changed() {
+        if cache.revision(position) == Some(UNEDITED_REVISION) {
+            continue;
+        }
+
+        for x in -2..=1 {
+            for y in -2..=1 {
+                for z in -2..=1 {
+                    let neighbour = position.offset(x, y, z);
+
+                    match loading.lod(neighbour) {
+                        Some(lod) if lod > 0 && ChunkNeighbourhood::chunks_covered(neighbour, 1 << lod).contains(&position) => {
+                            state.dirty.insert(neighbour);
+                        },
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    // Mesh the closest chunks, at full detail once their neighbourhoods have all loaded. A chunk
+    // already being meshed starts over, as its voxels or level of detail have changed since.
+    let started = Instant::now();
+    let mut dirty: Vec<ChunkPos> = state.dirty.iter().cloned().collect();
+    dirty.sort_by_key(|position| std::cmp::Reverse(load_priority(&state.centers, *position)));
+
+    while state.task_count() + loading.task_count() < settings.max_tasks && started.elapsed() < settings.frame_budget {
+        let position = match dirty.pop() {
+            Some(position) => position,
+            None => break
+        };
+
+        state.dirty.remove(&position);
+
+        let lod = match loading.lod(position) {
+            Some(lod) => lod,
+            None => continue
+        };
+
+        if lod > 0 {
+            // Edited chunks whose voxels haven't arrived are asked for, and drawn as generated
+            // until they do
+            let mut edited = vec![];
+            for covered in ChunkNeighbourhood::chunks_covered(position, 1 << lod) {
+                match cache.revision(covered) {
+                    None | Some(UNEDITED_REVISION) => {},
+                    Some(_) => match cache.chunk(covered) {
+                        Some(chunk) => edited.push((covered, chunk.clone())),
+                        None => cache.want(covered)
+                    }
+                }
+            }
+
+            let task = task_pool.spawn(mesh_coarse_chunk(world_generator.clone(), position, lod, edited));
+            state.tasks.insert(position, (lod, task));
+            continue;
+        }
+
+        // Chunks missing a neighbour are marked again once it loads
+        let neighbourhood = match ChunkNeighbourhood::from_world(&voxel_world, position) {
+            Some(neighbourhood) => neighbourhood,
+            None => continue
+        };
+
+        if neighbourhood.has_surface() {
+            let task = task_pool.spawn(mesh_chunk(neighbourhood, world_generator.meshing_method()));
+            state.tasks.insert(position, (0, task));
+        } else {
+            state.tasks.remove(&position);
+            state.queue_collider(position, None);
+
+            if let Some((_, Some(entity))) = state.drawn.insert(position, (0, None)) {
+                commands.despawn(entity);
+            }
+        }
+    }
+
+    // Swap in finished meshes, queueing their colliders
+    let mut swapped = 0;
+    let positions: Vec<ChunkPos> = state.tasks.keys().cloned().collect();
+
+    for position in positions {
+        if swapped >= settings.meshes_per_frame || started.elapsed() >= settings.frame_budget {
+            break;
+        }
+
+        let finished = match state.tasks.get_mut(&position) {
+            Some((lod, task)) => future::block_on(future::poll_once(task)).map(|output| (*lod, output)),
+            None => None
+        };
+
+        let (lod, output) = match finished {
+            Some(finished) => finished,
+            None => continue
+        };
+
+        state.tasks.remove(&position);
+        swapped += 1;
+
+        let (entity, collider) = match output {
+            Some((mesh, collider)) => {
+                (Some(spawn_chunk_entity(commands, &mut meshes, &material, &world_generator, position, mesh)), collider)
+            },
+            None => (None, None)
+        };
+
+        state.queue_collider(position, collider);
+
+        if let Some((_, Some(entity))) = state.drawn.insert(position, (lod, entity)) {
+            commands.despawn(entity);
+        }
+    }
+
+    // Swap in a few colliders, closest first, as each one changes the physics world
+    let mut queued: Vec<ChunkPos> = state.queued_colliders.keys().cloned().collect();
+    queued.sort_by_key(|position| std::cmp::Reverse(load_priority(&state.centers, *position)));
+
+    for _ in 0..settings.colliders_per_frame {
+        let position = match queued.pop() {
+            Some(position) => position,
+            None => break
+        };
+
+        let collider = state.queued_colliders.remove(&position).unwrap();
+
+        if let Some(entity) = state.colliders.remove(&position) {
+            commands.despawn(entity);
+        }
+
+        if let Some(collider) = collider {
+            let entity = spawn_collider_entity(commands, position, collider);
+            state.colliders.insert(position, entity);
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use bevy::prelude::*;
+use bevy_prototype_networking_laminar::{NetworkResource, NetworkDelivery};
+use crate::events::*;
+use crate::models::*;
+use crate::resources::*;
+
+/// The most chunks asked for in one message
+const CHUNKS_PER_REQUEST: usize = 64;
+
+/// Client only
+#[derive(Default)]
+pub struct ClientChunkTransferState {
+    /// The world the cached chunks belong to
+    world: Option<WorldGenerator>
+}
+
+/// Client only: records the revisions the server sends and puts together the edited chunks it
+/// streams, then asks for the edited chunks the loading and meshing systems are waiting on
+pub fn client_chunk_transfer_system(
+    mut state: ResMut<ClientChunkTransferState>,
+    mut listener_state: ResMut<NetworkEventListenerState>,
+    chunk_transfer_events: Res<Events<ChunkTransferEvent>>,
+    ci: Res<ConnectionInfo>,
+    net: Res<NetworkResource>,
+    mut cache: ResMut<ChunkCache>,
+    world_generator: Res<WorldGenerator>
+) {
+    // We joined a world or the server reloaded its terrain graph. Revisions come after the world
+    // they're for, so everything from here on belongs to the new one.
+    if !state.world.as_ref().map_or(false, |world| world.is_same_world(&world_generator)) {
+        cache.clear();
+        state.world = Some(world_generator.clone());
+    }
+
+    for event in listener_state.chunk_transfer_events.iter(&chunk_transfer_events) {
+        match event {
+            ChunkTransferEvent::Revisions(revisions) => {
+                for (position, revision) in revisions.iter() {
+                    cache.set_revision(*position, *revision);
+                }
+            },
+            ChunkTransferEvent::Fragment(fragment) => cache.receive(fragment.clone()),
+            ChunkTransferEvent::Requested { .. } => {}
+        }
+    }
+
+    for positions in cache.take_wanted().chunks(CHUNKS_PER_REQUEST) {
+        net.send(
+            *ci.server_addr(),
+            &bincode::serialize(&NetMessage::ChunkRequest(positions.to_vec())).unwrap(),
+            NetworkDelivery::ReliableOrdered(Some(3))
+        );
+    }
+}
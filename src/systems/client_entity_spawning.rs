@@ -1,21 +1,85 @@
 use bevy::prelude::*;
 use bevy_rapier3d::physics::{RigidBodyHandleComponent};
+use bevy_rapier3d::rapier::dynamics::{BodyStatus, RigidBodyBuilder};
+use bevy_rapier3d::rapier::geometry::{ColliderBuilder, InteractionGroups};
 use crate::components::*;
 use crate::events::*;
 use crate::models::*;
 use crate::resources::*;
+use crate::systems::PLAYER_COLLISION_GROUP;
 
 pub fn client_entity_spawning_system(
     commands: &mut Commands,
     mut state: ResMut<NetworkEventListenerState>,
     entity_spawn_events: Res<Events<EntitySpawnEvent>>,
-    mut query: Query<(Entity, &LocalPlayer, &mut Synchronized<RigidBodyHandleComponent>)>
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    local_player_query: Query<Entity, (With<LocalPlayer>, Without<ServerEntity>)>
 ) {
     for event in state.entity_spawn_events.iter(&entity_spawn_events) {
         let server_entity_id = event.spawn.entity_id;
-        println!("Entity spawned: {:?}", server_entity_id);
-        commands.spawn((Synchronize, ServerEntity {
-            id: server_entity_id
-        }));
+        println!("Entity spawned: {:?} ({:?})", server_entity_id, event.spawn.archetype);
+
+        // The entity we control already exists locally, link it to the server's copy
+        if event.spawn.controlled {
+            for entity in local_player_query.iter() {
+                commands.insert(entity, (Synchronize, ServerEntity {
+                    id: server_entity_id
+                }));
+
+                if let EntityArchetype::Player(profile) = &event.spawn.archetype {
+                    commands.insert(entity, (Player::new(profile.clone()), Synchronized::<Player>::default()));
+                }
+            }
+
+            continue;
+        }
+
+        match &event.spawn.archetype {
+            EntityArchetype::Player(profile) => {
+                println!("Player {} joined", profile.name);
+
+                let [half_x, half_y, half_z] = CharacterControllerSettings::default().half_extents;
+                let [x, y, z] = PLAYER_SPAWN_POINT;
+                let [red, green, blue] = profile.color;
+
+                commands
+                    .spawn((
+                        Synchronize,
+                        ServerEntity {
+                            id: server_entity_id
+                        },
+                        Player::new(profile.clone()),
+                        Synchronized::<Player>::default(),
+                        Synchronized::<RigidBodyHandleComponent>::default(),
+                        Transform::default(),
+                        GlobalTransform::default()
+                    ))
+                    .with_children(|player| {
+                        player
+                            .spawn(PbrBundle {
+                                mesh: meshes.add(Mesh::from(shape::Box::new(half_x * 2.0, half_y * 2.0, half_z * 2.0))),
+                                material: materials.add(Color::rgb(red, green, blue).into()),
+                                ..Default::default()
+                            })
+                            .with(PlayerBody)
+                            .spawn(PbrBundle {
+                                mesh: meshes.add(Mesh::from(shape::Cube { size: 0.5 })),
+                                material: materials.add(Color::rgb(red, green, blue).into()),
+                                transform: Transform::from_translation(Vec3::new(0.0, PLAYER_HEAD_HEIGHT, 0.0)),
+                                ..Default::default()
+                            })
+                            .with(PlayerHead);
+                    })
+                    .with(RigidBodyBuilder::new(BodyStatus::Kinematic).translation(x, y, z))
+                    .with(ColliderBuilder::cuboid(half_x, half_y, half_z)
+                        .collision_groups(InteractionGroups::new(PLAYER_COLLISION_GROUP, u16::MAX)));
+            },
+            EntityArchetype::None => {
+                commands.spawn((Synchronize, ServerEntity {
+                    id: server_entity_id
+                }));
+            }
+        }
     }
 }
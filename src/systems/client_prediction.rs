@@ -22,7 +22,11 @@ pub fn client_prediction_system<TComponent: Synchronizable>(
         if let Some(command_frame) = command_frame {
             let frame: u32 = command_frame.frame;
 
-            let command_frame_message = NetMessage::CommandFrame(*command_frame);
+            // The server knows this entity by its own id
+            let command_frame_message = NetMessage::CommandFrame(CommandFrame {
+                entity_id: server_entity.id,
+                ..*command_frame
+            });
 
             let bytes: Vec<u8> = bincode::serialize(&command_frame_message).unwrap();
             println!("Entity {} sending message for sim frame {}. Data: {:?}", server_entity.id, frame, &bytes);
@@ -1,4 +1,9 @@
 use bevy::{
+    input::{
+        Axis,
+        gamepad::{Gamepad, GamepadAxis, GamepadButton},
+        mouse::MouseMotion
+    },
     prelude::*,
 };
 use crate::models::*;
@@ -6,30 +11,61 @@ use crate::resources::*;
 
 #[derive(Default)]
 pub struct CommandAccumulatorState {
-    pub input_buffer: InputCommandBuffer
+    pub input_buffer: InputCommandBuffer,
+    pub mouse_motion_event_reader: EventReader<MouseMotion>
 }
 
+/// Bindings on gamepads are read from the first connected gamepad
+const GAMEPAD: Gamepad = Gamepad(0);
+
 /// This system accumulates input events as they come in
 pub fn command_accumulator_system(
     mut state: ResMut<CommandAccumulatorState>,
     sim_time: Res<SimulationTime>,
-    input: Res<Input<KeyCode>>
+    input_map: Res<InputMap>,
+    keys: Res<Input<KeyCode>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    mouse_motion_events: Res<Events<MouseMotion>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>
 ) {
-    let mut frame_command = {
-        if state.input_buffer.inputs.is_empty() {
-            InputCommand::default()
-        } else {
-            *state.input_buffer.inputs.back().unwrap()
+    let mut mouse_motion = Vec2::zero();
+    for event in state.mouse_motion_event_reader.iter(&mouse_motion_events) {
+        mouse_motion += event.delta;
+    }
+
+    let binding_value = |binding: &InputBinding| -> f32 {
+        match *binding {
+            InputBinding::Key(key) => if keys.pressed(key) { 1.0 } else { 0.0 },
+            InputBinding::MouseButton(button) => if mouse_buttons.pressed(button) { 1.0 } else { 0.0 },
+            InputBinding::MouseMotionX(scale) => mouse_motion.x * scale,
+            InputBinding::MouseMotionY(scale) => mouse_motion.y * scale,
+            InputBinding::GamepadButton(button_type) => {
+                if gamepad_buttons.pressed(GamepadButton(GAMEPAD, button_type)) { 1.0 } else { 0.0 }
+            },
+            InputBinding::GamepadAxis(axis_type, scale) => {
+                gamepad_axes.get(GamepadAxis(GAMEPAD, axis_type)).unwrap_or(0.0) * scale
+            }
         }
     };
 
+    let mut frame_command = InputCommand::default();
     frame_command.frame = sim_time.frame();
 
-    frame_command.forward = input.pressed(KeyCode::W);
-    frame_command.backward = input.pressed(KeyCode::S);
-    frame_command.right = input.pressed(KeyCode::D);
-    frame_command.left = input.pressed(KeyCode::A);
-    frame_command.jump = input.pressed(KeyCode::Space);
+    for action in InputAction::ALL.iter() {
+        let bindings = input_map.bindings(*action);
+
+        match action.kind() {
+            InputActionKind::Button(_) => {
+                let pressed = bindings.iter().any(|binding| binding_value(binding) > 0.5);
+                frame_command.set_pressed(*action, pressed);
+            },
+            InputActionKind::Axis(_) => {
+                let value = bindings.iter().map(|binding| binding_value(binding)).sum();
+                frame_command.set_axis(*action, value);
+            }
+        }
+    }
 
     state.input_buffer.inputs.push_back(frame_command);
 }
@@ -1,76 +1,72 @@
-use bevy::{
-    input::mouse::{MouseButtonInput, MouseMotion},
-    prelude::*,
-    window::CursorMoved,
-};
+use bevy::prelude::*;
 use crate::components::*;
+use crate::models::*;
+use crate::systems::CommandAccumulatorState;
 use crate::utilities::*;
 
 #[derive(Default)]
 pub struct LocalPlayerCameraState {
-    mouse_button_event_reader: EventReader<MouseButtonInput>,
-    mouse_motion_event_reader: EventReader<MouseMotion>,
-    cursor_moved_event_reader: EventReader<CursorMoved>,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub cursor_grabbed: bool
 }
 
-const MOUSE_SENSITIVITY: f32 = 1.0;
 const MAX_PITCH_ANGLE: f32 = 89.0;
 
-/// This system prints out all mouse events as they come in
+/// This system turns the local player with the look actions and stamps the resulting view
+/// orientation onto the latest input command
 pub fn local_player_camera_system(
     mut state: ResMut<LocalPlayerCameraState>,
-    time: Res<Time>,
-    mouse_motion_events: Res<Events<MouseMotion>>,
+    mut windows: ResMut<Windows>,
+    keys: Res<Input<KeyCode>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    mut command_accumulator: ResMut<CommandAccumulatorState>,
     mut player_head_query: Query<(&LocalPlayerHead, &mut Transform)>,
-    mut player_body_query: Query<(&LocalPlayerBody, &mut Transform)>,
 ) {
-    // let motion = {
-    //     let mut m_motion_x = 0.0;
-    //     let mut m_motion_y = 0.0;
+    let grab = if !state.cursor_grabbed && mouse_buttons.just_pressed(MouseButton::Left) {
+        Some(true)
+    } else if state.cursor_grabbed && keys.just_pressed(KeyCode::Escape) {
+        Some(false)
+    } else {
+        None
+    };
 
-    //     for event in state.mouse_motion_event_reader.iter(&mouse_motion_events) {
-    //         m_motion_x = event.delta.y() * -1.0;
-    //         m_motion_y = event.delta.x() * -1.0;
-    //     }
+    if let Some(grab) = grab {
+        if let Some(window) = windows.get_primary_mut() {
+            window.set_cursor_lock_mode(grab);
+            window.set_cursor_visibility(!grab);
+        }
+        state.cursor_grabbed = grab;
+    }
 
-    //     (
-    //         m_motion_x * MOUSE_SENSITIVITY,
-    //         m_motion_y * MOUSE_SENSITIVITY
-    //     )
-    // };
+    let command = match command_accumulator.input_buffer.inputs.back_mut() {
+        Some(command) => command,
+        None => return
+    };
 
-    // for (_player_body, mut rotation) in &mut player_body_query.iter() {
-    //     let delta_rotation_yaw = Quat::from_axis_angle(Vec3::unit_y(), motion.1 * time.delta_seconds);
+    // Pitch, yaw
+    let motion = if state.cursor_grabbed {
+        (command.axis(InputAction::LookPitch), command.axis(InputAction::LookYaw))
+    } else {
+        (0.0, 0.0)
+    };
 
-    //     rotation.0 = rotation.0 * delta_rotation_yaw;
-    // }
+    state.yaw = (state.yaw + motion.1) % std::f32::consts::TAU;
 
-    // for (_player_head, mut rotation) in &mut player_head_query.iter() {
-    //     let pitch_clamper = {   
-    //         let angles = rotation.0.ypr();
-    //         let mut pitch_deg = angles.0.to_degrees();
+    let max_pitch = MAX_PITCH_ANGLE.to_radians();
+    state.pitch = (state.pitch + motion.0).max(-max_pitch).min(max_pitch);
 
-    //         if angles.0.abs() > std::f32::consts::FRAC_PI_2 {
-    //             // Invert the pitch
-    //             if pitch_deg < 0.0 {
-    //                 pitch_deg = pitch_deg + 180.0;
-    //             } else {
-    //                 pitch_deg = pitch_deg - 180.0;
-    //             }
-    //         }
-    //         if pitch_deg > MAX_PITCH_ANGLE || pitch_deg < -MAX_PITCH_ANGLE {
-    //             if pitch_deg.signum() != motion.0.signum() {
-    //                 1.0
-    //             } else {
-    //                 0.0
-    //             }
-    //         } else {
-    //             1.0
-    //         }
-    //     };
+    for (_player_head, mut transform) in player_head_query.iter_mut() {
+        // Clamp against the head's actual orientation, so anything else rotating it can't push
+        // the view past straight up or down
+        let rotation = transform.rotation * Quat::from_rotation_x(motion.0);
+        let pitch = -rotation.ypr().0;
 
-    //     let delta_rotation_pitch = Quat::from_axis_angle(Vec3::unit_x(), motion.0 * pitch_clamper * time.delta_seconds);
+        state.pitch = pitch.max(-max_pitch).min(max_pitch);
+        transform.rotation = Quat::from_rotation_x(state.pitch);
+    }
 
-    //     rotation.0 = rotation.0 * delta_rotation_pitch;
-    // }
+    // Body yaw is applied by the movement simulation, so the server turns the player identically
+    command.yaw = state.yaw;
+    command.pitch = state.pitch;
 }
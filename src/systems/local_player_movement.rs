@@ -14,18 +14,17 @@ pub struct LocalPlayerMovementState {
 }
 
 fn predict(local_player_movement: &ResMut<LocalPlayerMovementState>, sim_time: &SimulationTime, entity: Entity, input_command: &InputCommand, rigid_body: &mut RigidBody, synchronizable_rigid_body: &mut Synchronized<RigidBodyHandleComponent>) {
-    synchronizable_rigid_body.command_frames().push(
-        entity.id(),
-        sim_time.frame(),
-        SynchronizedInput::InputCommand(*input_command)
-    );
+    synchronizable_rigid_body.command_frames().push(CommandFrame {
+        entity_id: entity.id(),
+        frame: sim_time.frame(),
+        input: SynchronizedInput::InputCommand(*input_command)
+    });
 }
 
 pub fn local_player_movement_system(
     mut state: ResMut<LocalPlayerMovementState>,
     sim_time: Res<SimulationTime>,
     command_accumulator: Res<CommandAccumulatorState>,
-    input: Res<Input<KeyCode>>,
     mut rigid_body_set: ResMut<RigidBodySet>,
     mut player_body_query: Query<(Entity, &LocalPlayerBody, &RigidBodyHandleComponent, &mut Synchronized<RigidBodyHandleComponent>)>
 ) {
@@ -19,7 +19,14 @@ pub fn network_message_listener_system/*<TComponent: 'static + Send + Sync>*/(
     mut command_frame_events: ResMut<Events<CommandFrameEvent>>,
     mut state_frame_events: ResMut<Events<StateFrameEvent>>,
     mut entity_spawn_events: ResMut<Events<EntitySpawnEvent>>,
-    mut clients: ResMut<Clients>
+    mut voxel_edit_request_events: ResMut<Events<VoxelEditRequestEvent>>,
+    mut voxel_edit_events: ResMut<Events<VoxelEditEvent>>,
+    mut voxel_edit_rejected_events: ResMut<Events<VoxelEditRejectedEvent>>,
+    mut chunk_transfer_events: ResMut<Events<ChunkTransferEvent>>,
+    mut clients: ResMut<Clients>,
+    mut recorder: ResMut<ReplayRecorder>,
+    mut world_generator: ResMut<WorldGenerator>,
+    sim_time: Res<SimulationTime>
 ) {
     for event in state.network_events.iter(&network_events) {
         println!("Received a NetworkEvent: {:?}", event);
@@ -48,7 +55,10 @@ pub fn network_message_listener_system/*<TComponent: 'static + Send + Sync>*/(
                         token,
                         *conn,
                         &net,
+                        &sim_time,
+                        &world_generator,
                         &mut clients,
+                        &mut recorder,
                         commands
                     ),
                     NetMessage::CommandFrame(command_frame) => handle_command_frame_event(
@@ -56,7 +66,8 @@ pub fn network_message_listener_system/*<TComponent: 'static + Send + Sync>*/(
                         *conn,
                         &ci,
                         &mut command_frame_events,
-                        &mut clients
+                        &mut clients,
+                        &mut recorder
                     ),
                     NetMessage::AuthoritativeStateFrame(state_frame) => handle_state_frame_event(
                         state_frame,
@@ -70,19 +81,73 @@ pub fn network_message_listener_system/*<TComponent: 'static + Send + Sync>*/(
                         &ci,
                         &mut entity_spawn_events
                     ),
+                    NetMessage::WorldInfo(world_info) => handle_world_info(
+                        world_info,
+                        &ci,
+                        &mut world_generator
+                    ),
+                    NetMessage::VoxelEditRequest(request) => handle_voxel_edit_request(
+                        request,
+                        *conn,
+                        &ci,
+                        &clients,
+                        &mut voxel_edit_request_events
+                    ),
+                    NetMessage::VoxelEdit(edit) => {
+                        // Only the server's edits are applied
+                        if ci.is_client() {
+                            voxel_edit_events.send(VoxelEditEvent {
+                                edit
+                            });
+                        }
+                    },
+                    NetMessage::VoxelEditRejected(id, reason) => {
+                        if ci.is_client() {
+                            voxel_edit_rejected_events.send(VoxelEditRejectedEvent {
+                                id,
+                                reason
+                            });
+                        }
+                    },
+                    NetMessage::ChunkRequest(positions) => {
+                        // Only the server has the voxels of edited chunks
+                        if ci.is_server() {
+                            if let Some(client_id) = clients.get_client_id(*conn) {
+                                chunk_transfer_events.send(ChunkTransferEvent::Requested {
+                                    from: *client_id,
+                                    positions
+                                });
+                            }
+                        }
+                    },
+                    NetMessage::ChunkRevisions(revisions) => {
+                        if ci.is_client() {
+                            chunk_transfer_events.send(ChunkTransferEvent::Revisions(revisions));
+                        }
+                    },
+                    NetMessage::ChunkFragment(fragment) => {
+                        if ci.is_client() {
+                            chunk_transfer_events.send(ChunkTransferEvent::Fragment(fragment));
+                        }
+                    },
                     _ => {}
                 }
             },
             _ => {}
         }
     }
+
+    recorder.flush();
 }
 
 fn handle_authorization(
     token: String,
     conn: Connection,
     net: &Res<NetworkResource>,
+    sim_time: &Res<SimulationTime>,
+    world_generator: &ResMut<WorldGenerator>,
     clients: &mut ResMut<Clients>,
+    recorder: &mut ResMut<ReplayRecorder>,
     mut commands: &mut Commands
 ) {
     // TODO: check auth token
@@ -92,7 +157,20 @@ fn handle_authorization(
 
     clients.add(conn, Client::new(user_device_id, conn));
 
-    commands.spawn((Synchronize, LocalPlayer, LocalPlayerBody));
+    // The client generates terrain from the same seed and graph so it matches ours
+    net.send(
+        conn.addr,
+        &bincode::serialize(&NetMessage::WorldInfo(world_generator.world_info())).unwrap(),
+        NetworkDelivery::ReliableOrdered(Some(3))
+    );
+
+    let entity = spawn_server_player(commands, user_device_id);
+
+    recorder.record(ReplayEvent::ClientAuthorized {
+        frame: sim_time.frame(),
+        client_id: user_device_id,
+        entity_id: entity.id()
+    });
 }
 
 fn handle_command_frame_event(
@@ -100,7 +178,8 @@ fn handle_command_frame_event(
     conn: Connection,
     ci: &Res<ConnectionInfo>,
     command_frame_events: &mut ResMut<Events<CommandFrameEvent>>,
-    clients: &mut ResMut<Clients>
+    clients: &mut ResMut<Clients>,
+    recorder: &mut ResMut<ReplayRecorder>
 ) {
     // Only handle command frames on the server
     if !ci.is_server() {
@@ -110,6 +189,11 @@ fn handle_command_frame_event(
     println!("{:?}", command_frame.input);
 
     if let Some(client_id) = clients.get_client_id(conn) {
+        recorder.record(ReplayEvent::CommandFrame {
+            from: *client_id,
+            command_frame
+        });
+
         command_frame_events.send(CommandFrameEvent {
             from: *client_id,
             command_frame
@@ -149,4 +233,48 @@ fn handle_entity_spawn_event(
     entity_spawn_events.send(EntitySpawnEvent {
         spawn
     });
-}
\ No newline at end of file
+}
+
+fn handle_world_info(
+    world_info: WorldInfo,
+    ci: &Res<ConnectionInfo>,
+    world_generator: &mut ResMut<WorldGenerator>
+) {
+    // Only the server decides which world is played
+    if !ci.is_client() {
+        return;
+    }
+
+    println!("Joined world with seed {}, meshed with {}", world_info.seed, world_info.meshing_method);
+
+    // Terrain from any other graph or caves wouldn't match the server's, so ours are replaced
+    let joined = if world_generator.graph() == &world_info.graph && world_generator.caves().settings() == &world_info.caves {
+        world_generator.with_seed(world_info.seed)
+    } else {
+        println!("Using the server's terrain graph and caves, ours differ");
+        WorldGenerator::with_graph(world_info.seed, world_info.graph, world_info.caves)
+            .expect("The server's terrain graph is invalid")
+    };
+
+    **world_generator = joined.with_meshing_method(world_info.meshing_method);
+}
+
+fn handle_voxel_edit_request(
+    request: VoxelEditRequest,
+    conn: Connection,
+    ci: &Res<ConnectionInfo>,
+    clients: &ResMut<Clients>,
+    voxel_edit_request_events: &mut ResMut<Events<VoxelEditRequestEvent>>
+) {
+    // Only the server decides which edits happen
+    if !ci.is_server() {
+        return;
+    }
+
+    if let Some(client_id) = clients.get_client_id(conn) {
+        voxel_edit_request_events.send(VoxelEditRequestEvent {
+            from: *client_id,
+            request
+        });
+    }
+}
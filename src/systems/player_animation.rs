@@ -0,0 +1,27 @@
+use bevy::prelude::*;
+use crate::components::*;
+use crate::models::*;
+
+/// Horizontal speed below which a grounded player counts as standing still
+const WALKING_SPEED: f32 = 0.1;
+
+/// Server only: derives each player's animation from how their character is moving
+pub fn player_animation_system(mut query: Query<(&CharacterController, &mut Player)>) {
+    for (controller, mut player) in query.iter_mut() {
+        let velocity = controller.velocity;
+        let horizontal_speed = Vec3::new(velocity.x, 0.0, velocity.z).length();
+
+        let animation = if controller.grounded {
+            if horizontal_speed > WALKING_SPEED { PlayerAnimation::Walking } else { PlayerAnimation::Idle }
+        } else if velocity.y > 0.0 {
+            PlayerAnimation::Jumping
+        } else {
+            PlayerAnimation::Falling
+        };
+
+        // Only flag the player as changed when the animation actually switched
+        if player.animation != animation {
+            player.animation = animation;
+        }
+    }
+}
@@ -2,58 +2,145 @@ use bevy::{
     prelude::*,
 };
 use bevy_rapier3d::rapier::dynamics::{RigidBody, RigidBodySet};
-use bevy_rapier3d::rapier::math::{Vector};
+use bevy_rapier3d::rapier::geometry::{ColliderSet, Cuboid, InteractionGroups};
+use bevy_rapier3d::rapier::math::{Isometry, Vector};
+use bevy_rapier3d::rapier::na::UnitQuaternion;
+use bevy_rapier3d::rapier::ncollide::bounding_volume::{self, BoundingVolume};
+use bevy_rapier3d::rapier::ncollide::query::{time_of_impact_support_map_support_map, TOI};
+use bevy_rapier3d::rapier::ncollide::shape::SupportMap;
 use bevy_rapier3d::physics::{RigidBodyHandleComponent};
 use crate::components::*;
 use crate::models::*;
 use crate::resources::*;
-use crate::systems::CommandAccumulatorState;
 
-#[derive(Default)]
-pub struct PlayerMovementState {
+/// Collision group player bodies belong to, so character sweeps ignore them
+pub const PLAYER_COLLISION_GROUP: u16 = 0b10;
+
+/// Sweeps boxes against rapier colliders. The query pipeline of this rapier version can only cast
+/// rays, so every collider whose bounds overlap the sweep is tested against the box directly.
+struct RapierCollisionWorld<'a> {
+    colliders: &'a ColliderSet
 }
 
-fn move_player(local_player_movement: &ResMut<PlayerMovementState>, sim_time: &SimulationTime, entity: Entity, rigid_body: &mut RigidBody, synchronized_rigid_body: &mut Synchronized<RigidBodyHandleComponent>) {
-    let mut command_frames = synchronized_rigid_body.command_frames();
-    let command_frame = command_frames.history_iter(3).next();
-    
-    if let Some(command_frame) = command_frame {
-        if let SynchronizedInput::InputCommand(input_command) = command_frame.input {
-            println!("Command: {:?}", &input_command);
+impl<'a> CharacterCollisionWorld for RapierCollisionWorld<'a> {
+    fn cast_box(&self, center: Vec3, half_extents: Vec3, direction: Vec3, max_distance: f32) -> Option<CharacterHit> {
+        let groups = InteractionGroups::new(u16::MAX, !PLAYER_COLLISION_GROUP);
+        let shape = Cuboid::new(Vector::new(half_extents.x, half_extents.y, half_extents.z));
+        let position = Isometry::translation(center.x, center.y, center.z);
+        let velocity = Vector::new(direction.x, direction.y, direction.z);
 
-            if input_command.jump {
-                rigid_body.apply_impulse(Vector::new(0.0, 10.0, 0.0), false);
-            }
-            
-            if input_command.left {
-                rigid_body.apply_impulse(Vector::new(-2.0, 0.0, 0.0), false);
-            }
+        let end = center + direction * max_distance;
+        let swept = bounding_volume::aabb(&shape, &position)
+            .merged(&bounding_volume::aabb(&shape, &Isometry::translation(end.x, end.y, end.z)));
 
-            if input_command.right {
-                rigid_body.apply_impulse(Vector::new(2.0, 0.0, 0.0), false);
+        let mut closest: Option<TOI<f32>> = None;
+        let mut keep_closest = |toi: Option<TOI<f32>>| {
+            if let Some(toi) = toi {
+                if closest.as_ref().map_or(true, |closest| toi.toi < closest.toi) {
+                    closest = Some(toi);
+                }
             }
+        };
 
-            if input_command.forward {
-                rigid_body.apply_impulse(Vector::new(0.0, 0.0, -2.0), false);
+        for (_, collider) in self.colliders.iter() {
+            if !collider.collision_groups().test(groups) || !collider.compute_aabb().intersects(&swept) {
+                continue;
             }
 
-            if input_command.backward {
-                rigid_body.apply_impulse(Vector::new(0.0, 0.0, 2.0), false);
+            let collider_position = collider.position();
+
+            if let Some(cuboid) = collider.shape().as_cuboid() {
+                keep_closest(cast_cuboid(&position, &velocity, &shape, collider_position, cuboid, max_distance));
+            } else if let Some(trimesh) = collider.shape().as_trimesh() {
+                // Only the triangles near the sweep are worth casting against
+                let local_swept = swept.transform_by(&collider_position.inverse());
+
+                for triangle in trimesh.triangles() {
+                    if bounding_volume::local_aabb(&triangle).intersects(&local_swept) {
+                        keep_closest(cast_cuboid(&position, &velocity, &shape, collider_position, &triangle, max_distance));
+                    }
+                }
             }
+        }
+
+        // The box's normal points into whatever it hit
+        closest.map(|toi| CharacterHit {
+            distance: toi.toi,
+            normal: -Vec3::new(toi.normal1.x, toi.normal1.y, toi.normal1.z)
+        })
+    }
+}
+
+fn cast_cuboid(
+    position: &Isometry<f32>,
+    velocity: &Vector<f32>,
+    shape: &Cuboid,
+    target_position: &Isometry<f32>,
+    target: &impl SupportMap<f32>,
+    max_distance: f32
+) -> Option<TOI<f32>> {
+    time_of_impact_support_map_support_map(position, velocity, shape, target_position, &Vector::zeros(), target, max_distance, 0.0)
+}
 
-            rigid_body.wake_up(true);
+/// Runs the character controller once for every command frame it hasn't simulated yet
+fn move_player(sim_time: &SimulationTime, world: &RapierCollisionWorld, rigid_body: &mut RigidBody, controller: &mut CharacterController, synchronized_rigid_body: &mut Synchronized<RigidBodyHandleComponent>) {
+    let command_frames = synchronized_rigid_body.command_frames();
+
+    let latest_frame = match command_frames.latest_frame() {
+        Some(latest_frame) => latest_frame,
+        None => return
+    };
+
+    let first_frame = match controller.last_frame {
+        Some(last_frame) if frame_offset(last_frame, latest_frame) > 0 => last_frame.wrapping_add(1),
+        Some(_) => return,
+        None => latest_frame
+    };
+
+    // Frames older than the buffer can't be simulated any more
+    let first_frame = match command_frames.earliest_frame() {
+        Some(earliest_frame) if frame_offset(first_frame, earliest_frame) > 0 => earliest_frame,
+        _ => first_frame
+    };
+
+    let translation = rigid_body.position().translation.vector;
+    let mut position = Vec3::new(translation.x, translation.y, translation.z);
+    let mut rotation = rigid_body.position().rotation;
+
+    for offset in 0..=frame_offset(first_frame, latest_frame) as u32 {
+        // Frames lost in transit repeat the last input, the client most likely kept holding it
+        if let Some(SynchronizedInput::InputCommand(input_command)) = command_frames.get(first_frame.wrapping_add(offset)).map(|command_frame| command_frame.input) {
+            controller.last_input = Some(input_command);
+        }
+
+        if let Some(input_command) = controller.last_input {
+            position = controller.step(position, &input_command, sim_time.fixed_delta_seconds(), world);
+
+            // Face where the player is looking
+            rotation = UnitQuaternion::from_axis_angle(&Vector::y_axis(), input_command.yaw);
         }
     }
+
+    controller.last_frame = Some(latest_frame);
+
+    rigid_body.set_next_kinematic_position(Isometry::from_parts(
+        Vector::new(position.x, position.y, position.z).into(),
+        rotation
+    ));
 }
 
 pub fn player_movement_system(
-    mut state: ResMut<PlayerMovementState>,
     sim_time: Res<SimulationTime>,
+    colliders: Res<ColliderSet>,
     mut rigid_body_set: ResMut<RigidBodySet>,
-    mut player_body_query: Query<(Entity, &LocalPlayerBody, &RigidBodyHandleComponent, &mut Synchronized<RigidBodyHandleComponent>)>
+    mut player_body_query: Query<(&RigidBodyHandleComponent, &mut CharacterController, &mut Synchronized<RigidBodyHandleComponent>)>
 ) {
-    for (entity, _player_body, rigid_body_handle, mut synchronized_rigid_body) in player_body_query.iter_mut() {
-        let mut rigid_body = rigid_body_set.get_mut(rigid_body_handle.handle()).unwrap();
-        move_player(&state, &sim_time, entity, rigid_body, &mut synchronized_rigid_body);
+    let world = RapierCollisionWorld {
+        colliders: &colliders
+    };
+
+    for (rigid_body_handle, mut controller, mut synchronized_rigid_body) in player_body_query.iter_mut() {
+        let rigid_body = rigid_body_set.get_mut(rigid_body_handle.handle()).unwrap();
+        move_player(&sim_time, &world, rigid_body, &mut controller, &mut synchronized_rigid_body);
     }
 }
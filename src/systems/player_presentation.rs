@@ -0,0 +1,38 @@
+use bevy::prelude::*;
+use crate::components::*;
+use crate::models::*;
+
+const WALK_BOB_FREQUENCY: f32 = 10.0;
+const WALK_BOB_HEIGHT: f32 = 0.05;
+const AIRBORNE_LEAN: f32 = 0.15;
+
+/// Poses the heads and bodies of other players from their replicated state
+pub fn player_presentation_system(
+    time: Res<Time>,
+    player_query: Query<(&Player, &Children), Without<LocalPlayer>>,
+    mut part_query: Query<(&mut Transform, Option<&PlayerHead>, Option<&PlayerBody>)>
+) {
+    let seconds = time.seconds_since_startup() as f32;
+
+    for (player, children) in player_query.iter() {
+        for child in children.iter() {
+            if let Ok((mut transform, head, body)) = part_query.get_mut(*child) {
+                if head.is_some() {
+                    transform.rotation = Quat::from_rotation_x(player.look_direction.pitch);
+                }
+
+                if body.is_some() {
+                    let (height, lean) = match player.animation {
+                        PlayerAnimation::Idle => (0.0, 0.0),
+                        PlayerAnimation::Walking => ((seconds * WALK_BOB_FREQUENCY).sin().abs() * WALK_BOB_HEIGHT, 0.0),
+                        PlayerAnimation::Jumping => (0.0, -AIRBORNE_LEAN),
+                        PlayerAnimation::Falling => (0.0, AIRBORNE_LEAN)
+                    };
+
+                    transform.translation = Vec3::new(0.0, height, 0.0);
+                    transform.rotation = Quat::from_rotation_x(lean);
+                }
+            }
+        }
+    }
+}
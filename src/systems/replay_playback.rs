@@ -0,0 +1,109 @@
+use bevy::prelude::*;
+use crate::components::*;
+use crate::events::*;
+use crate::models::*;
+use crate::resources::*;
+
+#[derive(Default)]
+pub struct ReplayPlaybackState {
+    last_sim_frame: Option<u32>
+}
+
+/// Feeds recorded command frames back into the simulation, one recorded frame per simulation tick.
+/// The recorded world and edits are played too, so players collide with the same terrain they did.
+pub fn replay_playback_system(
+    commands: &mut Commands,
+    mut state: Local<ReplayPlaybackState>,
+    mut player: ResMut<ReplayPlayer>,
+    mut sim_time: ResMut<SimulationTime>,
+    mut command_frame_events: ResMut<Events<CommandFrameEvent>>,
+    mut world_generator: ResMut<WorldGenerator>,
+    mut log: ResMut<VoxelEditLog>,
+    mut voxel_world: ResMut<VoxelWorld>
+) {
+    // The terrain as it was at the frame sought to
+    if let Some(world_info) = player.take_world_info() {
+        replay_world_info(&mut world_generator, world_info);
+    }
+
+    for brush in player.take_edits() {
+        replay_voxel_edit(&mut log, &mut voxel_world, brush);
+    }
+
+    // Players authorized before a seek are spawned first, their state is restored once they exist
+    for event in player.take_authorizations() {
+        replay_authorization(commands, &mut player, &event);
+    }
+
+    sim_time.adjust_speed(player.tick_rate());
+
+    if player.is_restoring() || player.is_paused() || player.is_finished() || state.last_sim_frame == Some(sim_time.frame()) {
+        return;
+    }
+
+    state.last_sim_frame = Some(sim_time.frame());
+
+    let events = player.advance().to_vec();
+    for event in events.iter() {
+        match event {
+            ReplayEvent::ClientAuthorized { .. } => replay_authorization(commands, &mut player, event),
+            ReplayEvent::CommandFrame { from, command_frame } => {
+                // Recorded commands refer to the recorded entity, not the one spawned for it here
+                let entity_id = player.entity(command_frame.entity_id)
+                    .map_or(command_frame.entity_id, |entity| entity.id());
+
+                command_frame_events.send(CommandFrameEvent {
+                    from: *from,
+                    command_frame: CommandFrame {
+                        entity_id,
+                        ..*command_frame
+                    }
+                });
+            },
+            ReplayEvent::WorldInfo { world_info, .. } => replay_world_info(&mut world_generator, world_info.clone()),
+            ReplayEvent::VoxelEdit { brush, .. } => replay_voxel_edit(&mut log, &mut voxel_world, *brush),
+            ReplayEvent::StateFrame(_) => {}
+        }
+    }
+}
+
+fn replay_world_info(world_generator: &mut ResMut<WorldGenerator>, world_info: WorldInfo) {
+    println!("Replaying world with seed {}, meshed with {}", world_info.seed, world_info.meshing_method);
+
+    **world_generator = WorldGenerator::with_graph(world_info.seed, world_info.graph, world_info.caves)
+        .expect("The recorded terrain graph is invalid")
+        .with_meshing_method(world_info.meshing_method);
+}
+
+/// Edits are accepted again in the order they were recorded, so chunks built for collision later
+/// have them too
+fn replay_voxel_edit(log: &mut ResMut<VoxelEditLog>, voxel_world: &mut ResMut<VoxelWorld>, brush: Brush) {
+    voxel_world.apply_brush(&brush);
+    log.accept(brush);
+}
+
+fn replay_authorization(commands: &mut Commands, player: &mut ResMut<ReplayPlayer>, event: &ReplayEvent) {
+    if let ReplayEvent::ClientAuthorized { client_id, entity_id, .. } = event {
+        println!("Replaying authorization of client {}", client_id);
+        let entity = spawn_server_player(commands, *client_id);
+        player.map_entity(*entity_id, entity);
+    }
+}
+
+/// Applies the state queued by a replay seek once the entities it belongs to have been spawned
+pub fn replay_restoration_system<TComponent: Synchronizable>(
+    commands: &mut Commands,
+    mut player: ResMut<ReplayPlayer>,
+    restorable_query: Query<(&TComponent, &Synchronized<TComponent>)>
+) {
+    for state_frame in player.take_snapshots(TComponent::type_id()) {
+        match player.entity(state_frame.entity_id) {
+            Some(entity) if restorable_query.get(entity).is_ok() => {
+                commands.add_command(Synchronized::<TComponent>::consume_state_command(entity, state_frame));
+            },
+            // Some components, like rigid bodies, only appear a frame after the entity is spawned
+            Some(_) => player.defer_snapshot(state_frame),
+            None => println!("Dropping recorded state of entity {}, it was never spawned", state_frame.entity_id)
+        }
+    }
+}
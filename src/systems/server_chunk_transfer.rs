@@ -0,0 +1,240 @@
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use bevy::{
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task}
+};
+use bevy_prototype_networking_laminar::{NetworkResource, NetworkDelivery};
+use futures_lite::future;
+use crate::components::*;
+use crate::events::*;
+use crate::models::*;
+use crate::resources::*;
+
+/// The most chunk revisions sent in one message
+const REVISIONS_PER_MESSAGE: usize = 48;
+
+/// Server only
+#[derive(Default)]
+pub struct ServerChunkTransferState {
+    /// The chunk each client's player was last in, and the chunks around it the client has been
+    /// told the revision of
+    clients: HashMap<u128, (ChunkPos, HashSet<ChunkPos>)>,
+    /// Edited chunks being generated in the background, with the sequence number of the last edit
+    /// made before they started. Dropping a task cancels it.
+    tasks: HashMap<ChunkPos, (u64, Task<Chunk>)>,
+    /// The clients waiting on each edited chunk being generated
+    waiting: HashMap<ChunkPos, HashSet<u128>>,
+    /// The edited chunks generated here and added to the voxel world
+    built: HashSet<ChunkPos>,
+    /// The world the edited chunks were generated for
+    world: Option<WorldGenerator>
+}
+
+/// Generates a chunk as it is once every chunk around it has been decorated, then replays the
+/// edits made to it, off the main thread. Features reach no further than the next chunk, so only
+/// its neighbours are needed.
+async fn build_edited_chunk(world_generator: WorldGenerator, position: ChunkPos, brushes: Vec<Brush>) -> Chunk {
+    let mut chunk = world_generator.generate(position);
+
+    let mut pending_writes = PendingVoxelWrites::default();
+    pending_writes.add(position, world_generator.decorate(position, &chunk));
+
+    for z in -1..=1 {
+        for y in -1..=1 {
+            for x in -1..=1 {
+                if (x, y, z) == (0, 0, 0) {
+                    continue;
+                }
+
+                let neighbour = position.offset(x, y, z);
+                let neighbour_chunk = world_generator.generate(neighbour);
+                pending_writes.add(neighbour, world_generator.decorate(neighbour, &neighbour_chunk));
+            }
+        }
+    }
+    pending_writes.resolve(position, &mut chunk);
+
+    apply_brushes(position, chunk, brushes.iter())
+}
+
+/// Applies brushes to one chunk alone. Brushes reaching past it don't touch its neighbours,
+/// which have had them applied already.
+pub fn apply_brushes<'a>(position: ChunkPos, chunk: Chunk, brushes: impl Iterator<Item = &'a Brush>) -> Chunk {
+    let mut world = VoxelWorld::default();
+    world.insert_chunk(position, chunk);
+
+    for brush in brushes {
+        world.apply_brush(brush);
+    }
+
+    world.remove_chunk(position).unwrap()
+}
+
+fn send_chunk(net: &NetworkResource, addr: SocketAddr, position: ChunkPos, revision: u64, chunk: &Chunk) {
+    for fragment in ChunkFragment::split(position, revision, chunk) {
+        net.send(
+            addr,
+            &bincode::serialize(&NetMessage::ChunkFragment(fragment)).unwrap(),
+            NetworkDelivery::ReliableOrdered(Some(3))
+        );
+    }
+}
+
+/// Server only: tells each client the revision of the chunks coming into view of its player, and
+/// sends the voxels of edited chunks to the clients that ask for them. Unedited chunks are never
+/// sent, as clients generate them from the seed.
+///
+/// Edited chunks are generated on the `AsyncComputeTaskPool` the first time they're asked for, and
+/// kept in the server's voxel world while a player has them in view, so later edits apply to them
+/// too. They're generated again if they're asked for after that. Everything goes out on the same
+/// stream as the edits, so an edit a client hears of after a chunk's voxels is always one they
+/// don't include.
+pub fn server_chunk_transfer_system(
+    mut state: ResMut<ServerChunkTransferState>,
+    mut listener_state: ResMut<NetworkEventListenerState>,
+    chunk_transfer_events: Res<Events<ChunkTransferEvent>>,
+    net: Res<NetworkResource>,
+    clients: Res<Clients>,
+    settings: Res<ChunkStreamingSettings>,
+    task_pool: Res<AsyncComputeTaskPool>,
+    log: Res<VoxelEditLog>,
+    mut voxel_world: ResMut<VoxelWorld>,
+    world_generator: Res<WorldGenerator>,
+    player_query: Query<(&Owner, &Transform), With<Player>>
+) {
+    state.clients.retain(|client_id, _| clients.get(*client_id).is_some());
+
+    // The terrain graph was reloaded, so edited chunks are generated again and clients, who forget
+    // everything about the old terrain, are told every revision again
+    if !state.world.as_ref().map_or(false, |world| world.is_same_world(&world_generator)) {
+        for position in state.built.drain() {
+            voxel_world.remove_chunk(position);
+        }
+
+        state.clients.clear();
+        state.tasks.clear();
+        state.waiting.clear();
+        state.world = Some(world_generator.clone());
+    }
+
+    // Tell clients about the chunks that have come into view of their players
+    for (owner, transform) in player_query.iter() {
+        let client = match clients.get(owner.client_id) {
+            Some(client) => client,
+            None => continue
+        };
+
+        let center = ChunkPos::from_world(transform.translation);
+        let (last_center, told) = state.clients.entry(owner.client_id).or_insert((center, HashSet::new()));
+
+        if *last_center == center && !told.is_empty() {
+            continue;
+        }
+
+        *last_center = center;
+
+        // Every chunk in view is told about, not only those clients load the voxels of, so edited
+        // ones far away can be drawn from their voxels. Chunks that go out of view are told about
+        // again when they come back.
+        told.retain(|position| settings.in_retention(center, *position));
+
+        let mut revisions = vec![];
+        for x in -settings.view_distance..=settings.view_distance {
+            for y in -settings.vertical_view_distance..=settings.vertical_view_distance {
+                for z in -settings.view_distance..=settings.view_distance {
+                    let position = center.offset(x, y, z);
+
+                    if told.insert(position) {
+                        revisions.push((position, log.revision(position)));
+                    }
+                }
+            }
+        }
+
+        for revisions in revisions.chunks(REVISIONS_PER_MESSAGE) {
+            net.send(
+                client.connection().addr,
+                &bincode::serialize(&NetMessage::ChunkRevisions(revisions.to_vec())).unwrap(),
+                NetworkDelivery::ReliableOrdered(Some(3))
+            );
+        }
+    }
+
+    // Forget the edited chunks no player has in view any more
+    let centers: Vec<ChunkPos> = state.clients.values().map(|(center, _)| *center).collect();
+    let evicting: Vec<ChunkPos> = state.built.iter()
+        .filter(|position| !centers.iter().any(|center| settings.in_retention(*center, **position)))
+        .cloned()
+        .collect();
+
+    for position in evicting {
+        state.built.remove(&position);
+        voxel_world.remove_chunk(position);
+    }
+
+    // Send edited chunks we have straight away, and start generating the rest
+    for event in listener_state.chunk_transfer_events.iter(&chunk_transfer_events) {
+        let (from, positions) = match event {
+            ChunkTransferEvent::Requested { from, positions } => (*from, positions),
+            _ => continue
+        };
+
+        let client = match clients.get(from) {
+            Some(client) => client,
+            None => continue
+        };
+
+        for position in positions.iter().cloned() {
+            let revision = log.revision(position);
+
+            // The client can generate it itself
+            if revision == UNEDITED_REVISION {
+                continue;
+            }
+
+            if let Some(chunk) = voxel_world.chunk(position) {
+                send_chunk(&net, client.connection().addr, position, revision, chunk);
+                continue;
+            }
+
+            state.waiting.entry(position).or_default().insert(from);
+
+            if !state.tasks.contains_key(&position) {
+                let brushes = log.edits_in(position).map(|edit| edit.brush).collect();
+                let task = task_pool.spawn(build_edited_chunk(world_generator.clone(), position, brushes));
+                state.tasks.insert(position, (log.last_sequence(), task));
+            }
+        }
+    }
+
+    // Catch finished chunks up on the edits made while they were generated, and send them out
+    let positions: Vec<ChunkPos> = state.tasks.keys().cloned().collect();
+
+    for position in positions {
+        let finished = match state.tasks.get_mut(&position) {
+            Some((since, task)) => future::block_on(future::poll_once(task)).map(|chunk| (*since, chunk)),
+            None => None
+        };
+
+        let (since, chunk) = match finished {
+            Some(finished) => finished,
+            None => continue
+        };
+
+        state.tasks.remove(&position);
+
+        let newer = log.edits_in(position).filter(|edit| edit.sequence > since).map(|edit| &edit.brush);
+        voxel_world.insert_chunk(position, apply_brushes(position, chunk, newer));
+        state.built.insert(position);
+
+        let revision = log.revision(position);
+        let chunk = voxel_world.chunk(position).unwrap();
+
+        for client_id in state.waiting.remove(&position).unwrap_or_default() {
+            if let Some(client) = clients.get(client_id) {
+                send_chunk(&net, client.connection().addr, position, revision, chunk);
+            }
+        }
+    }
+}
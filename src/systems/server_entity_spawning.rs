@@ -13,17 +13,15 @@ pub fn server_entity_spawning_for_connected_clients(
     commands: &mut Commands,
     clients: Res<Clients>,
     net: Res<NetworkResource>,
-    added_synchronizable_entities: Query<(Entity), Added<Synchronize>>,
+    added_synchronizable_entities: Query<(Entity, Option<&Player>, Option<&Owner>), Added<Synchronize>>,
 ) {
-    for (entity) in added_synchronizable_entities.iter() {
+    for (entity, player, owner) in added_synchronizable_entities.iter() {
         println!("Server spawning entity {}", entity.id());
 
         for client in clients.iter() {
             net.send(
                 client.connection().addr,
-                &bincode::serialize(&NetMessage::EntitySpawn(EntitySpawn {
-                    entity_id: entity.id()
-                })).unwrap(),
+                &bincode::serialize(&NetMessage::EntitySpawn(entity_spawn(entity, player, owner, client.id()))).unwrap(),
                 NetworkDelivery::ReliableOrdered(Some(2))
             );
         }
@@ -35,19 +33,29 @@ pub fn server_entity_spawning_for_new_clients(
     commands: &mut Commands,
     changed_clients: ChangedRes<Clients>,
     net: Res<NetworkResource>,
-    synchronizable_entities: Query<(Entity, &Synchronize)>,
+    synchronizable_entities: Query<(Entity, &Synchronize, Option<&Player>, Option<&Owner>)>,
 ) {
-    for (entity, _) in synchronizable_entities.iter() {
+    for (entity, _, player, owner) in synchronizable_entities.iter() {
         println!("Server synchronizing entity {}", entity.id());
 
         for client in changed_clients.iter() {
             net.send(
                 client.connection().addr,
-                &bincode::serialize(&NetMessage::EntitySpawn(EntitySpawn {
-                    entity_id: entity.id()
-                })).unwrap(),
+                &bincode::serialize(&NetMessage::EntitySpawn(entity_spawn(entity, player, owner, client.id()))).unwrap(),
                 NetworkDelivery::ReliableOrdered(Some(2))
             );
         }
     }
 }
+
+/// Describes an entity to one client, flagging it as controlled if that client owns it
+fn entity_spawn(entity: Entity, player: Option<&Player>, owner: Option<&Owner>, client_id: u128) -> EntitySpawn {
+    EntitySpawn {
+        entity_id: entity.id(),
+        archetype: match player {
+            Some(player) => EntityArchetype::Player(player.profile()),
+            None => EntityArchetype::None
+        },
+        controlled: owner.map_or(false, |owner| owner.client_id == client_id)
+    }
+}
@@ -8,22 +8,34 @@ use crate::resources::*;
 pub fn server_player_movement_system(
     mut state: ResMut<NetworkEventListenerState>,
     command_frame_events: Res<Events<CommandFrameEvent>>,
-    mut query: Query<(Entity, &LocalPlayer, &mut Synchronized<RigidBodyHandleComponent>)>
+    mut query: Query<(Entity, &Owner, &mut Player, &mut Synchronized<RigidBodyHandleComponent>)>
 ) {
     for event in state.command_frame_events.iter(&command_frame_events) {
 
         // If the command frame is for an input command then we're in control of it, proceed
-        if let SynchronizedInput::InputCommand(_) = event.command_frame.input {
-            for (entity, _, mut synchronized_rigid_body) in query.iter_mut() {
+        if let SynchronizedInput::InputCommand(input_command) = event.command_frame.input {
+            for (entity, owner, mut player, mut synchronized_rigid_body) in query.iter_mut() {
                 if entity.id() != event.command_frame.entity_id {
                     continue;
                 }
 
-                synchronized_rigid_body.command_frames().push(
-                    event.command_frame.entity_id,
-                    event.command_frame.frame,
-                    event.command_frame.input
-                );
+                // Clients may only drive the entities they own
+                if owner.client_id != event.from {
+                    println!("Client {} sent a command frame for entity {} it doesn't own", event.from, entity.id());
+                    break
+                }
+
+                let latest = LookDirection {
+                    yaw: input_command.yaw,
+                    pitch: input_command.pitch
+                };
+
+                // Only flag the component as changed when the view actually moved
+                if player.look_direction != latest {
+                    player.look_direction = latest;
+                }
+
+                synchronized_rigid_body.command_frames().insert(event.command_frame);
 
                 // No more entities by the same ID (hopefully?!)
                 break
@@ -13,6 +13,7 @@ pub fn server_state_authoring_system<TComponent: Synchronizable>(
     clients: Res<Clients>,
     sim_time: Res<SimulationTime>,
     net: Res<NetworkResource>,
+    mut recorder: ResMut<ReplayRecorder>,
     mut synchronizable_entity_query: Query<(Entity, &mut Synchronized<TComponent>), Changed<TComponent>>,
 ) {
     for (entity, mut synchronizable) in &mut synchronizable_entity_query.iter_mut() {
@@ -30,6 +31,9 @@ pub fn server_state_authoring_system<TComponent: Synchronizable>(
             for client in clients.iter() {
                 net.send(client.connection().addr, &bytes, NetworkDelivery::UnreliableUnordered);
             }
+
+            // Replays restore entities from these when seeking
+            recorder.record(ReplayEvent::StateFrame(state_frame.clone()));
         }
     }
 }
@@ -0,0 +1,251 @@
+use std::collections::{HashMap, HashSet};
+use bevy::{
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task}
+};
+use bevy_rapier3d::rapier::geometry::ColliderBuilder;
+use futures_lite::future;
+use crate::components::*;
+use crate::meshing::*;
+use crate::models::*;
+use crate::resources::*;
+use crate::systems::{ChunkTaskOutput, apply_brushes, build_chunk, spawn_collider_entity};
+
+/// How far from a player's chunk the terrain collides, in chunks. Players never move further
+/// than this in a frame.
+const COLLIDER_DISTANCE: i32 = 1;
+
+/// How much further than they're needed chunks and colliders are kept, so those at the edge
+/// aren't thrown away and made again as a player moves back and forth
+const UNLOAD_MARGIN: i32 = 1;
+
+/// Server only
+#[derive(Default)]
+pub struct ServerTerrainState {
+    /// Chunks being generated and decorated in the background. Dropping a task cancels it.
+    tasks: HashMap<ChunkPos, Task<ChunkTaskOutput>>,
+    /// Chunks as generated, with the writes their features make, for the chunks around them to be
+    /// put together from
+    generated: HashMap<ChunkPos, ChunkTaskOutput>,
+    /// Chunks put together here and added to the voxel world
+    loaded: HashSet<ChunkPos>,
+    /// Colliders being meshed in the background, or nothing where a chunk has no surface.
+    /// Dropping a task cancels it.
+    meshing: HashMap<ChunkPos, Task<Option<ColliderBuilder>>>,
+    /// Chunks whose collider is missing or out of date
+    dirty: HashSet<ChunkPos>,
+    /// The static body colliding with each chunk near a player, if it has a surface
+    colliders: HashMap<ChunkPos, Option<Entity>>,
+    /// The world the chunks were generated for
+    world: Option<WorldGenerator>
+}
+
+/// Puts a chunk together as clients see it: generated, with the features of every chunk around
+/// it, then with every edit made to it replayed
+fn finish_chunk(generated: &HashMap<ChunkPos, ChunkTaskOutput>, log: &VoxelEditLog, position: ChunkPos) -> Chunk {
+    let mut pending_writes = PendingVoxelWrites::default();
+
+    for z in -1..=1 {
+        for y in -1..=1 {
+            for x in -1..=1 {
+                let neighbour = position.offset(x, y, z);
+                pending_writes.add(neighbour, generated[&neighbour].1.clone());
+            }
+        }
+    }
+
+    let mut chunk = generated[&position].0.clone();
+    pending_writes.resolve(position, &mut chunk);
+
+    apply_brushes(position, chunk, log.edits_in(position).map(|edit| &edit.brush))
+}
+
+/// Meshes a chunk's surface into a collider off the main thread, the same way clients mesh the
+/// chunks they collide with
+async fn mesh_collider(neighbourhood: ChunkNeighbourhood, method: MeshingMethod) -> Option<ColliderBuilder> {
+    let mesh = build_mesh(&neighbourhood, method);
+
+    if mesh.is_empty() {
+        None
+    } else {
+        Some(mesh.collider())
+    }
+}
+
+/// Whether a chunk is within `distance` chunks of any of the players
+fn near(centers: &[ChunkPos], position: ChunkPos, distance: i32) -> bool {
+    centers.iter().any(|center| center.chebyshev_distance(position) <= distance)
+}
+
+/// Server only: gives the terrain around every player a collider, so the server moves players
+/// against the same ground their clients do. The chunks around each player are generated and
+/// decorated on the `AsyncComputeTaskPool`, put together with the edits made to them once every
+/// chunk around them has been, and added to the voxel world, where later edits apply to them.
+/// Colliders are meshed again whenever the voxel world reports a change they reach.
+pub fn server_terrain_collision_system(
+    commands: &mut Commands,
+    mut state: ResMut<ServerTerrainState>,
+    task_pool: Res<AsyncComputeTaskPool>,
+    log: Res<VoxelEditLog>,
+    mut voxel_world: ResMut<VoxelWorld>,
+    world_generator: Res<WorldGenerator>,
+    player_query: Query<&Transform, (With<Player>, With<Owner>)>
+) {
+    let mut centers: Vec<ChunkPos> = player_query.iter()
+        .map(|transform| ChunkPos::from_world(transform.translation))
+        .collect();
+    centers.sort();
+    centers.dedup();
+
+    // Everything generated so far belongs to a world that's gone
+    if !state.world.as_ref().map_or(false, |world| world.is_same_world(&world_generator)) {
+        for (_, entity) in state.colliders.drain() {
+            if let Some(entity) = entity {
+                commands.despawn(entity);
+            }
+        }
+
+        for position in state.loaded.drain() {
+            voxel_world.remove_chunk(position);
+        }
+
+        state.tasks.clear();
+        state.generated.clear();
+        state.meshing.clear();
+        state.dirty.clear();
+        state.world = Some(world_generator.clone());
+    }
+
+    // Colliders need the voxels of the chunks around them, and those need the features of the
+    // chunks around them in turn
+    let voxel_distance = COLLIDER_DISTANCE + 1;
+    let generated_distance = COLLIDER_DISTANCE + 2;
+
+    // Forget whatever no player is near any more
+    state.tasks.retain(|position, _| near(&centers, *position, generated_distance + UNLOAD_MARGIN));
+    state.generated.retain(|position, _| near(&centers, *position, generated_distance + UNLOAD_MARGIN));
+    state.meshing.retain(|position, _| near(&centers, *position, COLLIDER_DISTANCE + UNLOAD_MARGIN));
+    state.dirty.retain(|position| near(&centers, *position, COLLIDER_DISTANCE + UNLOAD_MARGIN));
+
+    let removing: Vec<ChunkPos> = state.colliders.keys()
+        .filter(|position| !near(&centers, **position, COLLIDER_DISTANCE + UNLOAD_MARGIN))
+        .cloned()
+        .collect();
+
+    for position in removing {
+        if let Some(Some(entity)) = state.colliders.remove(&position) {
+            commands.despawn(entity);
+        }
+    }
+
+    let unloading: Vec<ChunkPos> = state.loaded.iter()
+        .filter(|position| !near(&centers, **position, voxel_distance + UNLOAD_MARGIN))
+        .cloned()
+        .collect();
+
+    for position in unloading {
+        state.loaded.remove(&position);
+        voxel_world.remove_chunk(position);
+    }
+
+    // Collect finished chunks
+    let positions: Vec<ChunkPos> = state.tasks.keys().cloned().collect();
+
+    for position in positions {
+        let finished = match state.tasks.get_mut(&position) {
+            Some(task) => future::block_on(future::poll_once(task)),
+            None => None
+        };
+
+        if let Some(output) = finished {
+            state.tasks.remove(&position);
+            state.generated.insert(position, output);
+        }
+    }
+
+    for center in centers.iter() {
+        for x in -generated_distance..=generated_distance {
+            for y in -generated_distance..=generated_distance {
+                for z in -generated_distance..=generated_distance {
+                    let position = center.offset(x, y, z);
+
+                    if !state.generated.contains_key(&position) && !state.tasks.contains_key(&position) {
+                        let task = task_pool.spawn(build_chunk(world_generator.clone(), position));
+                        state.tasks.insert(position, task);
+                    }
+
+                    // Add the chunks whose neighbours have all been generated to the world, unless
+                    // an edited one was already sent to a client from there
+                    if center.chebyshev_distance(position) > voxel_distance || voxel_world.contains_chunk(position) {
+                        continue;
+                    }
+
+                    let ready = (-1..=1).all(|x| (-1..=1).all(|y| (-1..=1).all(|z| {
+                        state.generated.contains_key(&position.offset(x, y, z))
+                    })));
+
+                    if ready {
+                        voxel_world.insert_chunk(position, finish_chunk(&state.generated, &log, position));
+                        state.loaded.insert(position);
+                    }
+                }
+            }
+        }
+    }
+
+    // Mesh again the colliders whose voxels changed, and those of chunks players have come near
+    for position in voxel_world.take_dirty_chunks() {
+        if near(&centers, position, COLLIDER_DISTANCE) {
+            state.dirty.insert(position);
+        }
+    }
+
+    for center in centers.iter() {
+        for x in -COLLIDER_DISTANCE..=COLLIDER_DISTANCE {
+            for y in -COLLIDER_DISTANCE..=COLLIDER_DISTANCE {
+                for z in -COLLIDER_DISTANCE..=COLLIDER_DISTANCE {
+                    let position = center.offset(x, y, z);
+
+                    if !state.colliders.contains_key(&position) && !state.meshing.contains_key(&position) {
+                        state.dirty.insert(position);
+                    }
+                }
+            }
+        }
+    }
+
+    // A chunk already being meshed starts over, as its voxels have changed since. Chunks missing a
+    // neighbour are tried again next frame.
+    let dirty: Vec<ChunkPos> = state.dirty.drain().collect();
+
+    for position in dirty {
+        if let Some(neighbourhood) = ChunkNeighbourhood::from_world(&voxel_world, position) {
+            let task = task_pool.spawn(mesh_collider(neighbourhood, world_generator.meshing_method()));
+            state.meshing.insert(position, task);
+        }
+    }
+
+    // Swap in finished colliders
+    let positions: Vec<ChunkPos> = state.meshing.keys().cloned().collect();
+
+    for position in positions {
+        let finished = match state.meshing.get_mut(&position) {
+            Some(task) => future::block_on(future::poll_once(task)),
+            None => None
+        };
+
+        let collider = match finished {
+            Some(collider) => collider,
+            None => continue
+        };
+
+        state.meshing.remove(&position);
+
+        if let Some(Some(entity)) = state.colliders.remove(&position) {
+            commands.despawn(entity);
+        }
+
+        let entity = collider.map(|collider| spawn_collider_entity(commands, position, collider));
+        state.colliders.insert(position, entity);
+    }
+}
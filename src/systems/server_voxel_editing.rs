@@ -0,0 +1,124 @@
+use std::collections::HashMap;
+use bevy::prelude::*;
+use bevy_prototype_networking_laminar::{NetworkResource, NetworkDelivery};
+use crate::components::*;
+use crate::events::*;
+use crate::models::*;
+use crate::resources::*;
+
+/// Server only: how many edits each client may make right away, and the frame it was worked out
+#[derive(Default)]
+pub struct ServerVoxelEditState {
+    allowances: HashMap<u128, (f32, u32)>
+}
+
+/// Server only: checks each edit clients ask for, applies the ones allowed and sends them to every
+/// client in the order they were made. Clients hear why an edit of theirs was turned down, so they
+/// can roll it back.
+pub fn server_voxel_editing_system(
+    mut state: ResMut<ServerVoxelEditState>,
+    mut listener_state: ResMut<NetworkEventListenerState>,
+    voxel_edit_request_events: Res<Events<VoxelEditRequestEvent>>,
+    net: Res<NetworkResource>,
+    clients: Res<Clients>,
+    settings: Res<VoxelEditSettings>,
+    sim_time: Res<SimulationTime>,
+    mut recorder: ResMut<ReplayRecorder>,
+    mut log: ResMut<VoxelEditLog>,
+    mut voxel_world: ResMut<VoxelWorld>,
+    player_query: Query<(&Owner, &Transform), With<Player>>
+) {
+    for event in listener_state.voxel_edit_request_events.iter(&voxel_edit_request_events) {
+        let request = event.request;
+
+        let checked = check_edit(event.from, &request.brush, &clients, &settings, &player_query)
+            .and_then(|_| spend_allowance(&mut state, event.from, &settings, &sim_time));
+
+        if let Err(reason) = checked {
+            println!("Rejected voxel edit {} from client {}: {:?}", request.id, event.from, reason);
+
+            if let Some(client) = clients.get(event.from) {
+                net.send(
+                    client.connection().addr,
+                    &bincode::serialize(&NetMessage::VoxelEditRejected(request.id, reason)).unwrap(),
+                    NetworkDelivery::ReliableOrdered(Some(3))
+                );
+            }
+
+            continue;
+        }
+
+        voxel_world.apply_brush(&request.brush);
+        let edit = log.accept(request.brush);
+
+        recorder.record(ReplayEvent::VoxelEdit {
+            frame: sim_time.frame(),
+            brush: request.brush
+        });
+
+        for client in clients.iter() {
+            let edit = VoxelEdit {
+                request: if client.id() == event.from { Some(request.id) } else { None },
+                ..edit.clone()
+            };
+
+            net.send(
+                client.connection().addr,
+                &bincode::serialize(&NetMessage::VoxelEdit(edit)).unwrap(),
+                NetworkDelivery::ReliableOrdered(Some(3))
+            );
+        }
+    }
+}
+
+/// Whether a client may make an edit, leaving the rate limit to `spend_allowance`
+fn check_edit(
+    client_id: u128,
+    brush: &Brush,
+    clients: &Clients,
+    settings: &VoxelEditSettings,
+    player_query: &Query<(&Owner, &Transform), With<Player>>
+) -> Result<(), VoxelEditRejection> {
+    if !clients.get(client_id).map_or(false, |client| client.can_edit_terrain()) {
+        return Err(VoxelEditRejection::NotPermitted);
+    }
+
+    if !brush.is_valid(settings.max_radius) {
+        return Err(VoxelEditRejection::InvalidBrush);
+    }
+
+    // The body collides with the terrain, so this is where the player stands on the client too
+    let position = player_query.iter()
+        .find(|(owner, _)| owner.client_id == client_id)
+        .map(|(_, transform)| transform.translation)
+        .ok_or(VoxelEditRejection::NoPlayer)?;
+
+    if !settings.in_reach(position, brush.center) {
+        return Err(VoxelEditRejection::OutOfReach);
+    }
+
+    Ok(())
+}
+
+/// Takes one edit from a client's allowance, which refills at `edits_per_second` up to
+/// `edit_burst`
+fn spend_allowance(
+    state: &mut ServerVoxelEditState,
+    client_id: u128,
+    settings: &VoxelEditSettings,
+    sim_time: &SimulationTime
+) -> Result<(), VoxelEditRejection> {
+    let frame = sim_time.frame();
+    let (allowance, counted_at) = state.allowances.entry(client_id).or_insert((settings.edit_burst, frame));
+
+    let seconds = frame.saturating_sub(*counted_at) as f32 / sim_time.tick_rate() as f32;
+    *allowance = (*allowance + seconds * settings.edits_per_second).min(settings.edit_burst);
+    *counted_at = frame;
+
+    if *allowance < 1.0 {
+        return Err(VoxelEditRejection::RateLimited);
+    }
+
+    *allowance -= 1.0;
+    Ok(())
+}
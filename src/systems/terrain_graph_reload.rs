@@ -0,0 +1,66 @@
+use bevy::prelude::*;
+use bevy_prototype_networking_laminar::{NetworkResource, NetworkDelivery};
+use crate::models::*;
+use crate::resources::*;
+
+/// Server only: rebuilds the world generator when the terrain graph or cave settings change, and
+/// sends it to every client, whose terrain has to match ours. The server's systems and the clients'
+/// chunk loading see the new generator and regenerate everything around the players. An invalid
+/// file is reported and the current terrain kept.
+pub fn terrain_graph_reload_system(
+    time: Res<Time>,
+    sim_time: Res<SimulationTime>,
+    net: Res<NetworkResource>,
+    clients: Res<Clients>,
+    mut recorder: ResMut<ReplayRecorder>,
+    mut watcher: ResMut<TerrainGraphWatcher>,
+    mut world_generator: ResMut<WorldGenerator>
+) {
+    watcher.timer.tick(time.delta_seconds());
+
+    if !watcher.timer.just_finished() || !watcher.poll_changed() {
+        return;
+    }
+
+    let graph = match NoiseGraph::load(&watcher.path) {
+        Ok(graph) => graph,
+        Err(error) => {
+            println!("Failed to load terrain graph {}: {}", watcher.path.display(), error);
+            return;
+        }
+    };
+
+    // Cave settings are optional, like when the generator was first loaded
+    let caves = if watcher.caves_path.exists() {
+        match CaveSettings::load(&watcher.caves_path) {
+            Ok(caves) => caves,
+            Err(error) => {
+                println!("Failed to load cave settings {}: {}", watcher.caves_path.display(), error);
+                return;
+            }
+        }
+    } else {
+        CaveSettings::default()
+    };
+
+    match WorldGenerator::with_graph(world_generator.seed(), graph, caves) {
+        Ok(reloaded) => {
+            println!("Reloaded terrain graph {}", watcher.path.display());
+            *world_generator = reloaded.with_meshing_method(world_generator.meshing_method());
+
+            recorder.record(ReplayEvent::WorldInfo {
+                frame: sim_time.frame(),
+                world_info: world_generator.world_info()
+            });
+
+            for client in clients.iter() {
+                net.send(
+                    client.connection().addr,
+                    &bincode::serialize(&NetMessage::WorldInfo(world_generator.world_info())).unwrap(),
+                    NetworkDelivery::ReliableOrdered(Some(3))
+                );
+            }
+        },
+        Err(error) => println!("Failed to build terrain graph {}: {}", watcher.path.display(), error)
+    }
+}
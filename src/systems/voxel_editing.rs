@@ -0,0 +1,105 @@
+use bevy::prelude::*;
+use bevy_prototype_networking_laminar::{NetworkResource, NetworkDelivery};
+use crate::components::*;
+use crate::events::*;
+use crate::models::*;
+use crate::resources::*;
+use crate::systems::{CommandAccumulatorState, LocalPlayerCameraState};
+
+/// How far digging and building reach around the voxel aimed at
+const EDIT_RADIUS: f32 = 1.5;
+
+#[derive(Default)]
+pub struct VoxelEditingState {
+    /// The buttons held last frame, so an edit is only made when one is first pressed
+    previous: InputCommand,
+    /// Whether the cursor was grabbed last frame. The click that grabs it doesn't dig.
+    was_grabbed: bool
+}
+
+/// Client only: digs out or builds onto the voxel the local player is looking at, applying the
+/// edit straight away and asking the server for it
+pub fn voxel_editing_system(
+    mut state: ResMut<VoxelEditingState>,
+    ci: Res<ConnectionInfo>,
+    net: Res<NetworkResource>,
+    settings: Res<VoxelEditSettings>,
+    camera_state: Res<LocalPlayerCameraState>,
+    command_accumulator: Res<CommandAccumulatorState>,
+    mut prediction: ResMut<VoxelEditPrediction>,
+    mut voxel_world: ResMut<VoxelWorld>,
+    player_head_query: Query<&GlobalTransform, With<LocalPlayerHead>>
+) {
+    let command = match command_accumulator.input_buffer.inputs.back() {
+        Some(command) => *command,
+        None => return
+    };
+
+    let previous = state.previous;
+    let just_pressed = |action: InputAction| command.pressed(action) && !previous.pressed(action);
+    let (dig, build) = (just_pressed(InputAction::Dig), just_pressed(InputAction::Build));
+
+    let grabbed = camera_state.cursor_grabbed && state.was_grabbed;
+    state.previous = command;
+    state.was_grabbed = camera_state.cursor_grabbed;
+
+    if !grabbed || !(dig || build) {
+        return;
+    }
+
+    for transform in player_head_query.iter() {
+        let forward = transform.rotation * -Vec3::unit_z();
+
+        let hit = match voxel_world.raycast(transform.translation, forward, settings.reach) {
+            Some(hit) => hit,
+            None => continue
+        };
+
+        let (mode, target) = if dig {
+            (BrushMode::Remove, hit.position)
+        } else {
+            (BrushMode::Add(voxel_world.voxel_or_air(hit.position).material), hit.previous)
+        };
+
+        let brush = Brush {
+            shape: BrushShape::Sphere,
+            mode,
+            center: [target.0 as f32 + 0.5, target.1 as f32 + 0.5, target.2 as f32 + 0.5],
+            radius: EDIT_RADIUS,
+            strength: 1.0
+        };
+
+        let request = prediction.predict(&mut voxel_world, brush);
+
+        net.send(
+            *ci.server_addr(),
+            &bincode::serialize(&NetMessage::VoxelEditRequest(request)).unwrap(),
+            NetworkDelivery::ReliableOrdered(Some(3))
+        );
+    }
+}
+
+/// Client only: applies the server's edits in the order it made them, and rolls back predicted
+/// edits it turned down. Each edit moves the chunks it reaches on to a new revision, so copies of
+/// them cached from before aren't used again.
+pub fn client_voxel_edit_system(
+    mut listener_state: ResMut<NetworkEventListenerState>,
+    voxel_edit_events: Res<Events<VoxelEditEvent>>,
+    voxel_edit_rejected_events: Res<Events<VoxelEditRejectedEvent>>,
+    mut prediction: ResMut<VoxelEditPrediction>,
+    mut cache: ResMut<ChunkCache>,
+    mut voxel_world: ResMut<VoxelWorld>
+) {
+    for event in listener_state.voxel_edit_events.iter(&voxel_edit_events) {
+        if prediction.apply_authoritative(&mut voxel_world, &event.edit) {
+            for (position, revision) in event.edit.revisions.iter() {
+                cache.set_revision(*position, *revision);
+            }
+        }
+    }
+
+    for event in listener_state.voxel_edit_rejected_events.iter(&voxel_edit_rejected_events) {
+        println!("The server rejected voxel edit {}: {:?}", event.id, event.reason);
+        prediction.reject(&mut voxel_world, event.id);
+    }
+}
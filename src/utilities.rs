@@ -1,7 +1,17 @@
 mod euler;
+mod biome_map;
+mod cave_carver;
+mod feature_placer;
 mod gradient;
+mod noise_sampler;
+mod seed;
 
 pub use self::{
     euler::*,
-    gradient::*
+    biome_map::*,
+    cave_carver::*,
+    feature_placer::*,
+    gradient::*,
+    noise_sampler::*,
+    seed::*
 };
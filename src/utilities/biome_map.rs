@@ -0,0 +1,67 @@
+use noise::*;
+
+use crate::models::*;
+use crate::utilities::split_seed;
+
+/// The seed streams of the climate maps. The terrain graph's own streams start at 0, so these
+/// are kept well clear of them.
+const TEMPERATURE_SEED: u64 = 1000;
+const HUMIDITY_SEED: u64 = 1001;
+
+/// How quickly the climate changes, per chunk
+const CLIMATE_FREQUENCY: f64 = 0.04;
+
+/// How far a column's climate can be from a biome's before it stops mattering. Wider blends
+/// make smoother borders.
+const BLEND_WIDTH: f64 = 0.12;
+
+/// Chooses the biome of each column of the world from temperature and humidity noise
+pub struct BiomeMap {
+    temperature: Fbm,
+    humidity: Fbm
+}
+
+impl BiomeMap {
+    pub fn new(world_seed: u64) -> BiomeMap {
+        let climate = |stream| Fbm::new()
+            .set_seed(split_seed(world_seed, stream))
+            .set_octaves(3)
+            .set_frequency(CLIMATE_FREQUENCY);
+
+        BiomeMap {
+            temperature: climate(TEMPERATURE_SEED),
+            humidity: climate(HUMIDITY_SEED)
+        }
+    }
+
+    /// The temperature and humidity of a column, each roughly in `-1.0..=1.0`. The column is
+    /// given in chunks.
+    pub fn climate(&self, x: f64, z: f64) -> (f64, f64) {
+        (self.temperature.get([x, z]), self.humidity.get([x, z]))
+    }
+
+    /// The biomes of a column, weighted by how close its climate is to each of theirs
+    pub fn blend(&self, x: f64, z: f64) -> BiomeBlend {
+        let (temperature, humidity) = self.climate(x, z);
+        let mut distances = [0.0; Biome::ALL.len()];
+
+        for (distance, biome) in distances.iter_mut().zip(Biome::ALL.iter()) {
+            let definition = biome.definition();
+            *distance = (temperature - definition.temperature).powi(2) + (humidity - definition.humidity).powi(2);
+        }
+
+        // Measured from the closest biome, so it always has full weight however far away it is
+        let closest = distances.iter().cloned().fold(std::f64::MAX, f64::min);
+        let mut weights = [0.0; Biome::ALL.len()];
+
+        for (weight, distance) in weights.iter_mut().zip(distances.iter()) {
+            *weight = (-(distance - closest) / (BLEND_WIDTH * BLEND_WIDTH)).exp();
+        }
+
+        BiomeBlend::new(weights)
+    }
+
+    pub fn biome(&self, x: f64, z: f64) -> Biome {
+        self.blend(x, z).dominant()
+    }
+}
@@ -0,0 +1,210 @@
+use noise::*;
+
+use crate::models::*;
+use crate::utilities::{split_seed, position_seed, SeedRandom};
+
+/// The seed streams of the cave noise, clear of the terrain graph's and the climate's
+const CHEESE_SEED: u64 = 1100;
+const SPAGHETTI_SEEDS: [u64; 2] = [1101, 1102];
+const WORM_SEED: u64 = 1103;
+
+/// The size of the regions worms start in, in voxels. A worm never reaches further than the
+/// regions next to its own.
+const WORM_REGION_SIZE: i32 = 128;
+
+/// How many voxels caves take to taper shut at the ends of their height range
+const RANGE_TAPER: f32 = 8.0;
+
+/// Roughly how much the noise changes per unit, which turns noise values into distances
+const NOISE_SLOPE: f64 = 1.5;
+
+/// How far inside a cave each voxel of one chunk is, in voxels. Positive values are inside a
+/// cave and negative values are solid, so carved walls can be meshed smoothly.
+pub struct CaveCarving {
+    depths: Vec<f32>
+}
+
+impl CaveCarving {
+    pub fn get(&self, position: LocalVoxelPos) -> f32 {
+        let LocalVoxelPos(x, y, z) = position;
+        self.depths[(y * CHUNK_SIZE + z) * CHUNK_SIZE + x]
+    }
+
+    fn carve(&mut self, position: LocalVoxelPos, depth: f32) {
+        let LocalVoxelPos(x, y, z) = position;
+        let current = &mut self.depths[(y * CHUNK_SIZE + z) * CHUNK_SIZE + x];
+        *current = current.max(depth);
+    }
+}
+
+/// Works out where caves are, from the world seed alone, so every chunk carves the same caves
+/// whatever order chunks are generated in
+pub struct CaveCarver {
+    settings: CaveSettings,
+    world_seed: u64,
+    cheese: Fbm,
+    spaghetti: [Fbm; 2]
+}
+
+impl CaveCarver {
+    pub fn new(world_seed: u64, settings: CaveSettings) -> CaveCarver {
+        let noise = |stream, caves: &Option<NoiseCaves>, octaves| Fbm::new()
+            .set_seed(split_seed(world_seed, stream))
+            .set_octaves(octaves)
+            .set_frequency(caves.map_or(1.0, |caves| caves.frequency));
+
+        CaveCarver {
+            cheese: noise(CHEESE_SEED, &settings.cheese, 2),
+            spaghetti: [
+                noise(SPAGHETTI_SEEDS[0], &settings.spaghetti, 1),
+                noise(SPAGHETTI_SEEDS[1], &settings.spaghetti, 1)
+            ],
+            settings,
+            world_seed
+        }
+    }
+
+    pub fn settings(&self) -> &CaveSettings {
+        &self.settings
+    }
+
+    /// The caves in a chunk, or nothing if no kind of cave is carved at its height. The surface
+    /// margin isn't applied, as that depends on the terrain.
+    pub fn carve(&self, position: ChunkPos) -> Option<CaveCarving> {
+        let origin = position.origin();
+        let overlaps = |range: &CaveRange| {
+            range.min_height < origin.1 + CHUNK_SIZE as i32 && range.max_height >= origin.1
+        };
+
+        let cheese = self.settings.cheese.filter(|caves| overlaps(&caves.range));
+        let spaghetti = self.settings.spaghetti.filter(|caves| overlaps(&caves.range));
+        let worms = self.settings.worms.filter(|caves| overlaps(&caves.range));
+
+        if cheese.is_none() && spaghetti.is_none() && worms.is_none() {
+            return None;
+        }
+
+        let mut carving = CaveCarving {
+            depths: vec![std::f32::MIN; CHUNK_VOLUME]
+        };
+
+        if cheese.is_some() || spaghetti.is_some() {
+            for y in 0..CHUNK_SIZE {
+                for z in 0..CHUNK_SIZE {
+                    for x in 0..CHUNK_SIZE {
+                        let local = LocalVoxelPos(x, y, z);
+                        let voxel = local.to_world(position);
+                        let point = [voxel.0 as f64, voxel.1 as f64, voxel.2 as f64];
+
+                        if let Some(caves) = cheese {
+                            let depth = (self.cheese.get(point) - caves.threshold) / (NOISE_SLOPE * caves.frequency);
+                            carving.carve(local, depth as f32 - taper(&caves.range, voxel.1));
+                        }
+
+                        if let Some(caves) = spaghetti {
+                            let closeness = self.spaghetti[0].get(point).abs().max(self.spaghetti[1].get(point).abs());
+                            let depth = (caves.threshold - closeness) / (NOISE_SLOPE * caves.frequency);
+                            carving.carve(local, depth as f32 - taper(&caves.range, voxel.1));
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(caves) = worms {
+            let region = |value: i32| value.div_euclid(WORM_REGION_SIZE);
+            let (x, y, z) = (region(origin.0), region(origin.1), region(origin.2));
+
+            for region_z in z - 1..=z + 1 {
+                for region_y in y - 1..=y + 1 {
+                    for region_x in x - 1..=x + 1 {
+                        self.carve_worm(&caves, position, (region_x, region_y, region_z), &mut carving);
+                    }
+                }
+            }
+        }
+
+        Some(carving)
+    }
+
+    /// Carves the part of a region's worm, if it has one, that passes through a chunk
+    fn carve_worm(&self, caves: &WormCaves, chunk: ChunkPos, region: (i32, i32, i32), carving: &mut CaveCarving) {
+        let mut random = SeedRandom::new(position_seed(self.world_seed, WORM_SEED, region.0, region.1, region.2));
+
+        if random.next_f32() >= caves.chance {
+            return;
+        }
+
+        let size = WORM_REGION_SIZE as f32;
+        let mut position = [
+            (region.0 * WORM_REGION_SIZE) as f32 + random.range(0.0, size),
+            (region.1 * WORM_REGION_SIZE) as f32 + random.range(0.0, size),
+            (region.2 * WORM_REGION_SIZE) as f32 + random.range(0.0, size)
+        ];
+
+        if position[1] < caves.range.min_height as f32 || position[1] > caves.range.max_height as f32 {
+            return;
+        }
+
+        let mut yaw = random.range(0.0, std::f32::consts::PI * 2.0);
+        let mut pitch = random.range(-0.3, 0.3);
+        let phase = random.range(0.0, std::f32::consts::PI * 2.0);
+
+        // Kept short enough that the worm can't leave the regions around its own
+        let steps = caves.length.min((size - caves.radius * 2.0).max(0.0) as usize);
+
+        let origin = chunk.origin();
+        let chunk_min = [origin.0 as f32, origin.1 as f32, origin.2 as f32];
+        let chunk_max = [chunk_min[0] + CHUNK_SIZE as f32, chunk_min[1] + CHUNK_SIZE as f32, chunk_min[2] + CHUNK_SIZE as f32];
+
+        for step in 0..steps {
+            let radius = caves.radius * (1.0 + 0.25 * (step as f32 * 0.15 + phase).sin());
+
+            let touches_chunk = (0..3).all(|axis| {
+                position[axis] + radius >= chunk_min[axis] && position[axis] - radius < chunk_max[axis]
+            });
+
+            if touches_chunk {
+                carve_sphere(caves, chunk, position, radius, carving);
+            }
+
+            yaw += random.range(-0.3, 0.3);
+            pitch = (pitch + random.range(-0.15, 0.15)).max(-0.6).min(0.6);
+
+            position[0] += yaw.cos() * pitch.cos();
+            position[1] += pitch.sin();
+            position[2] += yaw.sin() * pitch.cos();
+        }
+    }
+}
+
+fn carve_sphere(caves: &WormCaves, chunk: ChunkPos, center: [f32; 3], radius: f32, carving: &mut CaveCarving) {
+    let origin = chunk.origin();
+    let origin = [origin.0, origin.1, origin.2];
+    let bounds = |axis: usize| {
+        let min = ((center[axis] - radius).floor() as i32 - origin[axis]).max(0);
+        let max = ((center[axis] + radius).ceil() as i32 - origin[axis]).min(CHUNK_SIZE as i32 - 1);
+        min..=max
+    };
+
+    for z in bounds(2) {
+        for y in bounds(1) {
+            for x in bounds(0) {
+                let (dx, dy, dz) = (
+                    (origin[0] + x) as f32 - center[0],
+                    (origin[1] + y) as f32 - center[1],
+                    (origin[2] + z) as f32 - center[2]
+                );
+                let depth = radius - (dx * dx + dy * dy + dz * dz).sqrt();
+
+                carving.carve(LocalVoxelPos(x as usize, y as usize, z as usize), depth - taper(&caves.range, origin[1] + y));
+            }
+        }
+    }
+}
+
+/// How much shallower caves are at a height, closing them towards the ends of their range
+fn taper(range: &CaveRange, height: i32) -> f32 {
+    let inside = (height - range.min_height).min(range.max_height - height) as f32;
+    (RANGE_TAPER - inside).max(0.0)
+}
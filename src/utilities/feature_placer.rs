@@ -0,0 +1,180 @@
+use crate::models::*;
+use crate::utilities::{position_seed, SeedRandom};
+
+/// The seed stream features are placed from, clear of the terrain's and the caves'
+const FEATURE_SEED: u64 = 1200;
+
+/// How many times a chunk looks for a spot for each tree or boulder before giving up on it
+const PLACEMENT_ATTEMPTS: usize = 4;
+
+/// Decorates generated chunks with trees, boulders and ore. Everything a chunk places depends
+/// only on the world seed, the chunk's position and its own voxels, so chunks can be decorated
+/// in any order.
+pub struct FeaturePlacer {
+    world_seed: u64
+}
+
+impl FeaturePlacer {
+    pub fn new(world_seed: u64) -> FeaturePlacer {
+        FeaturePlacer {
+            world_seed
+        }
+    }
+
+    /// The voxels of every feature a chunk places, which may reach into the chunks around it.
+    /// `biome` gives the biome of a column, by world position.
+    pub fn place(&self, position: ChunkPos, chunk: &Chunk, biome: impl Fn(i32, i32) -> BiomeDefinition) -> Vec<VoxelWrite> {
+        let mut random = SeedRandom::new(position_seed(self.world_seed, FEATURE_SEED, position.0, position.1, position.2));
+        let mut writes = vec![];
+
+        self.place_ores(position, &mut random, &mut writes);
+
+        // Empty and completely solid chunks have no surface to place anything on
+        if chunk.storage().uniform().is_some() {
+            return writes;
+        }
+
+        let middle = position.origin().offset(CHUNK_SIZE as i32 / 2, 0, CHUNK_SIZE as i32 / 2);
+        let biome = biome(middle.0, middle.2);
+
+        for _ in 0..count(&mut random, biome.trees_per_chunk) {
+            if let Some(ground) = find_surface(chunk, position, &mut random, biome.surface_material) {
+                place_tree(ground, &biome, &mut random, &mut writes);
+            }
+        }
+
+        for _ in 0..count(&mut random, biome.boulders_per_chunk) {
+            if let Some(ground) = find_surface(chunk, position, &mut random, biome.surface_material) {
+                place_boulder(ground, &mut random, &mut writes);
+            }
+        }
+
+        writes
+    }
+
+    fn place_ores(&self, position: ChunkPos, random: &mut SeedRandom, writes: &mut Vec<VoxelWrite>) {
+        let origin = position.origin();
+
+        for ore in ORES.iter() {
+            if ore.max_height < origin.1 || ore.min_height >= origin.1 + CHUNK_SIZE as i32 {
+                continue;
+            }
+
+            for _ in 0..count(random, ore.veins_per_chunk) {
+                let mut vein = origin.offset(
+                    random.range(0.0, CHUNK_SIZE as f32) as i32,
+                    random.range(0.0, CHUNK_SIZE as f32) as i32,
+                    random.range(0.0, CHUNK_SIZE as f32) as i32
+                );
+
+                if vein.1 < ore.min_height || vein.1 > ore.max_height {
+                    continue;
+                }
+
+                // Veins wander a voxel at a time, so they stay in one connected clump
+                for _ in 0..ore.vein_size {
+                    writes.push(VoxelWrite {
+                        position: vein,
+                        voxel: Voxel::solid(ore.material),
+                        rule: WriteRule::IntoMaterial(STONE)
+                    });
+
+                    let step = (random.next_u32() % 6) as usize;
+                    let direction = [(1, 0, 0), (-1, 0, 0), (0, 1, 0), (0, -1, 0), (0, 0, 1), (0, 0, -1)][step];
+                    vein = vein.offset(direction.0, direction.1, direction.2);
+                }
+            }
+        }
+    }
+}
+
+/// Rounds an average count to a whole number, up or down at random in proportion
+fn count(random: &mut SeedRandom, average: f32) -> usize {
+    let whole = average.floor();
+    whole as usize + if random.next_f32() < average - whole { 1 } else { 0 }
+}
+
+/// A random voxel of `material` with open space above it, in the same chunk
+fn find_surface(chunk: &Chunk, position: ChunkPos, random: &mut SeedRandom, material: u8) -> Option<VoxelPos> {
+    for _ in 0..PLACEMENT_ATTEMPTS {
+        let x = (random.next_u32() as usize) % CHUNK_SIZE;
+        let z = (random.next_u32() as usize) % CHUNK_SIZE;
+
+        for y in (0..CHUNK_SIZE - 1).rev() {
+            let voxel = chunk.get(LocalVoxelPos(x, y, z));
+            let above = chunk.get(LocalVoxelPos(x, y + 1, z));
+
+            if voxel.is_solid() && !above.is_solid() {
+                if voxel.material == material {
+                    return Some(LocalVoxelPos(x, y, z).to_world(position));
+                }
+
+                break;
+            }
+        }
+    }
+
+    None
+}
+
+fn place_tree(ground: VoxelPos, biome: &BiomeDefinition, random: &mut SeedRandom, writes: &mut Vec<VoxelWrite>) {
+    let height = random.range(4.0, 7.0) as i32;
+
+    for y in 1..=height {
+        writes.push(VoxelWrite {
+            position: ground.offset(0, y, 0),
+            voxel: Voxel::solid(WOOD),
+            rule: WriteRule::IntoAir
+        });
+    }
+
+    // Pines narrow to a point, other trees have a round crown
+    let pointed = biome.leaves_material == PINE_LEAVES;
+    let top = ground.offset(0, height, 0);
+    let radius = 2;
+
+    for y in -radius..=radius + 1 {
+        let layer_radius = if pointed {
+            (radius as f32 * (radius + 1 - y) as f32 / (radius * 2 + 1) as f32 + 0.5) as i32
+        } else {
+            radius
+        };
+
+        for z in -layer_radius..=layer_radius {
+            for x in -layer_radius..=layer_radius {
+                let distance_squared = x * x + z * z + if pointed { 0 } else { y * y };
+
+                if distance_squared > layer_radius * layer_radius + 1 {
+                    continue;
+                }
+
+                writes.push(VoxelWrite {
+                    position: top.offset(x, y, z),
+                    voxel: Voxel::solid(biome.leaves_material),
+                    rule: WriteRule::IntoAir
+                });
+            }
+        }
+    }
+}
+
+fn place_boulder(ground: VoxelPos, random: &mut SeedRandom, writes: &mut Vec<VoxelWrite>) {
+    let radius = random.range(1.5, 3.0);
+    let reach = radius.ceil() as i32;
+
+    for z in -reach..=reach {
+        for y in -reach..=reach {
+            for x in -reach..=reach {
+                if ((x * x + y * y + z * z) as f32).sqrt() > radius {
+                    continue;
+                }
+
+                writes.push(VoxelWrite {
+                    position: ground.offset(x, y, z),
+                    voxel: Voxel::solid(STONE),
+                    rule: WriteRule::IntoAir
+                });
+            }
+        }
+    }
+}
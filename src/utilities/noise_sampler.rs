@@ -0,0 +1,294 @@
+use std::collections::HashMap;
+use noise::*;
+
+use crate::models::{NoiseGraph, NoiseNode, NoiseGraphError};
+use crate::utilities::{Gradient, split_seed};
+
+/// How many points are sampled to measure the range of an AutoCorrect node's source
+const AUTO_CORRECT_SAMPLES: usize = 4096;
+
+/// How far from the origin AutoCorrect samples its source, in noise units
+const AUTO_CORRECT_EXTENT: f64 = 64.0;
+
+enum SamplerNode {
+    Constant(f64),
+    Gradient(Gradient),
+    Perlin(Perlin),
+    Fbm(Fbm),
+    Billow(Billow),
+    RidgedMulti(RidgedMulti),
+    Clamp { source: usize, lower: f64, upper: f64 },
+    ScaleBias { source: usize, scale: f64, bias: f64 },
+    ScalePoint { source: usize, scale: [f64; 3] },
+    Displace { source: usize, offsets: [Option<usize>; 3] },
+    Select { outside: usize, inside: usize, control: usize, lower_bound: f64, upper_bound: f64, falloff: f64 }
+}
+
+/// A `NoiseGraph` built for one world seed. Nodes are stored once and referenced by index, so
+/// the sampler owns all of its modules and can be shared between generation tasks.
+pub struct NoiseSampler {
+    nodes: Vec<SamplerNode>,
+    output: usize
+}
+
+impl NoiseSampler {
+    /// Builds every node the graph's output depends on. Fractal nodes are seeded from their seed
+    /// stream of the world seed.
+    pub fn build(graph: &NoiseGraph, world_seed: u64) -> Result<NoiseSampler, NoiseGraphError> {
+        let mut builder = SamplerBuilder {
+            graph,
+            world_seed,
+            nodes: vec![],
+            built: HashMap::new(),
+            visiting: vec![]
+        };
+
+        let output = builder.build(&graph.output)?;
+
+        Ok(NoiseSampler {
+            nodes: builder.nodes,
+            output
+        })
+    }
+}
+
+impl NoiseFn<[f64; 3]> for NoiseSampler {
+    fn get(&self, point: [f64; 3]) -> f64 {
+        sample(&self.nodes, self.output, point)
+    }
+}
+
+fn sample(nodes: &[SamplerNode], index: usize, point: [f64; 3]) -> f64 {
+    match &nodes[index] {
+        SamplerNode::Constant(value) => *value,
+        SamplerNode::Gradient(gradient) => gradient.get(point),
+        SamplerNode::Perlin(perlin) => perlin.get(point),
+        SamplerNode::Fbm(fbm) => fbm.get(point),
+        SamplerNode::Billow(billow) => billow.get(point),
+        SamplerNode::RidgedMulti(ridged_multi) => ridged_multi.get(point),
+        SamplerNode::Clamp { source, lower, upper } => {
+            sample(nodes, *source, point).max(*lower).min(*upper)
+        },
+        SamplerNode::ScaleBias { source, scale, bias } => {
+            sample(nodes, *source, point) * scale + bias
+        },
+        SamplerNode::ScalePoint { source, scale } => {
+            sample(nodes, *source, [point[0] * scale[0], point[1] * scale[1], point[2] * scale[2]])
+        },
+        SamplerNode::Displace { source, offsets } => {
+            let mut displaced = point;
+
+            for (axis, offset) in offsets.iter().enumerate() {
+                if let Some(offset) = offset {
+                    displaced[axis] += sample(nodes, *offset, point);
+                }
+            }
+
+            sample(nodes, *source, displaced)
+        },
+        SamplerNode::Select { outside, inside, control, lower_bound, upper_bound, falloff } => {
+            let control = sample(nodes, *control, point);
+            let (lower, upper, falloff) = (*lower_bound, *upper_bound, *falloff);
+
+            // Same blend as noise::Select, an s-curve across the falloff at each bound
+            let blend = |from: f64, to: f64, t: f64| {
+                let t = t * t * (3.0 - 2.0 * t);
+                from + t * (to - from)
+            };
+
+            if falloff > 0.0 {
+                if control < lower - falloff || control > upper + falloff {
+                    sample(nodes, *outside, point)
+                } else if control < lower + falloff {
+                    let t = (control - (lower - falloff)) / (2.0 * falloff);
+                    blend(sample(nodes, *outside, point), sample(nodes, *inside, point), t)
+                } else if control > upper - falloff {
+                    let t = (control - (upper - falloff)) / (2.0 * falloff);
+                    blend(sample(nodes, *inside, point), sample(nodes, *outside, point), t)
+                } else {
+                    sample(nodes, *inside, point)
+                }
+            } else if control < lower || control > upper {
+                sample(nodes, *outside, point)
+            } else {
+                sample(nodes, *inside, point)
+            }
+        }
+    }
+}
+
+struct SamplerBuilder<'a> {
+    graph: &'a NoiseGraph,
+    world_seed: u64,
+    nodes: Vec<SamplerNode>,
+    /// The index of each node built so far, so shared nodes are only built once
+    built: HashMap<&'a str, usize>,
+    /// The nodes being built, to catch cycles
+    visiting: Vec<&'a str>
+}
+
+impl<'a> SamplerBuilder<'a> {
+    fn build(&mut self, name: &'a str) -> Result<usize, NoiseGraphError> {
+        if let Some(index) = self.built.get(name) {
+            return Ok(*index);
+        }
+
+        if self.visiting.contains(&name) {
+            return Err(NoiseGraphError::Cycle(name.to_string()));
+        }
+
+        let node = self.graph.node(name)?;
+
+        self.visiting.push(name);
+        let built = self.build_node(node)?;
+        self.visiting.pop();
+
+        let index = self.nodes.len();
+        self.nodes.push(built);
+        self.built.insert(name, index);
+
+        Ok(index)
+    }
+
+    fn build_optional(&mut self, name: &'a Option<String>) -> Result<Option<usize>, NoiseGraphError> {
+        match name {
+            Some(name) => self.build(name).map(Some),
+            None => Ok(None)
+        }
+    }
+
+    fn seed(&self, stream: u64) -> u32 {
+        split_seed(self.world_seed, stream)
+    }
+
+    fn build_node(&mut self, node: &'a NoiseNode) -> Result<SamplerNode, NoiseGraphError> {
+        Ok(match node {
+            NoiseNode::Constant(value) => SamplerNode::Constant(*value),
+            NoiseNode::Gradient { x_start, x_stop, y_start, y_stop, z_start, z_stop } => {
+                SamplerNode::Gradient(Gradient::new()
+                    .set_x_start(*x_start)
+                    .set_x_stop(*x_stop)
+                    .set_y_start(*y_start)
+                    .set_y_stop(*y_stop)
+                    .set_z_start(*z_start)
+                    .set_z_stop(*z_stop))
+            },
+            NoiseNode::Perlin { seed } => SamplerNode::Perlin(Perlin::new().set_seed(self.seed(*seed))),
+            NoiseNode::Fbm(settings) => {
+                let mut fbm = Fbm::new()
+                    .set_seed(self.seed(settings.seed))
+                    .set_octaves(settings.octaves)
+                    .set_frequency(settings.frequency);
+
+                if let Some(lacunarity) = settings.lacunarity {
+                    fbm = fbm.set_lacunarity(lacunarity);
+                }
+
+                if let Some(persistence) = settings.persistence {
+                    fbm = fbm.set_persistence(persistence);
+                }
+
+                SamplerNode::Fbm(fbm)
+            },
+            NoiseNode::Billow(settings) => {
+                let mut billow = Billow::new()
+                    .set_seed(self.seed(settings.seed))
+                    .set_octaves(settings.octaves)
+                    .set_frequency(settings.frequency);
+
+                if let Some(lacunarity) = settings.lacunarity {
+                    billow = billow.set_lacunarity(lacunarity);
+                }
+
+                if let Some(persistence) = settings.persistence {
+                    billow = billow.set_persistence(persistence);
+                }
+
+                SamplerNode::Billow(billow)
+            },
+            NoiseNode::RidgedMulti(settings) => {
+                let mut ridged_multi = RidgedMulti::new()
+                    .set_seed(self.seed(settings.seed))
+                    .set_octaves(settings.octaves)
+                    .set_frequency(settings.frequency);
+
+                if let Some(lacunarity) = settings.lacunarity {
+                    ridged_multi = ridged_multi.set_lacunarity(lacunarity);
+                }
+
+                if let Some(persistence) = settings.persistence {
+                    ridged_multi = ridged_multi.set_persistence(persistence);
+                }
+
+                SamplerNode::RidgedMulti(ridged_multi)
+            },
+            NoiseNode::Clamp { source, lower, upper } => SamplerNode::Clamp {
+                source: self.build(source)?,
+                lower: *lower,
+                upper: *upper
+            },
+            NoiseNode::AutoCorrect { source, lower, upper } => {
+                let source = self.build(source)?;
+                let (min, max) = self.measure(source);
+
+                // A flat source has no range to stretch, so it sits at the bottom of the new one
+                let scale = if max - min > std::f64::EPSILON { (upper - lower) / (max - min) } else { 0.0 };
+
+                SamplerNode::ScaleBias {
+                    source,
+                    scale,
+                    bias: lower - min * scale
+                }
+            },
+            NoiseNode::ScaleBias { source, scale, bias } => SamplerNode::ScaleBias {
+                source: self.build(source)?,
+                scale: *scale,
+                bias: *bias
+            },
+            NoiseNode::ScalePoint { source, x_scale, y_scale, z_scale } => SamplerNode::ScalePoint {
+                source: self.build(source)?,
+                scale: [*x_scale, *y_scale, *z_scale]
+            },
+            NoiseNode::Displace { source, x, y, z } => SamplerNode::Displace {
+                source: self.build(source)?,
+                offsets: [self.build_optional(x)?, self.build_optional(y)?, self.build_optional(z)?]
+            },
+            NoiseNode::Select { outside, inside, control, lower_bound, upper_bound, falloff } => {
+                // Falloff can't be wider than half the gap between the bounds
+                let falloff = falloff.max(0.0).min((upper_bound - lower_bound) / 2.0);
+
+                SamplerNode::Select {
+                    outside: self.build(outside)?,
+                    inside: self.build(inside)?,
+                    control: self.build(control)?,
+                    lower_bound: *lower_bound,
+                    upper_bound: *upper_bound,
+                    falloff
+                }
+            }
+        })
+    }
+
+    /// The lowest and highest values a built node takes over a fixed spread of points. The points
+    /// don't depend on the seed, so a graph always measures the same way for the same world.
+    fn measure(&self, index: usize) -> (f64, f64) {
+        let mut state = 0u64;
+        let mut min = std::f64::MAX;
+        let mut max = std::f64::MIN;
+
+        for _ in 0..AUTO_CORRECT_SAMPLES {
+            let mut coordinate = || {
+                state = state.wrapping_add(1);
+                let unit = split_seed(state, 0) as f64 / std::u32::MAX as f64;
+                (unit * 2.0 - 1.0) * AUTO_CORRECT_EXTENT
+            };
+            let point = [coordinate(), coordinate(), coordinate()];
+            let value = sample(&self.nodes, index, point);
+
+            min = min.min(value);
+            max = max.max(value);
+        }
+
+        (min, max)
+    }
+}
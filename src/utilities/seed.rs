@@ -0,0 +1,54 @@
+/// Derives an independent 32 bit seed for one consumer of a world seed, so that every noise
+/// module gets a different but reproducible seed. Uses the SplitMix64 finalizer, which spreads
+/// nearby inputs across the whole output range.
+pub fn split_seed(world_seed: u64, stream: u64) -> u32 {
+    (mix(world_seed.wrapping_add(stream.wrapping_add(1).wrapping_mul(0x9E37_79B9_7F4A_7C15))) >> 32) as u32
+}
+
+/// Derives a seed for one position of the world, such as a region that places its own features
+pub fn position_seed(world_seed: u64, stream: u64, x: i32, y: i32, z: i32) -> u64 {
+    let mut seed = world_seed.wrapping_add(stream.wrapping_add(1).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+
+    for coordinate in [x, y, z].iter() {
+        seed = mix(seed ^ *coordinate as u32 as u64).wrapping_add(0x9E37_79B9_7F4A_7C15);
+    }
+
+    mix(seed)
+}
+
+/// A small, fast random number generator for generating the world, which always produces the
+/// same numbers from the same seed on every platform
+#[derive(Clone, Debug)]
+pub struct SeedRandom {
+    state: u64
+}
+
+impl SeedRandom {
+    pub fn new(seed: u64) -> SeedRandom {
+        SeedRandom {
+            state: seed
+        }
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        self.state = self.state.wrapping_add(1);
+        split_seed(self.state, 0)
+    }
+
+    /// A number in the range `0.0..1.0`
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1u32 << 24) as f32
+    }
+
+    /// A number in the range `min..max`
+    pub fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+}
+
+/// The SplitMix64 finalizer
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}